@@ -0,0 +1,143 @@
+// sawfish-client -- client library to communicate with Sawfish window manager
+// © 2025 by Michał Nazarewicz <mina86@mina86.com>
+
+//! Builders of forms for commonly used Sawfish commands.
+//!
+//! Arguments are strongly typed which prevents mistakes, such as swapping
+//! arguments, which building forms with [`format!`] invites.
+
+/// Horizontal viewport coordinate; see [`set_screen_viewport`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ViewportX(pub u32);
+
+/// Vertical viewport coordinate; see [`set_screen_viewport`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ViewportY(pub u32);
+
+/// X11 id of a window managed by Sawfish.
+///
+/// The id is what `window-id` function returns and can be parsed from its
+/// response.  It formats as a decimal integer.
+///
+/// # Example
+///
+/// ```
+/// use sawfish_client::commands::WindowId;
+///
+/// let id: WindowId = "41943043".parse().unwrap();
+/// assert_eq!(WindowId(0x2800003), id);
+/// assert_eq!("41943043", id.to_string());
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct WindowId(pub u32);
+
+impl core::fmt::Display for WindowId {
+    fn fmt(&self, fmtr: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        self.0.fmt(fmtr)
+    }
+}
+
+impl core::str::FromStr for WindowId {
+    type Err = core::num::ParseIntError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        value.trim().parse().map(Self)
+    }
+}
+
+/// Returns form evaluating to the window with given id.
+fn window(id: WindowId) -> String {
+    crate::sexp::call("get-window-by-id", [id.to_string()])
+}
+
+/// Returns form moving window to given position.
+///
+/// # Example
+///
+/// ```
+/// use sawfish_client::commands::{WindowId, move_window};
+///
+/// assert_eq!(
+///     "(move-window-to (get-window-by-id 42) 10 -20)",
+///     move_window(WindowId(42), 10, -20)
+/// );
+/// ```
+pub fn move_window(id: WindowId, x: i32, y: i32) -> String {
+    crate::sexp::call("move-window-to", [
+        window(id),
+        x.to_string(),
+        y.to_string(),
+    ])
+}
+
+/// Returns form asking window to close.
+///
+/// # Example
+///
+/// ```
+/// use sawfish_client::commands::{WindowId, delete_window};
+///
+/// assert_eq!(
+///     "(delete-window (get-window-by-id 42))",
+///     delete_window(WindowId(42))
+/// );
+/// ```
+pub fn delete_window(id: WindowId) -> String {
+    crate::sexp::call("delete-window", [window(id)])
+}
+
+/// Returns form switching the screen to viewport at given coordinates.
+///
+/// # Example
+///
+/// ```
+/// use sawfish_client::commands::{ViewportX, ViewportY, set_screen_viewport};
+///
+/// let form = set_screen_viewport(ViewportX(1), ViewportY(2));
+/// assert_eq!("(set-screen-viewport 1 2)", form);
+/// ```
+///
+/// Passing coordinates in wrong order doesn’t compile:
+///
+/// ```compile_fail
+/// use sawfish_client::commands::{ViewportX, ViewportY, set_screen_viewport};
+///
+/// set_screen_viewport(ViewportY(2), ViewportX(1));
+/// ```
+pub fn set_screen_viewport(x: ViewportX, y: ViewportY) -> String {
+    crate::sexp::call("set-screen-viewport", [x.0.to_string(), y.0.to_string()])
+}
+
+
+#[test]
+fn test_window_id() {
+    for id in [0, 42, 0x2800003, u32::MAX] {
+        let id = WindowId(id);
+        assert_eq!(Ok(id), id.to_string().parse());
+    }
+    assert_eq!(Ok(WindowId(42)), " 42\n".parse());
+    assert!("".parse::<WindowId>().is_err());
+    assert!("-1".parse::<WindowId>().is_err());
+    assert!("#<window 2800003>".parse::<WindowId>().is_err());
+
+    assert_eq!(
+        "(move-window-to (get-window-by-id 42) 0 0)",
+        move_window(WindowId(42), 0, 0)
+    );
+    assert_eq!(
+        "(delete-window (get-window-by-id 7))",
+        delete_window(WindowId(7))
+    );
+}
+
+#[test]
+fn test_set_screen_viewport() {
+    assert_eq!(
+        "(set-screen-viewport 0 0)",
+        set_screen_viewport(ViewportX(0), ViewportY(0))
+    );
+    assert_eq!(
+        "(set-screen-viewport 4294967295 3)",
+        set_screen_viewport(ViewportX(u32::MAX), ViewportY(3))
+    );
+}
@@ -18,16 +18,105 @@ pub enum ConnError {
     /// An I/O error during establishing of the connection (e.g. Unix socket
     /// does not exist or user lacks permissions to access it).
     Io(std::path::PathBuf, std::io::Error),
+    /// Failed to create Tokio runtime for [`crate::BlockingClient`].
+    #[cfg(feature = "blocking")]
+    #[from(ignore)]
+    Runtime(std::io::Error),
     /// Invalid X11 display screen number.
     #[cfg(feature = "experimental-xcb")]
     BadScreen(i32),
     /// No Sawfish server found on display.
+    ///
+    /// The atom naming the root window property which holds Sawfish’s request
+    /// window doesn’t exist which means Sawfish has never run on the display.
     #[cfg(feature = "experimental-xcb")]
     ServerNotFound,
+    /// Root window property which should hold Sawfish’s request window is
+    /// missing or malformed.
+    ///
+    /// This may happen if Sawfish has exited or if another window manager
+    /// set the property.  A missing property has type `ATOM_NONE` and zero
+    /// format and length.
+    #[cfg(feature = "experimental-xcb")]
+    BadServerProperty {
+        /// The actual type of the property (an atom), see
+        /// [`x::GetPropertyReply::type`].
+        typ: x::Atom,
+        /// The actual format of the property, see
+        /// [`x::GetPropertyReply::format`].
+        format: u8,
+        /// Length of the property value in units of `format`, see
+        /// [`x::GetPropertyReply::length`].
+        length: u32,
+    },
     /// An X11 error during establishing of the connection.
     #[cfg(feature = "experimental-xcb")]
     #[from(xcb::Error, xcb::ConnError, xcb::ProtocolError)]
     X11(xcb::Error),
+    /// Both connecting via Unix socket and via X11 failed.
+    ///
+    /// The first error is from the Unix socket connection attempt and the
+    /// second is from the X11 connection attempt.
+    #[cfg(feature = "experimental-xcb")]
+    #[from(ignore)]
+    Both(Box<ConnError>, Box<ConnError>),
+}
+
+/// Kind of a [`ConnError`] without the data it carries; see
+/// [`ConnError::kind`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ConnErrorKind {
+    /// See [`ConnError::NoDisplay`].
+    NoDisplay,
+    /// See [`ConnError::NoLogname`].
+    NoLogname,
+    /// See [`ConnError::Io`].
+    Io,
+    /// See [`ConnError::Runtime`].
+    #[cfg(feature = "blocking")]
+    Runtime,
+    /// See [`ConnError::BadScreen`].
+    #[cfg(feature = "experimental-xcb")]
+    BadScreen,
+    /// See [`ConnError::ServerNotFound`].
+    #[cfg(feature = "experimental-xcb")]
+    ServerNotFound,
+    /// See [`ConnError::BadServerProperty`].
+    #[cfg(feature = "experimental-xcb")]
+    BadServerProperty,
+    /// See [`ConnError::X11`].
+    #[cfg(feature = "experimental-xcb")]
+    X11,
+    /// See [`ConnError::Both`].
+    #[cfg(feature = "experimental-xcb")]
+    Both,
+}
+
+impl ConnError {
+    /// Returns kind of the error.
+    ///
+    /// The errors can’t be cloned since some hold [`std::io::Error`].  The kind
+    /// can be stored or compared instead, e.g. to count failures by kind.
+    pub fn kind(&self) -> ConnErrorKind {
+        match self {
+            Self::NoDisplay => ConnErrorKind::NoDisplay,
+            Self::NoLogname => ConnErrorKind::NoLogname,
+            Self::Io(..) => ConnErrorKind::Io,
+            #[cfg(feature = "blocking")]
+            Self::Runtime(_) => ConnErrorKind::Runtime,
+            #[cfg(feature = "experimental-xcb")]
+            Self::BadScreen(_) => ConnErrorKind::BadScreen,
+            #[cfg(feature = "experimental-xcb")]
+            Self::ServerNotFound => ConnErrorKind::ServerNotFound,
+            #[cfg(feature = "experimental-xcb")]
+            Self::BadServerProperty { .. } => ConnErrorKind::BadServerProperty,
+            #[cfg(feature = "experimental-xcb")]
+            Self::X11(_) => ConnErrorKind::X11,
+            #[cfg(feature = "experimental-xcb")]
+            Self::Both(..) => ConnErrorKind::Both,
+        }
+    }
 }
 
 impl core::fmt::Display for ConnError {
@@ -46,8 +135,26 @@ impl core::fmt::Display for ConnError {
                 "No Sawfish server found on X11 screen".fmt(fmtr)
             }
             #[cfg(feature = "experimental-xcb")]
+            Self::BadServerProperty { typ, format, length } => {
+                use xcb::Xid;
+                write!(
+                    fmtr,
+                    "Invalid Sawfish request window property (typ:{}, \
+                     format:{}, length:{})",
+                    typ.resource_id(),
+                    format,
+                    length
+                )
+            }
+            #[cfg(feature = "experimental-xcb")]
             Self::X11(err) => err.fmt(fmtr),
+            #[cfg(feature = "experimental-xcb")]
+            Self::Both(unix, x11) => {
+                write!(fmtr, "Unix socket: {unix}; X11: {x11}")
+            }
             Self::Io(path, err) => write!(fmtr, "{}: {}", path.display(), err),
+            #[cfg(feature = "blocking")]
+            Self::Runtime(err) => write!(fmtr, "Creating runtime: {err}"),
         }
     }
 }
@@ -63,9 +170,53 @@ pub enum EvalError {
     /// data, what [`crate::Client::eval`] returns in `Ok` variant, may be empty
     /// and that’s not considered an error.
     NoResponse,
-    /// Response too large to handle.  This can only happen on systems where
-    /// `usize` is smaller than 64-bit.
-    ResponseTooLarge(std::ffi::c_ulong),
+    /// The server closed the connection.
+    ///
+    /// This happens when the Sawfish server exits (e.g. is restarted).  The
+    /// client should reconnect.
+    Disconnected,
+    /// Malformed response from the server, i.e. invalid status byte or
+    /// implausibly large length.
+    ///
+    /// Most likely requests and responses got out of sync, e.g. because
+    /// a response to an earlier request hasn’t been read.  The connection
+    /// should be closed.
+    Desync,
+    /// The connection failed after part of the request has been sent.
+    ///
+    /// The server may have received part of the form.  The connection is in
+    /// an unknown state and the client should reconnect rather than retry
+    /// the request.  Holds number of bytes of the request (including the
+    /// header) which have been written and the error which interrupted the
+    /// write.
+    #[from(ignore)]
+    PartialRequest(usize, std::io::Error),
+    /// Form is empty.  Returned before anything is sent to the server since
+    /// the server wouldn’t respond to an empty request.
+    EmptyForm,
+    /// Form too large to send.  Returned before anything is sent to the
+    /// server; see [`crate::Client::set_max_request`].
+    RequestTooLarge(usize),
+    /// Response too large to handle; see [`crate::Client::set_max_response`].
+    ///
+    /// Holds the declared length of the response data and, if available, its
+    /// first few bytes which can help diagnose what the server was sending.
+    /// The prefix is captured with Unix socket connections only.
+    #[from(ignore)]
+    ResponseTooLarge(std::ffi::c_ulong, Option<Vec<u8>>),
+    /// The server responded but not with what was expected, e.g. to
+    /// [`crate::Client::ping`].
+    ///
+    /// Holds the response.  Unlike [`EvalError::Desync`], the response was
+    /// well-formed and the connection remains usable.
+    #[from(ignore)]
+    UnexpectedResponse(crate::EvalResponse),
+    /// The deadline passed before response has been received; see
+    /// [`crate::Client::eval_deadline`].
+    ///
+    /// The response may still arrive later so the connection should be
+    /// closed.
+    Timeout,
     /// An I/O error during communication with the Sawfish server.
     #[from(std::io::Error, std::io::ErrorKind)]
     Io(std::io::Error),
@@ -89,13 +240,68 @@ pub enum EvalError {
     X11(xcb::Error),
 }
 
+/// Kind of an [`EvalError`] without the data it carries; see
+/// [`EvalError::kind`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum EvalErrorKind {
+    /// See [`EvalError::NoResponse`].
+    NoResponse,
+    /// See [`EvalError::Disconnected`].
+    Disconnected,
+    /// See [`EvalError::Desync`].
+    Desync,
+    /// See [`EvalError::PartialRequest`].
+    PartialRequest,
+    /// See [`EvalError::EmptyForm`].
+    EmptyForm,
+    /// See [`EvalError::RequestTooLarge`].
+    RequestTooLarge,
+    /// See [`EvalError::ResponseTooLarge`].
+    ResponseTooLarge,
+    /// See [`EvalError::UnexpectedResponse`].
+    UnexpectedResponse,
+    /// See [`EvalError::Timeout`].
+    Timeout,
+    /// See [`EvalError::Io`].
+    Io,
+    /// See [`EvalError::BadResponse`].
+    #[cfg(feature = "experimental-xcb")]
+    BadResponse,
+    /// See [`EvalError::X11`].
+    #[cfg(feature = "experimental-xcb")]
+    X11,
+}
+
 impl core::fmt::Display for EvalError {
     fn fmt(&self, fmtr: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self {
             Self::NoResponse => "No response to non-async request".fmt(fmtr),
-            Self::ResponseTooLarge(len) => {
+            Self::Disconnected => "Server closed the connection".fmt(fmtr),
+            Self::Desync => "Malformed response; requests and responses out \
+                             of sync"
+                .fmt(fmtr),
+            Self::PartialRequest(len, err) => {
+                write!(fmtr, "Request interrupted after {len} bytes: {err}")
+            }
+            Self::EmptyForm => "Empty form".fmt(fmtr),
+            Self::RequestTooLarge(len) => {
+                write!(fmtr, "Form of {len} bytes too large")
+            }
+            Self::ResponseTooLarge(len, _) => {
                 write!(fmtr, "Response of {len} bytes too large")
             }
+            Self::UnexpectedResponse(Ok(data)) => write!(
+                fmtr,
+                "Unexpected response: {}",
+                String::from_utf8_lossy(data)
+            ),
+            Self::UnexpectedResponse(Err(data)) => write!(
+                fmtr,
+                "Unexpected error response: {}",
+                String::from_utf8_lossy(data)
+            ),
+            Self::Timeout => "Timed out waiting for response".fmt(fmtr),
             Self::Io(err) => err.fmt(fmtr),
             #[cfg(feature = "experimental-xcb")]
             Self::BadResponse { window, atom, typ, format } => {
@@ -116,6 +322,175 @@ impl core::fmt::Display for EvalError {
     }
 }
 
+impl EvalError {
+    /// Returns kind of the error.
+    ///
+    /// Like [`ConnError::kind`], this allows storing and comparing the error
+    /// without cloning it.  For example, a caller may retry on
+    /// [`EvalErrorKind::Disconnected`] after reconnecting.
+    pub fn kind(&self) -> EvalErrorKind {
+        match self {
+            Self::NoResponse => EvalErrorKind::NoResponse,
+            Self::Disconnected => EvalErrorKind::Disconnected,
+            Self::Desync => EvalErrorKind::Desync,
+            Self::PartialRequest(..) => EvalErrorKind::PartialRequest,
+            Self::EmptyForm => EvalErrorKind::EmptyForm,
+            Self::RequestTooLarge(_) => EvalErrorKind::RequestTooLarge,
+            Self::ResponseTooLarge(..) => EvalErrorKind::ResponseTooLarge,
+            Self::UnexpectedResponse(_) => EvalErrorKind::UnexpectedResponse,
+            Self::Timeout => EvalErrorKind::Timeout,
+            Self::Io(_) => EvalErrorKind::Io,
+            #[cfg(feature = "experimental-xcb")]
+            Self::BadResponse { .. } => EvalErrorKind::BadResponse,
+            #[cfg(feature = "experimental-xcb")]
+            Self::X11(_) => EvalErrorKind::X11,
+        }
+    }
+
+    /// Converts an I/O error which occurred when sending a request.
+    ///
+    /// Errors indicating the server closed the connection are mapped to
+    /// [`EvalError::Disconnected`].
+    pub(crate) fn from_write(err: std::io::Error) -> Self {
+        use std::io::ErrorKind;
+
+        match err.kind() {
+            ErrorKind::BrokenPipe | ErrorKind::ConnectionReset => {
+                Self::Disconnected
+            }
+            _ => Self::Io(err),
+        }
+    }
+}
+
+
+impl From<EvalError> for std::io::Error {
+    /// Converts the error into an I/O error.
+    ///
+    /// [`EvalError::Io`] is converted into the underlying I/O error while
+    /// [`EvalError::PartialRequest`] is wrapped in an I/O error of the same
+    /// kind as the error which interrupted the request.  Other variants are
+    /// wrapped in an I/O error whose kind is `UnexpectedEof` for missing
+    /// response or closed connection, `InvalidInput` for empty or too large
+    /// form, `InvalidData` for malformed, unexpected or too large response,
+    /// `TimedOut` for passed deadline and `Other` for X11 errors.
+    fn from(err: EvalError) -> Self {
+        use std::io::ErrorKind;
+
+        let kind = match err {
+            EvalError::Io(err) => return err,
+            EvalError::PartialRequest(_, ref err) => err.kind(),
+            EvalError::NoResponse | EvalError::Disconnected => {
+                ErrorKind::UnexpectedEof
+            }
+            EvalError::EmptyForm | EvalError::RequestTooLarge(_) => {
+                ErrorKind::InvalidInput
+            }
+            EvalError::Desync |
+            EvalError::UnexpectedResponse(_) |
+            EvalError::ResponseTooLarge(..) => ErrorKind::InvalidData,
+            EvalError::Timeout => ErrorKind::TimedOut,
+            #[cfg(feature = "experimental-xcb")]
+            EvalError::BadResponse { .. } | EvalError::X11(_) => {
+                ErrorKind::Other
+            }
+        };
+        Self::new(kind, err)
+    }
+}
+
 
 impl std::error::Error for ConnError {}
 impl std::error::Error for EvalError {}
+
+
+#[test]
+fn test_io_error_from_eval_error() {
+    use std::io::ErrorKind;
+
+    #[track_caller]
+    fn check(want: ErrorKind, err: EvalError) {
+        assert_eq!(want, std::io::Error::from(err).kind());
+    }
+
+    check(ErrorKind::TimedOut, ErrorKind::TimedOut.into());
+    check(ErrorKind::UnexpectedEof, EvalError::NoResponse);
+    check(ErrorKind::UnexpectedEof, EvalError::Disconnected);
+    check(
+        ErrorKind::BrokenPipe,
+        EvalError::PartialRequest(5, ErrorKind::BrokenPipe.into()),
+    );
+    check(ErrorKind::InvalidInput, EvalError::EmptyForm);
+    check(ErrorKind::InvalidInput, EvalError::RequestTooLarge(42));
+    check(ErrorKind::InvalidData, EvalError::Desync);
+    check(ErrorKind::InvalidData, EvalError::ResponseTooLarge(42, None));
+    check(ErrorKind::InvalidData, EvalError::UnexpectedResponse(Ok(vec![])));
+    check(ErrorKind::TimedOut, EvalError::Timeout);
+    #[cfg(feature = "experimental-xcb")]
+    check(
+        ErrorKind::Other,
+        EvalError::X11(xcb::Error::Connection(xcb::ConnError::Connection)),
+    );
+
+    let err = std::io::Error::from(EvalError::NoResponse);
+    assert!(err.into_inner().unwrap().is::<EvalError>());
+}
+
+#[test]
+fn test_conn_error_kind() {
+    use std::io::ErrorKind;
+
+    assert_eq!(ConnErrorKind::NoDisplay, ConnError::NoDisplay.kind());
+    assert_eq!(ConnErrorKind::NoLogname, ConnError::NoLogname.kind());
+    let err = ConnError::Io("/tmp".into(), ErrorKind::NotFound.into());
+    assert_eq!(ConnErrorKind::Io, err.kind());
+    #[cfg(feature = "experimental-xcb")]
+    {
+        assert_eq!(ConnErrorKind::BadScreen, ConnError::BadScreen(1).kind());
+        let both =
+            ConnError::Both(Box::new(err), Box::new(ConnError::ServerNotFound));
+        assert_eq!(ConnErrorKind::Both, both.kind());
+    }
+}
+
+#[test]
+fn test_eval_error_kind() {
+    use std::io::ErrorKind;
+
+    #[track_caller]
+    fn check(want: EvalErrorKind, err: EvalError) {
+        assert_eq!(want, err.kind());
+    }
+
+    check(EvalErrorKind::NoResponse, EvalError::NoResponse);
+    check(EvalErrorKind::Disconnected, EvalError::Disconnected);
+    check(EvalErrorKind::Desync, EvalError::Desync);
+    check(
+        EvalErrorKind::PartialRequest,
+        EvalError::PartialRequest(5, ErrorKind::BrokenPipe.into()),
+    );
+    check(EvalErrorKind::EmptyForm, EvalError::EmptyForm);
+    check(EvalErrorKind::RequestTooLarge, EvalError::RequestTooLarge(42));
+    check(
+        EvalErrorKind::ResponseTooLarge,
+        EvalError::ResponseTooLarge(42, Some(b"prefix".to_vec())),
+    );
+    check(
+        EvalErrorKind::UnexpectedResponse,
+        EvalError::UnexpectedResponse(Err(b"oops".to_vec())),
+    );
+    check(EvalErrorKind::Timeout, EvalError::Timeout);
+    check(EvalErrorKind::Io, ErrorKind::TimedOut.into());
+    #[cfg(feature = "experimental-xcb")]
+    check(
+        EvalErrorKind::X11,
+        EvalError::X11(xcb::Error::Connection(xcb::ConnError::Connection)),
+    );
+
+    // Kinds are Copy and can be used as map keys.
+    let mut counts = std::collections::HashMap::new();
+    for err in [EvalError::Timeout, EvalError::Desync, EvalError::Timeout] {
+        *counts.entry(err.kind()).or_insert(0) += 1;
+    }
+    assert_eq!(Some(&2), counts.get(&EvalErrorKind::Timeout));
+}
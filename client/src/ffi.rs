@@ -0,0 +1,251 @@
+// sawfish-client -- client library to communicate with Sawfish window manager
+// © 2025 by Michał Nazarewicz <mina86@mina86.com>
+
+//! C API to the library.
+//!
+//! The module exports the following functions with C linkage:
+//!
+//! ```c
+//! #include <stddef.h>
+//! #include <stdint.h>
+//!
+//! typedef struct sawfish_client sawfish_client;
+//!
+//! sawfish_client *sawfish_open(const char *display);
+//! int sawfish_eval(sawfish_client *client,
+//!                  const uint8_t *form, size_t len,
+//!                  uint8_t **out, size_t *out_len, int *is_err);
+//! void sawfish_free(uint8_t *data, size_t len);
+//! void sawfish_close(sawfish_client *client);
+//! ```
+//!
+//! `sawfish_open` returns `NULL` on failure.  `sawfish_eval` returns one of
+//! the `SAWFISH_*` codes defined in this module.  On success, the response is
+//! stored in `*out` and `*out_len` and `*is_err` is set to non-zero if the
+//! evaluation failed on the server side.  The response must be freed with
+//! `sawfish_free`.  A connection must be closed with `sawfish_close`.
+//!
+//! None of the functions unwind across the FFI boundary.  Panics are caught
+//! and reported as errors.
+//!
+//! To build a C library, use `cargo rustc`, e.g.:
+//!
+//! ```shell
+//! cargo rustc -p sawfish-client --release --features capi \
+//!     --crate-type cdylib
+//! ```
+
+use core::ffi::{c_char, c_int};
+
+use crate::Client;
+
+/// The form has been evaluated; response is returned in output arguments.
+pub const SAWFISH_OK: c_int = 0;
+/// Error communicating with the Sawfish server.
+pub const SAWFISH_ERR_EVAL: c_int = -1;
+/// Invalid argument (e.g. a `NULL` pointer) passed to the function.
+pub const SAWFISH_ERR_ARG: c_int = -2;
+/// Internal error (a panic) in the library.
+pub const SAWFISH_ERR_PANIC: c_int = -3;
+
+/// Opens a connection to the Sawfish server.
+///
+/// If `display` is `NULL`, uses the `DISPLAY` environment variable.  Returns
+/// `NULL` on error.
+///
+/// # Safety
+///
+/// `display` must be `NULL` or point at a NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sawfish_open(display: *const c_char) -> *mut Client {
+    let display = if display.is_null() {
+        None
+    } else {
+        // SAFETY: Caller promises display is a NUL-terminated string.
+        match unsafe { core::ffi::CStr::from_ptr(display) }.to_str() {
+            Ok(display) => Some(display),
+            Err(_) => return core::ptr::null_mut(),
+        }
+    };
+    std::panic::catch_unwind(|| Client::open(display))
+        .ok()
+        .and_then(Result::ok)
+        .map_or(core::ptr::null_mut(), |client| Box::into_raw(Box::new(client)))
+}
+
+/// Sends a form to the Sawfish server for evaluation and waits for a reply.
+///
+/// On success, returns `SAWFISH_OK` and stores the response in `*out` and
+/// `*out_len` and sets `*is_err` to zero if the form was evaluated
+/// successfully or to one if evaluation failed.  The response must be freed
+/// with [`sawfish_free`].  On failure, returns a negative error code and
+/// leaves output arguments unmodified.
+///
+/// # Safety
+///
+/// `client` must be a pointer returned by [`sawfish_open`] which hasn’t been
+/// closed yet.  `form` must point at `len` readable bytes (it may be `NULL`
+/// if `len` is zero).  `out`, `out_len` and `is_err` must be valid for
+/// writes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sawfish_eval(
+    client: *mut Client,
+    form: *const u8,
+    len: usize,
+    out: *mut *mut u8,
+    out_len: *mut usize,
+    is_err: *mut c_int,
+) -> c_int {
+    if client.is_null() ||
+        (form.is_null() && len != 0) ||
+        out.is_null() ||
+        out_len.is_null() ||
+        is_err.is_null()
+    {
+        return SAWFISH_ERR_ARG;
+    }
+    // SAFETY: Caller promises client is a valid pointer.
+    let client = unsafe { &mut *client };
+    let form = if len == 0 {
+        &[][..]
+    } else {
+        // SAFETY: Caller promises form points at len bytes.
+        unsafe { core::slice::from_raw_parts(form, len) }
+    };
+    let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        client.eval(form)
+    }));
+    let (err, data) = match res {
+        Ok(Ok(Ok(data))) => (0, data),
+        Ok(Ok(Err(data))) => (1, data),
+        Ok(Err(_)) => return SAWFISH_ERR_EVAL,
+        Err(_) => return SAWFISH_ERR_PANIC,
+    };
+    let data = Box::into_raw(data.into_boxed_slice());
+    // SAFETY: Caller promises the pointers are valid for writes.
+    unsafe {
+        *out_len = data.len();
+        *out = data.cast::<u8>();
+        *is_err = err;
+    }
+    SAWFISH_OK
+}
+
+/// Frees response returned by [`sawfish_eval`].
+///
+/// Does nothing if `data` is `NULL`.
+///
+/// # Safety
+///
+/// `data` and `len` must be values returned by [`sawfish_eval`] which haven’t
+/// been freed yet.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sawfish_free(data: *mut u8, len: usize) {
+    if !data.is_null() {
+        let data = core::ptr::slice_from_raw_parts_mut(data, len);
+        // SAFETY: Caller promises data and len come from sawfish_eval which
+        // obtained them from a boxed slice.
+        core::mem::drop(unsafe { Box::from_raw(data) });
+    }
+}
+
+/// Closes connection to the Sawfish server.
+///
+/// Does nothing if `client` is `NULL`.
+///
+/// # Safety
+///
+/// `client` must be `NULL` or a pointer returned by [`sawfish_open`] which
+/// hasn’t been closed yet.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn sawfish_close(client: *mut Client) {
+    if !client.is_null() {
+        // SAFETY: Caller promises client comes from sawfish_open.
+        let client = unsafe { Box::from_raw(client) };
+        let _ = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            core::mem::drop(client)
+        }));
+    }
+}
+
+
+#[test]
+fn test_ffi() {
+    use std::io::{Read, Write};
+    use std::os::unix::net::UnixStream;
+
+    let (client, mut server) = UnixStream::pair().unwrap();
+    let server = std::thread::spawn(move || {
+        for status in [1u8, 0] {
+            let mut buf = [0u8; crate::protocol::REQUEST_HEADER_LEN + 6];
+            server.read_exact(&mut buf).unwrap();
+            assert_eq!(crate::protocol::render_request(b"(form)", false), buf);
+            server.write_all(&4u64.to_ne_bytes()).unwrap();
+            server.write_all(&[status, b'r', b'e', b's']).unwrap();
+        }
+    });
+
+    let client =
+        crate::Client(crate::Inner::Unix(crate::unix::Client::new(client)));
+    let client = Box::into_raw(Box::new(client));
+
+    let mut out = core::ptr::null_mut();
+    let mut out_len = 0;
+    let mut is_err = -1;
+    for want_err in [0, 1] {
+        // SAFETY: All pointers are valid.
+        let ret = unsafe {
+            sawfish_eval(
+                client,
+                b"(form)".as_ptr(),
+                6,
+                &mut out,
+                &mut out_len,
+                &mut is_err,
+            )
+        };
+        assert_eq!(SAWFISH_OK, ret);
+        assert_eq!(want_err, is_err);
+        // SAFETY: out and out_len were set by sawfish_eval.
+        assert_eq!(b"res", unsafe {
+            core::slice::from_raw_parts(out, out_len)
+        });
+        // SAFETY: out and out_len were set by sawfish_eval.
+        unsafe { sawfish_free(out, out_len) };
+    }
+    server.join().unwrap();
+
+    // SAFETY: Passing NULL pointers is safe.
+    let ret = unsafe {
+        sawfish_eval(
+            core::ptr::null_mut(),
+            b"(form)".as_ptr(),
+            6,
+            &mut out,
+            &mut out_len,
+            &mut is_err,
+        )
+    };
+    assert_eq!(SAWFISH_ERR_ARG, ret);
+
+    // The server is gone so communication fails.
+    // SAFETY: All pointers are valid.
+    let ret = unsafe {
+        sawfish_eval(
+            client,
+            b"t".as_ptr(),
+            1,
+            &mut out,
+            &mut out_len,
+            &mut is_err,
+        )
+    };
+    assert_eq!(SAWFISH_ERR_EVAL, ret);
+
+    // SAFETY: client comes from a Box and sawfish_close accepts NULL.
+    unsafe {
+        sawfish_close(client);
+        sawfish_close(core::ptr::null_mut());
+        sawfish_free(core::ptr::null_mut(), 0);
+    }
+}
@@ -22,12 +22,33 @@ use std::borrow::Cow;
 #[cfg(feature = "async")]
 use futures_util::io::{AsyncRead, AsyncWrite};
 
+pub mod commands;
 mod error;
+#[cfg(feature = "capi")]
+pub mod ffi;
+#[cfg(all(test, feature = "async"))]
+mod mock;
+#[cfg(feature = "async")]
+mod pool;
+pub mod protocol;
+#[cfg(feature = "tower")]
+mod service;
+pub mod sexp;
 mod unix;
 #[cfg(feature = "experimental-xcb")]
 mod x11;
 
-pub use error::{ConnError, EvalError};
+pub use error::{ConnError, ConnErrorKind, EvalError, EvalErrorKind};
+#[cfg(feature = "async")]
+pub use pool::{BufferPool, PooledBuffer, PooledResponse};
+#[cfg(feature = "tower")]
+pub use service::EvalService;
+
+/// Default maximum length of response data; see [`Client::set_max_response`].
+const DEFAULT_MAX_RESPONSE: usize = 256 << 20;
+
+/// Default maximum length of a form; see [`Client::set_max_request`].
+const DEFAULT_MAX_REQUEST: usize = 256 << 20;
 
 /// A connection to the Sawfish window manager.
 pub struct Client(Inner);
@@ -38,8 +59,250 @@ pub struct Client(Inner);
 /// the value the form evaluated to) is represented by the `Ok` variant.  If the
 /// form failed to evaluated (most likely due to syntax error), the error
 /// message is represented by the `Err` variant.
+///
+/// The data is passed through as sent by the server without any decoding.
+/// Note that with X11 connection the server may respond with a `STRING`
+/// property, whose encoding is Latin-1, or a `UTF8_STRING` property.
 pub type EvalResponse = Result<Vec<u8>, Vec<u8>>;
 
+/// A Lisp form to be sent to the Sawfish server.
+///
+/// Methods sending forms accept anything which can be viewed as a byte slice
+/// so using this type isn’t necessary.  However, it documents that the bytes
+/// are Lisp source and offers convenient ways of constructing forms.
+///
+/// # Example
+///
+/// ```no_run
+/// use sawfish_client::{Form, sexp};
+///
+/// let mut client = sawfish_client::Client::open(None).unwrap();
+/// let msg = sexp::quote_string("Hello, world!");
+/// client.eval(Form::call("display-message", [msg])).unwrap();
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Form<'a>(pub Cow<'a, [u8]>);
+
+impl<'a> Form<'a> {
+    /// Constructs a form from Lisp source.
+    pub fn new(form: &'a str) -> Self { Self(Cow::Borrowed(form.as_bytes())) }
+
+    /// Constructs a form from Lisp source given as bytes.
+    pub fn from_bytes(form: &'a [u8]) -> Self { Self(Cow::Borrowed(form)) }
+
+    /// Constructs a function call form; see [`sexp::call`].
+    pub fn call(
+        func: &str,
+        args: impl IntoIterator<Item = impl AsRef<str>>,
+    ) -> Form<'static> {
+        Form(Cow::Owned(sexp::call(func, args).into_bytes()))
+    }
+
+    /// Returns the form as bytes.
+    pub fn as_bytes(&self) -> &[u8] { &self.0 }
+}
+
+impl AsRef<[u8]> for Form<'_> {
+    fn as_ref(&self) -> &[u8] { &self.0 }
+}
+
+impl<'a> From<&'a str> for Form<'a> {
+    fn from(form: &'a str) -> Self { Self::new(form) }
+}
+
+impl<'a> From<&'a [u8]> for Form<'a> {
+    fn from(form: &'a [u8]) -> Self { Self::from_bytes(form) }
+}
+
+impl From<String> for Form<'static> {
+    fn from(form: String) -> Self { Self(Cow::Owned(form.into_bytes())) }
+}
+
+/// Takes ownership of the vector without copying it.
+impl From<Vec<u8>> for Form<'static> {
+    fn from(form: Vec<u8>) -> Self { Self(Cow::Owned(form)) }
+}
+
+impl<'a> From<Cow<'a, [u8]>> for Form<'a> {
+    fn from(form: Cow<'a, [u8]>) -> Self { Self(form) }
+}
+
+/// Helper methods for [`EvalResponse`].
+///
+/// Since [`EvalResponse`] is a type alias for [`Result`], helper methods are
+/// provided through an extension trait.
+///
+/// # Example
+///
+/// ```no_run
+/// use sawfish_client::EvalResponseExt;
+///
+/// let mut client = sawfish_client::Client::open(None).unwrap();
+/// match client.eval("(system-name)").unwrap().into_result_str() {
+///     Ok(name) => println!("System name: {name}"),
+///     Err(msg) => println!("Error evaluating form: {msg}"),
+/// }
+/// ```
+pub trait EvalResponseExt {
+    /// Returns whether the form has been evaluated successfully.
+    fn is_success(&self) -> bool;
+
+    /// Returns response data lossily decoded as UTF-8 if the form has been
+    /// evaluated successfully.
+    fn success_str(&self) -> Option<Cow<'_, str>>;
+
+    /// Returns response data lossily decoded as UTF-8 if evaluation of the form
+    /// failed.
+    fn error_str(&self) -> Option<Cow<'_, str>>;
+
+    /// Lossily decodes response data as UTF-8 preserving success status.
+    fn into_result_str(self) -> Result<String, String>;
+
+    /// Returns response data or, if evaluation failed, the server error.
+    ///
+    /// The error form is parsed with [`sexp::parse_error`].  If it can’t be
+    /// parsed, the error kind is `error` and the message is the entire
+    /// response lossily decoded as UTF-8.  Since [`sexp::ServerError`]
+    /// implements [`std::error::Error`], this allows treating evaluation
+    /// failures as hard errors with the `?` operator.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use sawfish_client::EvalResponseExt;
+    ///
+    /// fn system_name() -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    ///     let mut client = sawfish_client::Client::open(None)?;
+    ///     Ok(client.eval("(system-name)")?.or_server_err()?)
+    /// }
+    /// ```
+    fn or_server_err(self) -> Result<Vec<u8>, sexp::ServerError>;
+
+    /// Returns whether response data looks like an error form.
+    ///
+    /// Checks the data regardless of the evaluation status so that errors
+    /// returned as successful values (so called soft errors) are detected as
+    /// well; see [`sexp::is_error_form`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use sawfish_client::{EvalResponse, EvalResponseExt};
+    ///
+    /// let res: EvalResponse = Ok(br#"(error "Bad argument" 42)"#.to_vec());
+    /// assert!(res.is_success());
+    /// assert!(res.looks_like_error());
+    /// ```
+    fn looks_like_error(&self) -> bool;
+}
+
+impl EvalResponseExt for EvalResponse {
+    fn is_success(&self) -> bool { self.is_ok() }
+
+    fn success_str(&self) -> Option<Cow<'_, str>> {
+        self.as_deref().ok().map(String::from_utf8_lossy)
+    }
+
+    fn error_str(&self) -> Option<Cow<'_, str>> {
+        self.as_ref().err().map(|data| String::from_utf8_lossy(data))
+    }
+
+    fn into_result_str(self) -> Result<String, String> {
+        fn decode(bytes: Vec<u8>) -> String {
+            String::from_utf8(bytes).unwrap_or_else(|err| {
+                String::from_utf8_lossy(err.as_bytes()).into_owned()
+            })
+        }
+        self.map(decode).map_err(decode)
+    }
+
+    fn looks_like_error(&self) -> bool {
+        let (Ok(data) | Err(data)) = self;
+        sexp::is_error_form(data)
+    }
+
+    fn or_server_err(self) -> Result<Vec<u8>, sexp::ServerError> {
+        self.map_err(|data| {
+            sexp::parse_error(&data).unwrap_or_else(|| sexp::ServerError {
+                kind: "error".into(),
+                message: Some(String::from_utf8_lossy(&data).into_owned()),
+            })
+        })
+    }
+}
+
+/// Information about the Sawfish server; see [`Client::open_with_info`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ServerInfo {
+    /// Version of the server, i.e. value of the `sawfish-version` variable.
+    /// `None` if it couldn’t be determined.
+    pub version: Option<String>,
+}
+
+/// Transport a [`Client`] uses to communicate with the server; see
+/// [`Client::transport`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Transport {
+    /// Unix socket.
+    Unix,
+    /// X11 property protocol.  Used only with `experimental-xcb` feature.
+    X11,
+}
+
+impl Transport {
+    /// Returns features supported by the transport.
+    pub const fn capabilities(self) -> Capabilities {
+        match self {
+            Self::Unix => Capabilities {
+                supports_read_timeout: true,
+                supports_write_timeout: true,
+                supports_peer_uid: true,
+                supports_pipelining: true,
+                is_network_capable: false,
+            },
+            Self::X11 => Capabilities {
+                supports_read_timeout: true,
+                supports_write_timeout: false,
+                supports_peer_uid: false,
+                supports_pipelining: false,
+                is_network_capable: true,
+            },
+        }
+    }
+}
+
+/// Features supported by a transport; see [`Client::capabilities`].
+///
+/// This allows transport-agnostic code to degrade gracefully when a feature
+/// isn’t available.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct Capabilities {
+    /// Whether [`Client::set_read_timeout`] is supported.
+    pub supports_read_timeout: bool,
+    /// Whether [`Client::set_write_timeout`] is supported.
+    pub supports_write_timeout: bool,
+    /// Whether credentials of the server process can be obtained from the
+    /// connection (e.g. with `SO_PEERCRED` on the file descriptor).
+    pub supports_peer_uid: bool,
+    /// Whether multiple requests may be sent with [`Client::send_request`]
+    /// before reading their responses.
+    pub supports_pipelining: bool,
+    /// Whether the server may be running on a different host.
+    pub is_network_capable: bool,
+}
+
+/// Number of bytes exchanged with the server; see [`Client::io_stats`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct IoStats {
+    /// Total number of bytes of requests sent to the server.
+    pub bytes_sent: u64,
+    /// Total number of bytes of responses received from the server.
+    pub bytes_received: u64,
+}
+
 enum Inner {
     Unix(unix::Client),
     X11(x11::Client),
@@ -53,14 +316,344 @@ impl Client {
     ///
     /// Tries to connect to the Unix socket of the Sawfish server.  If that
     /// fails and the `experimental-xcb` Cargo feature is enabled, tries using
-    /// X11 protocol to communicate with Sawfish.
+    /// X11 protocol to communicate with Sawfish.  If that fails as well,
+    /// returns the X11 error if the Unix socket does not exist or
+    /// `ConnError::Both` with both errors otherwise.
+    ///
+    /// If the `SAWFISH_TIMEOUT_MS` environment variable is set to a positive
+    /// number of milliseconds, it’s used as the default read and write timeout
+    /// of the connection and bounds the X11 handshake (see
+    /// [`Self::open_with_timeout`]).  Explicit [`Self::set_read_timeout`] and
+    /// [`Self::set_write_timeout`] calls made after opening take precedence.
+    /// Invalid values of the variable are ignored.  The same applies to all
+    /// other `open` methods, including [`Self::open_x11_with_atoms`] and
+    /// [`Self::open_x11_with_auth`], but not to [`Self::from_x11_connection`]
+    /// which uses an already established connection.
     pub fn open(display: Option<&str>) -> Result<Self, ConnError> {
-        let display = get_display(display)?;
-        match unix::Client::open(&display) {
+        Self::open_verbose(display).map(|(client, _)| client)
+    }
+
+    /// Opens a connection to the Sawfish server retrying on transient errors.
+    ///
+    /// Behaves like [`Self::open`] except that connecting to the Unix socket
+    /// is attempted up to `attempts` times with `delay` between attempts so
+    /// long as it fails because the socket doesn’t exist or the connection is
+    /// refused.  This happens during session startup when the server hasn’t
+    /// started listening yet.  If all attempts fail, the last error is
+    /// returned (or X11 connection is tried if `experimental-xcb` feature is
+    /// enabled).  `attempts` of zero is treated as one.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// let client = sawfish_client::Client::open_retry(
+    ///     None,
+    ///     10,
+    ///     std::time::Duration::from_millis(100),
+    /// )
+    /// .unwrap();
+    /// ```
+    pub fn open_retry(
+        display: Option<&str>,
+        attempts: usize,
+        delay: std::time::Duration,
+    ) -> Result<Self, ConnError> {
+        let display = get_display(display, None)?;
+        let timeout = default_timeout();
+        match unix::Client::open_retry(&display, attempts, delay) {
             Ok(client) => Ok(Self(Inner::Unix(client))),
-            Err(err) => x11::Client::fallback(&display, err)
+            Err(err) => x11::Client::fallback(&display, err, timeout)
+                .map(|client| Self(Inner::X11(client))),
+        }
+        .map(|client| client.with_default_timeout(timeout))
+    }
+
+    /// Opens a connection to the Sawfish server bounding the X11 handshake with
+    /// a timeout.
+    ///
+    /// Behaves like [`Self::open`] except that, when falling back to X11
+    /// communication, requests made while setting up the connection fail with
+    /// an I/O error whose kind is `TimedOut` if the X server doesn’t respond
+    /// within `timeout`.  Without the `experimental-xcb` feature this is the
+    /// same as [`Self::open`] since connecting to a Unix socket doesn’t block.
+    /// `timeout` takes precedence over `SAWFISH_TIMEOUT_MS` for the handshake
+    /// but the variable still sets default read and write timeouts.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// let client = sawfish_client::Client::open_with_timeout(
+    ///     None,
+    ///     std::time::Duration::from_secs(5),
+    /// )
+    /// .unwrap();
+    /// ```
+    pub fn open_with_timeout(
+        display: Option<&str>,
+        timeout: std::time::Duration,
+    ) -> Result<Self, ConnError> {
+        let display = get_display(display, None)?;
+        match unix::Client::open(&display) {
+            Ok((client, _)) => Ok(Self(Inner::Unix(client))),
+            Err(err) => x11::Client::fallback(&display, err, Some(timeout))
                 .map(|client| Self(Inner::X11(client))),
         }
+        .map(|client| client.with_default_timeout(default_timeout()))
+    }
+
+    /// Checks whether the Sawfish server is running.
+    ///
+    /// Returns `true` if the Unix socket of the Sawfish server accepts
+    /// connections.  Otherwise, if the `experimental-xcb` Cargo feature is
+    /// enabled, checks whether Sawfish’s request window is registered on the
+    /// X11 display.  Unlike [`Self::open`], doesn’t set up communication over
+    /// X11 (e.g. doesn’t create the portal window).  This lets tools confirm
+    /// that Sawfish rather than another window manager runs on the display.
+    ///
+    /// Returns `false` if the socket doesn’t exist or refuses connections (and
+    /// Sawfish isn’t found on the X11 display).  Other failures, such as
+    /// missing `LOGNAME` or inability to connect to the X server, are
+    /// returned as errors.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// if !sawfish_client::Client::probe(None).unwrap_or(false) {
+    ///     eprintln!("Sawfish isn’t running");
+    /// }
+    /// ```
+    pub fn probe(display: Option<&str>) -> Result<bool, ConnError> {
+        let display = get_display(display, None)?;
+        let res = unix::server_path(&display).and_then(unix::probe_path);
+        probe_with(&display, res)
+    }
+
+    /// Opens a connection to the Sawfish server and returns path of the
+    /// server it connected to.
+    ///
+    /// Connects as described in [`Self::open`].  For Unix socket connections,
+    /// the path is the socket’s path, i.e. what [`server_path`] returns.  For
+    /// X11 connections, it’s a synthetic `x11:<display>` descriptor.  This is
+    /// useful for tools which display connection details.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// let (client, path) =
+    ///     sawfish_client::Client::open_verbose(None).unwrap();
+    /// println!("Connected to {}", path.display());
+    /// ```
+    pub fn open_verbose(
+        display: Option<&str>,
+    ) -> Result<(Self, std::path::PathBuf), ConnError> {
+        let display = get_display(display, None)?;
+        let timeout = default_timeout();
+        match unix::Client::open(&display) {
+            Ok((client, path)) => Ok((Self(Inner::Unix(client)), path)),
+            Err(err) => {
+                x11::Client::fallback(&display, err, timeout).map(|client| {
+                    let path =
+                        std::path::PathBuf::from(format!("x11:{display}"));
+                    (Self(Inner::X11(client)), path)
+                })
+            }
+        }
+        .map(|(client, path)| (client.with_default_timeout(timeout), path))
+    }
+
+    /// Sets read and, if supported, write timeout of a freshly opened
+    /// connection; see [`default_timeout`].
+    fn with_default_timeout(
+        mut self,
+        timeout: Option<std::time::Duration>,
+    ) -> Self {
+        if timeout.is_some() {
+            // Neither can fail since the timeout is non-zero.  Write timeout
+            // is unsupported with X11 connection which is fine.
+            let _ = self.set_read_timeout(timeout);
+            let _ = self.set_write_timeout(timeout);
+        }
+        self
+    }
+
+    /// Opens a connection to the Sawfish server and queries its version.
+    ///
+    /// Connects as described in [`Self::open`] and evaluates `sawfish-version`
+    /// on the server.  Failure to query the version doesn’t fail the
+    /// connection; instead [`ServerInfo::version`] is `None`.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// let (client, info) =
+    ///     sawfish_client::Client::open_with_info(None).unwrap();
+    /// println!("Sawfish {}", info.version.as_deref().unwrap_or("unknown"));
+    /// ```
+    pub fn open_with_info(
+        display: Option<&str>,
+    ) -> Result<(Self, ServerInfo), ConnError> {
+        let mut client = Self::open(display)?;
+        let info = client.server_info();
+        Ok((client, info))
+    }
+
+    /// Queries information about the server.
+    fn server_info(&mut self) -> ServerInfo {
+        let version = match self.eval("sawfish-version") {
+            Ok(Ok(data)) => String::from_utf8(data).ok().and_then(|data| {
+                let version = data.strip_prefix('"')?.strip_suffix('"')?;
+                Some(version.to_owned())
+            }),
+            _ => None,
+        };
+        ServerInfo { version }
+    }
+
+    /// Opens an X11 connection to the Sawfish server using custom atom names.
+    ///
+    /// Unlike [`Self::open`], doesn’t try to connect to the Unix socket.
+    /// `req_win_atom` is the name of the root window property holding the
+    /// server’s request window (`_SAWFISH_REQUEST_WIN` in Sawfish) and
+    /// `request_atom` is the name of the property used to pass forms and
+    /// responses (`_SAWFISH_REQUEST` in Sawfish).  This is useful when
+    /// communicating with Sawfish forks or test harnesses.
+    #[cfg(feature = "experimental-xcb")]
+    pub fn open_x11_with_atoms(
+        display: Option<&str>,
+        req_win_atom: &str,
+        request_atom: &str,
+    ) -> Result<Self, ConnError> {
+        let display = get_display(display, None)?;
+        let timeout = default_timeout();
+        x11::Client::open_with_atoms(
+            &display,
+            req_win_atom,
+            request_atom,
+            timeout,
+        )
+        .map(|client| Self(Inner::X11(client)).with_default_timeout(timeout))
+    }
+
+    /// Opens an X11 connection to the Sawfish server using explicit
+    /// authorisation information.
+    ///
+    /// Unlike [`Self::open`], doesn’t try to connect to the Unix socket.
+    /// `auth` is passed to the X server instead of credentials X libraries
+    /// read from `XAUTHORITY` file.  This allows connecting to Sawfish over
+    /// forwarded displays (e.g. SSH X forwarding) which use a non-default
+    /// cookie.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// let auth = xcb::AuthInfo {
+    ///     name: "MIT-MAGIC-COOKIE-1",
+    ///     data: "0123456789abcdef",
+    /// };
+    /// let client =
+    ///     sawfish_client::Client::open_x11_with_auth(Some("host:10"), auth);
+    /// ```
+    #[cfg(feature = "experimental-xcb")]
+    pub fn open_x11_with_auth(
+        display: Option<&str>,
+        auth: xcb::AuthInfo<'_>,
+    ) -> Result<Self, ConnError> {
+        let display = get_display(display, None)?;
+        let timeout = default_timeout();
+        x11::Client::open_with_auth(&display, auth, timeout).map(|client| {
+            Self(Inner::X11(client)).with_default_timeout(timeout)
+        })
+    }
+
+    /// Sets up an X11 connection to the Sawfish server over an existing XCB
+    /// connection.
+    ///
+    /// This allows applications which already have an X11 connection (e.g.
+    /// panel applets) to share it rather than opening a new one.  `screen` is
+    /// the index of the screen Sawfish is running on (usually the screen
+    /// returned by [`xcb::Connection::connect`]).  [`Self::display`] of the
+    /// resulting client is empty.
+    ///
+    /// **Note** that the client takes ownership of the connection and
+    /// [`Self::eval`] consumes events on it while waiting for the response.
+    /// Events other than the property notification the client waits for are
+    /// discarded.  Applications which run their own event loop on the
+    /// connection must not evaluate forms through the client concurrently.
+    #[cfg(feature = "experimental-xcb")]
+    pub fn from_x11_connection(
+        conn: xcb::Connection,
+        screen: usize,
+    ) -> Result<Self, ConnError> {
+        x11::Client::from_connection(conn, screen)
+            .map(|client| Self(Inner::X11(client)))
+    }
+
+    /// Returns canonical name of the display the client is connected to.
+    ///
+    /// The name is in the `host:display.screen` form, e.g.
+    /// `"example.com:0.0"`, which is also the name of the Unix socket the
+    /// Sawfish server listens on.
+    pub fn display(&self) -> &str {
+        match &self.0 {
+            Inner::Unix(client) => &client.display,
+            Inner::X11(client) => client.display(),
+        }
+    }
+
+    /// Returns id of the Sawfish request window for X11 connections.
+    ///
+    /// With X11 transport, forms are delivered to Sawfish via a property of
+    /// a window the server advertises on the root window.  This returns that
+    /// window’s resource id which can be cross-checked with tools such as
+    /// `xprop`.  Returns `None` for Unix socket connections.
+    pub fn x11_request_window(&self) -> Option<u32> {
+        match &self.0 {
+            Inner::Unix(_) => None,
+            Inner::X11(client) => Some(client.request_window()),
+        }
+    }
+
+    /// Returns features supported by the transport the client uses.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// let mut client = sawfish_client::Client::open(None).unwrap();
+    /// if client.capabilities().supports_write_timeout {
+    ///     let timeout = std::time::Duration::from_secs(1);
+    ///     client.set_write_timeout(Some(timeout)).unwrap();
+    /// }
+    /// ```
+    pub fn capabilities(&self) -> Capabilities {
+        self.transport().capabilities()
+    }
+
+    /// Returns time elapsed since the connection was established.
+    ///
+    /// This can be used to recycle long-lived connections.
+    pub fn age(&self) -> std::time::Duration {
+        match &self.0 {
+            Inner::Unix(client) => client.created_at.elapsed(),
+            Inner::X11(client) => client.created_at().elapsed(),
+        }
+    }
+
+    /// Returns time elapsed since a form was last sent to the server.
+    ///
+    /// If no form has been sent yet, this is the same as [`Self::age`].
+    pub fn idle_time(&self) -> std::time::Duration {
+        match &self.0 {
+            Inner::Unix(client) => client.last_used.elapsed(),
+            Inner::X11(client) => client.last_used().elapsed(),
+        }
+    }
+
+    /// Returns transport the client uses to communicate with the server.
+    pub fn transport(&self) -> Transport {
+        match &self.0 {
+            Inner::Unix(_) => Transport::Unix,
+            Inner::X11(_) => Transport::X11,
+        }
     }
 
     /// Sends a Lisp `form` to the Sawfish server for evaluation and waits for
@@ -73,6 +666,10 @@ impl Client {
     /// * Otherwise, if the `form` has been successfully executed by the server,
     ///   returns `Ok(Ok(data))` value.
     ///
+    /// With Unix socket connection, the form is written to the socket directly
+    /// and is never copied.  The only allocation is the buffer for response
+    /// data.  Similarly, [`Self::send`] doesn’t allocate at all.
+    ///
     /// # Example
     ///
     /// ```no_run
@@ -108,6 +705,12 @@ impl Client {
     /// side has changed (e.g. due to syntax error).  Use [`Self::eval`] instead
     /// to check whether evaluation succeeded.
     ///
+    /// Sawfish protocol calls such requests ‘async’.  This has nothing to do
+    /// with Rust’s `async` functions (see `AsyncClient`) which is why the
+    /// library uses `send` and `eval` names instead.  Similarly, `is_async`
+    /// arguments of low-level methods (such as `AsyncSender::send_request`)
+    /// refer to the Sawfish meaning.
+    ///
     /// # Example
     ///
     /// ```no_run
@@ -123,128 +726,1104 @@ impl Client {
             Inner::X11(client) => client.eval(form.as_ref(), true).map(|_| ()),
         }
     }
-}
-
-/// Opens a connection to the Sawfish server.
-///
-/// This is a convenience alias for [`Client::open`].
-#[inline]
-pub fn open(display: Option<&str>) -> Result<Client, ConnError> {
-    Client::open(display)
-}
-
-
-/// A connection to the Sawfish window manager using asynchronous I/O.
-#[cfg(feature = "async")]
-pub struct AsyncClient<S>(unix::AsyncClient<S>);
-
-/// An alias for the [`AsyncClient`] which uses Tokio runtime Unix stream.
-///
-/// # Example
-///
-/// ```no_run
-/// use tokio_util::compat::TokioAsyncReadCompatExt;
-///
-/// async fn print_system_name() {
-///     let mut client = sawfish_client::open_tokio(None).await.unwrap();
-///     let sysname = client.eval("(system-name)").await.unwrap().unwrap();
-///     println!("{}", String::from_utf8_lossy(&sysname));
-/// }
-/// ```
-#[cfg(feature = "tokio")]
-pub type TokioClient =
-    AsyncClient<tokio_util::compat::Compat<tokio::net::UnixStream>>;
 
-#[cfg(feature = "tokio")]
-impl AsyncClient<tokio_util::compat::Compat<tokio::net::UnixStream>> {
-    /// Opens a connection to the Sawfish server using the Tokio runtime.
+    /// Sends Lisp `forms` to the Sawfish server for evaluation without waiting
+    /// for replies.
     ///
-    /// The `display` argument specifies an optional display string, (such as
-    /// `":0"`).  If not provided, the `DISPLAY` environment variable is used.
-    pub async fn open(display: Option<&str>) -> Result<Self, ConnError> {
-        let display = get_display(display)?;
-        unix::AsyncClient::open(&display).await.map(Self)
+    /// Behaves like calling [`Self::send`] for each form except that all the
+    /// requests are written at once.  With Unix socket connection, this is
+    /// a single write.  With X11 connection, each request still needs a round
+    /// trip to set the shared property but the connection is flushed only
+    /// once at the end.
+    ///
+    /// All forms are checked before anything is sent so an empty or too large
+    /// form fails the whole batch without any request being sent.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// let mut client = sawfish_client::Client::open(None).unwrap();
+    /// client
+    ///     .batch_send(&["(next-workspace 1)", "(display-message \"Moved\")"])
+    ///     .unwrap();
+    /// ```
+    pub fn batch_send(
+        &mut self,
+        forms: &[impl AsRef<[u8]>],
+    ) -> Result<(), EvalError> {
+        match &mut self.0 {
+            Inner::Unix(client) => client.batch_send(forms),
+            Inner::X11(client) => client.batch_send(forms),
+        }
     }
-}
-
-/// Opens a connection to the Sawfish server using the Tokio runtime.
-///
-/// This is a convenience alias for [`AsyncClient::open`] with the generic
-/// argument `S` set to Tokio Unix stream type.
-#[cfg(feature = "tokio")]
-#[inline]
-pub async fn open_tokio(
-    display: Option<&str>,
-) -> Result<TokioClient, ConnError> {
-    TokioClient::open(display).await
-}
 
-#[cfg(feature = "async")]
-impl<S: AsyncRead + AsyncWrite + Unpin> AsyncClient<S> {
-    /// Constructs a connection to the Sawfish server over an asynchronous Unix
-    /// socket.
+    /// Sends a Lisp `form` to the Sawfish server for evaluation and, if `wait`
+    /// is `true`, waits for a reply.
     ///
-    /// Because the creation of an asynchronous Unix socket depends on the async
-    /// runtime, responsibility to open the connection falls on the caller.  Use
-    /// [`server_path`] to determine path to the Unix Socket the Sawfish server
-    /// is (supposed to be) listening on.
+    /// If `wait` is `true`, behaves like [`Self::eval`] and returns the
+    /// response wrapped in `Some`.  Otherwise, behaves like [`Self::send`] and
+    /// returns `None`.  This is convenient when whether to wait for the
+    /// response is decided at runtime.
     ///
     /// # Example
     ///
     /// ```no_run
-    /// use tokio_util::compat::TokioAsyncReadCompatExt;
+    /// let quiet = std::env::args().any(|arg| arg == "-q");
+    /// let mut client = sawfish_client::Client::open(None).unwrap();
+    /// if let Some(res) = client.eval_opt("(system-name)", !quiet).unwrap() {
+    ///     println!("{res:?}");
+    /// }
+    /// ```
+    pub fn eval_opt(
+        &mut self,
+        form: impl AsRef<[u8]>,
+        wait: bool,
+    ) -> Result<Option<EvalResponse>, EvalError> {
+        if wait {
+            self.eval(form).map(Some)
+        } else {
+            self.send(form).map(|()| None)
+        }
+    }
+
+    /// Deprecated alias of [`Self::send`].
+    #[deprecated(since = "0.2.2", note = "use `send` instead")]
+    #[doc(hidden)]
+    pub fn eval_async(
+        &mut self,
+        form: impl AsRef<[u8]>,
+    ) -> Result<(), EvalError> {
+        self.send(form)
+    }
+
+    /// Sends a Lisp `form` to the Sawfish server without reading the response.
+    ///
+    /// This is a low-level interface for callers who wish to interleave
+    /// sending requests and reading responses manually, e.g. to pipeline
+    /// requests.  If `is_async` is `true`, the server doesn’t respond to the
+    /// request (this is equivalent to [`Self::send`]).  Otherwise, the server
+    /// sends a response which **must** be read with [`Self::read_response`].
+    ///
+    /// **Requests and responses must be kept balanced.**  Responses are read
+    /// in the order requests were sent and there’s nothing in a response
+    /// which identifies its request.  Failing to read a response (or reading
+    /// one too many) causes subsequent calls to return responses to wrong
+    /// requests or block indefinitely.  Prefer [`Self::eval`] unless there’s
+    /// a need for this interface.
+    ///
+    /// With X11 connection, requests cannot be pipelined and the response
+    /// must be read before sending another request.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// let mut client = sawfish_client::Client::open(None).unwrap();
+    /// let forms = ["(system-name)", "(screen-width)", "(screen-height)"];
+    /// for form in forms {
+    ///     client.send_request(form, false).unwrap();
+    /// }
+    /// for form in forms {
+    ///     let res = client.read_response().unwrap();
+    ///     println!("{form} → {res:?}");
+    /// }
+    /// ```
+    pub fn send_request(
+        &mut self,
+        form: impl AsRef<[u8]>,
+        is_async: bool,
+    ) -> Result<(), EvalError> {
+        match &mut self.0 {
+            Inner::Unix(client) => client.send_request(form.as_ref(), is_async),
+            Inner::X11(client) => client.send_request(form.as_ref(), is_async),
+        }
+    }
+
+    /// Reads a response to a request sent with [`Self::send_request`].
+    ///
+    /// This is a low-level interface; see [`Self::send_request`] for
+    /// discussion of keeping requests and responses balanced.
+    pub fn read_response(&mut self) -> Result<EvalResponse, EvalError> {
+        match &mut self.0 {
+            Inner::Unix(client) => client.read_response(),
+            Inner::X11(client) => client.read_response(),
+        }
+    }
+
+    /// Sends a Lisp `form` to the Sawfish server for evaluation and waits for
+    /// it to finish, discarding the result.
+    ///
+    /// Unlike [`Self::send`], waits for the server to respond.  Unlike
+    /// [`Self::eval`], ignores the response, including whether evaluation
+    /// succeeded.  Returns `Ok(())` so long as communication with the server
+    /// succeeded.  This is useful for commands executed for their side
+    /// effects which need to complete before continuing.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// let mut client = sawfish_client::Client::open(None).unwrap();
+    /// client.run("(set-screen-viewport 0 0)").unwrap();
+    /// ```
+    pub fn run(&mut self, form: impl AsRef<[u8]>) -> Result<(), EvalError> {
+        self.eval(form).map(|_| ())
+    }
+
+    /// Returns number of bytes sent to and received from the server.
+    ///
+    /// The counters include protocol overhead (such as request and response
+    /// headers of the Unix socket protocol) and are updated once a request is
+    /// sent or a response is read in full.  This is cheap bookkeeping useful
+    /// for diagnosing throughput.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// let mut client = sawfish_client::Client::open(None).unwrap();
+    /// client.eval("(system-name)").unwrap();
+    /// let stats = client.io_stats();
+    /// println!("sent: {} bytes", stats.bytes_sent);
+    /// println!("received: {} bytes", stats.bytes_received);
+    /// ```
+    pub fn io_stats(&self) -> IoStats {
+        match &self.0 {
+            Inner::Unix(client) => client.io_stats,
+            Inner::X11(client) => client.io_stats(),
+        }
+    }
+
+    /// Returns whether the most recent response was a server error.
+    ///
+    /// Returns `Some(true)` if evaluation of the last form whose response was
+    /// read failed on the server side, `Some(false)` if it succeeded and
+    /// `None` if no response has been read yet.  Communication errors don’t
+    /// change the value.  This is handy for collecting error rate metrics
+    /// without inspecting each response.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// let mut client = sawfish_client::Client::open(None).unwrap();
+    /// for form in ["(system-name)", "(no-such-function)"] {
+    ///     let _ = client.eval(form);
+    ///     println!("{form}: error={:?}", client.last_was_error());
+    /// }
+    /// ```
+    pub fn last_was_error(&self) -> Option<bool> {
+        match &self.0 {
+            Inner::Unix(client) => client.last_was_error,
+            Inner::X11(client) => client.last_was_error(),
+        }
+    }
+
+    /// Switches the screen to viewport at given coordinates.
+    ///
+    /// Evaluates form built by [`commands::set_screen_viewport`] and returns
+    /// the response like [`Self::eval`] does.  In particular, if Sawfish
+    /// fails to switch the viewport (e.g. because the coordinates are out of
+    /// range), the error is returned in the `Err` variant of the response.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use sawfish_client::commands::{ViewportX, ViewportY};
+    ///
+    /// let mut client = sawfish_client::Client::open(None).unwrap();
+    /// match client.set_screen_viewport(ViewportX(1), ViewportY(0)) {
+    ///     Ok(Ok(_)) => (),
+    ///     Ok(Err(data)) => {
+    ///         eprintln!("Sawfish: {}", String::from_utf8_lossy(&data))
+    ///     }
+    ///     Err(err) => eprintln!("{err}"),
+    /// }
+    /// ```
+    pub fn set_screen_viewport(
+        &mut self,
+        x: commands::ViewportX,
+        y: commands::ViewportY,
+    ) -> Result<EvalResponse, EvalError> {
+        self.eval(commands::set_screen_viewport(x, y))
+    }
+
+    /// Checks whether the Sawfish server is responsive.
+    ///
+    /// Evaluates `t` and returns `Ok(())` if the server responded with `t`.
+    /// Any other response, including an evaluation error, is reported as
+    /// [`EvalError::UnexpectedResponse`] holding the response.  Since the
+    /// server did respond, the connection remains usable.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// let mut client = sawfish_client::Client::open(None).unwrap();
+    /// if let Err(err) = client.ping() {
+    ///     eprintln!("Sawfish not responding: {err}");
+    /// }
+    /// ```
+    pub fn ping(&mut self) -> Result<(), EvalError> {
+        check_ping(self.eval("t"))
+    }
+
+    /// Asks the Sawfish server to quit.
+    ///
+    /// Sends `(quit)` form without waiting for a reply (see [`Self::send`]).
+    /// Once the server exits, the connection is closed so the client should be
+    /// dropped afterwards.
+    pub fn quit(&mut self) -> Result<(), EvalError> { self.send("(quit)") }
+
+    /// Asks the Sawfish server to restart.
+    ///
+    /// Sends `(restart)` form without waiting for a reply (see [`Self::send`]).
+    /// The restarted server doesn’t keep existing connections so the client
+    /// should be dropped afterwards and a new connection opened if needed.
+    pub fn restart(&mut self) -> Result<(), EvalError> {
+        self.send("(restart)")
+    }
+
+    /// Sends a Lisp `form` to the Sawfish server for evaluation and reads
+    /// `count` responses.
+    ///
+    /// This is an advanced feature which depends on server behaviour.  Normally
+    /// Sawfish sends exactly one response to each request and [`Self::eval`]
+    /// should be used.  If the server sends fewer responses than `count`, the
+    /// call blocks (or fails if read timeout is set); if it sends more, the
+    /// responses are going to be read by subsequent calls and requests and
+    /// responses will get out of sync.
+    ///
+    /// If `count` is zero, the form is sent as with [`Self::send`].  With X11
+    /// connection, `count` greater than one results in an [`EvalError::Io`]
+    /// error whose kind is `Unsupported`.
+    pub fn eval_multi(
+        &mut self,
+        form: impl AsRef<[u8]>,
+        count: usize,
+    ) -> Result<Vec<EvalResponse>, EvalError> {
+        match &mut self.0 {
+            Inner::Unix(client) => client.eval_multi(form.as_ref(), count),
+            Inner::X11(client) => client.eval_multi(form.as_ref(), count),
+        }
+    }
+
+    /// Reads and returns a stray response if one is available.
+    ///
+    /// If requests and responses got out of sync (e.g. because the server
+    /// responded to a request sent with [`Self::send`] or a read timed out),
+    /// this can be used to recover the connection rather than dropping it.
+    /// Reads exactly one response if one is available and returns it, or
+    /// returns `None` if there’s no response to read.
+    ///
+    /// Whether a response is available is determined by attempting a read.
+    /// **The method relies on read timeout being set** (see
+    /// [`Self::set_read_timeout`]); without it, the call blocks until the
+    /// server sends a response.
+    ///
+    /// With X11 connection, returns an [`EvalError::Io`] error whose kind is
+    /// `Unsupported`.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// let mut client = sawfish_client::Client::open(None).unwrap();
+    /// client
+    ///     .set_read_timeout(Some(std::time::Duration::from_millis(10)))
+    ///     .unwrap();
+    /// while let Some(res) = client.drain_pending().unwrap() {
+    ///     eprintln!("Discarding stray response: {res:?}");
+    /// }
+    /// ```
+    pub fn drain_pending(&mut self) -> Result<Option<EvalResponse>, EvalError> {
+        match &mut self.0 {
+            Inner::Unix(client) => client.drain_pending(),
+            Inner::X11(client) => client.drain_pending(),
+        }
+    }
+
+    /// Sends a Lisp `form` to the Sawfish server for evaluation and copies the
+    /// response data into `out`.
+    ///
+    /// With Unix socket connection, the data is copied from the socket into
+    /// `out` in chunks without holding the entire response in memory.  This is
+    /// useful for forms which return large amount of data.
+    ///
+    /// * If there’s an error sending the `form` to the server or reading the
+    ///   response (e.g. an I/O error), returns an `Err(error)` value.  This
+    ///   includes errors writing to `out` in which case the connection should
+    ///   be closed since the rest of the response hasn’t been read.
+    /// * Otherwise, if the `form` has been successfully sent to the server but
+    ///   evaluation failed, returns `Ok(false)`.  The error message is written
+    ///   to `out`.
+    /// * Otherwise, if the `form` has been successfully executed by the server,
+    ///   returns `Ok(true)`.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// let mut client = sawfish_client::Client::open(None).unwrap();
+    /// let mut out = std::io::stdout().lock();
+    /// if !client.eval_to_writer("(managed-windows)", &mut out).unwrap() {
+    ///     eprintln!("Error evaluating form");
+    /// }
+    /// ```
+    pub fn eval_to_writer(
+        &mut self,
+        form: impl AsRef<[u8]>,
+        out: &mut impl std::io::Write,
+    ) -> Result<bool, EvalError> {
+        match &mut self.0 {
+            Inner::Unix(client) => client.eval_to_writer(form.as_ref(), out),
+            Inner::X11(client) => client.eval_to_writer(form.as_ref(), out),
+        }
+    }
+
+    /// Sends a Lisp `form` to the Sawfish server for evaluation and waits for
+    /// a reply until `deadline`.
+    ///
+    /// Sets the read timeout of the connection (see [`Self::set_read_timeout`])
+    /// to time remaining until the deadline, evaluates the form and restores
+    /// the previous timeout.  If the response doesn’t arrive in time, returns
+    /// [`EvalError::Timeout`].  The same is returned without sending anything
+    /// if the deadline has already passed.  Note that the timeout applies to
+    /// each read so a server trickling response slowly may overrun the
+    /// deadline.
+    ///
+    /// After a timeout, the response may still arrive later and the connection
+    /// should be closed.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// let mut client = sawfish_client::Client::open(None).unwrap();
+    /// let deadline =
+    ///     std::time::Instant::now() + std::time::Duration::from_secs(1);
+    /// match client.eval_deadline("(system-name)", deadline) {
+    ///     Err(sawfish_client::EvalError::Timeout) => eprintln!("Timed out"),
+    ///     res => println!("{res:?}"),
+    /// }
+    /// ```
+    pub fn eval_deadline(
+        &mut self,
+        form: impl AsRef<[u8]>,
+        deadline: std::time::Instant,
+    ) -> Result<EvalResponse, EvalError> {
+        use std::io::ErrorKind;
+
+        let timeout =
+            deadline.saturating_duration_since(std::time::Instant::now());
+        if timeout.is_zero() {
+            return Err(EvalError::Timeout);
+        }
+        let prev = match &self.0 {
+            Inner::Unix(client) => client.sock.read_timeout()?,
+            Inner::X11(client) => client.read_timeout(),
+        };
+        self.set_read_timeout(Some(timeout))?;
+        let res = self.eval(form);
+        let restored = self.set_read_timeout(prev);
+        let res = res.map_err(|err| match err {
+            EvalError::Io(err)
+                if matches!(
+                    err.kind(),
+                    ErrorKind::WouldBlock | ErrorKind::TimedOut
+                ) =>
+            {
+                EvalError::Timeout
+            }
+            err => err,
+        })?;
+        restored?;
+        Ok(res)
+    }
+
+    /// Sends a Lisp `form` to the Sawfish server for evaluation, waits for
+    /// a reply and returns whether evaluation succeeded.
+    ///
+    /// The response data is read and discarded which keeps the connection in
+    /// sync.  With Unix socket connection, the data is read in chunks without
+    /// holding the entire response in memory.  This is useful for forms whose
+    /// value is of no interest (e.g. ones changing settings) and may be
+    /// large.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// let mut client = sawfish_client::Client::open(None).unwrap();
+    /// if !client.eval_status("(setq focus-mode 'click)").unwrap() {
+    ///     eprintln!("Error changing focus mode");
+    /// }
+    /// ```
+    pub fn eval_status(
+        &mut self,
+        form: impl AsRef<[u8]>,
+    ) -> Result<bool, EvalError> {
+        self.eval_to_writer(form, &mut std::io::sink())
+    }
+
+    /// Sends a textual Lisp `form` to the Sawfish server for evaluation and
+    /// waits for a reply.
+    ///
+    /// Behaves like [`Self::eval`] but accepts only `&str` which guarantees
+    /// the form is valid UTF-8 as expected by Sawfish’s reader.  This is the
+    /// safe default for textual forms; use [`Self::eval`] when the form is
+    /// already bytes (and possibly check it with
+    /// [`sexp::is_valid_form_encoding`]).
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// let mut client = sawfish_client::Client::open(None).unwrap();
+    /// let msg = sawfish_client::sexp::quote_string("zażółć");
+    /// let form = format!("(display-message {msg})");
+    /// client.eval_utf8(&form).unwrap();
+    /// ```
+    pub fn eval_utf8(&mut self, form: &str) -> Result<EvalResponse, EvalError> {
+        self.eval(form)
+    }
+
+    /// Sends a Lisp `form` to the Sawfish server for evaluation and waits for
+    /// a reply reporting progress of reading it.
+    ///
+    /// Behaves like [`Self::eval`] but while reading response data calls
+    /// `progress` with the number of bytes read so far and the total length
+    /// of the data.  The callback is called at least once, with both
+    /// arguments equal, once all data has been read.  This is useful for
+    /// progress reporting in interactive tools when responses are large.
+    ///
+    /// With X11 connections the response is read in one go so the callback is
+    /// called only once.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// let mut client = sawfish_client::Client::open(None).unwrap();
+    /// let res = client.eval_with_progress("(managed-windows)", |read, total| {
+    ///     eprint!("\r{read}/{total} bytes");
+    /// });
+    /// ```
+    pub fn eval_with_progress(
+        &mut self,
+        form: impl AsRef<[u8]>,
+        progress: impl FnMut(usize, usize),
+    ) -> Result<EvalResponse, EvalError> {
+        match &mut self.0 {
+            Inner::Unix(client) => {
+                client.eval_with_progress(form.as_ref(), progress)
+            }
+            Inner::X11(client) => {
+                client.eval_with_progress(form.as_ref(), progress)
+            }
+        }
+    }
+
+    /// Sends a Lisp `form` to the Sawfish server for evaluation and returns the
+    /// raw response frame.
+    ///
+    /// The frame consists of the 8-byte response length, the status byte and
+    /// the response data exactly as sent by the server.  This is useful for
+    /// protocol debugging and complements [`protocol::render_request`] which
+    /// shows the request as sent on the wire.  Use [`Self::eval`] to get the
+    /// decoded response instead.
+    ///
+    /// Only Unix socket connections have response frames.  With X11
+    /// connections, fails with an I/O error whose kind is `Unsupported`.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// let mut client = sawfish_client::Client::open(None).unwrap();
+    /// let frame = client.eval_frame("(system-name)").unwrap();
+    /// println!("{frame:02x?}");
+    /// ```
+    pub fn eval_frame(
+        &mut self,
+        form: impl AsRef<[u8]>,
+    ) -> Result<Vec<u8>, EvalError> {
+        match &mut self.0 {
+            Inner::Unix(client) => client.eval_frame(form.as_ref()),
+            Inner::X11(client) => client.eval_frame(form.as_ref()),
+        }
+    }
+
+    /// Sets maximum length of response data.
+    ///
+    /// If the server sends a response longer than that, [`Self::eval`] fails
+    /// with [`EvalError::ResponseTooLarge`] error.  This protects from
+    /// a misbehaving server making the client allocate excessive amounts of
+    /// memory.  By default, the limit is 256 MiB.
+    ///
+    /// The declared length is checked against the limit before it’s converted
+    /// to `usize` so the check behaves the same way on all platforms.
+    /// Regardless of the limit, declared lengths over 4 GiB are considered
+    /// a sign of requests and responses getting out of sync and fail with
+    /// [`EvalError::Desync`].
+    pub fn set_max_response(&mut self, max_response: usize) {
+        match &mut self.0 {
+            Inner::Unix(client) => client.max_response = max_response,
+            Inner::X11(client) => client.set_max_response(max_response),
+        }
+    }
+
+    /// Sets maximum length of a form.
+    ///
+    /// Trying to evaluate a longer form fails with
+    /// [`EvalError::RequestTooLarge`] error before anything is sent to the
+    /// server.  This protects from accidentally sending huge amounts of data.
+    /// By default, the limit is 256 MiB.
+    pub fn set_max_request(&mut self, max_request: usize) {
+        match &mut self.0 {
+            Inner::Unix(client) => client.max_request = max_request,
+            Inner::X11(client) => client.set_max_request(max_request),
+        }
+    }
+
+    /// Sets byte order used to encode lengths in requests and responses.
+    ///
+    /// By default, native byte order is used which is correct for connections
+    /// to a local Sawfish server.  Different byte order is needed only if the
+    /// Unix socket has been forwarded from a host with different endianness.
+    ///
+    /// Lengths aren’t encoded in X11 protocol messages so this has no effect on
+    /// X11 connections.  The setting is available for this synchronous client
+    /// only; `AsyncClient` always uses native byte order.
+    pub fn set_byte_order(&mut self, byte_order: protocol::ByteOrder) {
+        if let Inner::Unix(client) = &mut self.0 {
+            client.byte_order = byte_order;
+        }
+    }
+
+    /// Sets the read timeout of the connection.
+    ///
+    /// With Unix socket connection, this sets the read timeout of the socket
+    /// (see [`std::os::unix::net::UnixStream::set_read_timeout`]).  With X11
+    /// connection, this limits time spent waiting for the server to write the
+    /// response.  If reading a response times out, [`Self::eval`] returns an
+    /// [`EvalError::Io`] error whose kind is `WouldBlock` or `TimedOut`.  Note
+    /// that the connection should be closed afterwards since a response read
+    /// later may not correspond to the next request.
+    ///
+    /// Zero timeout results in an `InvalidInput` error.
+    pub fn set_read_timeout(
+        &mut self,
+        timeout: Option<std::time::Duration>,
+    ) -> std::io::Result<()> {
+        match &mut self.0 {
+            Inner::Unix(client) => client.sock.set_read_timeout(timeout),
+            Inner::X11(client) => client.set_read_timeout(timeout),
+        }
+    }
+
+    /// Sets the write timeout of the connection.
+    ///
+    /// With Unix socket connection, this sets the write timeout of the socket
+    /// (see [`std::os::unix::net::UnixStream::set_write_timeout`]).
+    ///
+    /// Write timeouts are not supported with X11 connections and the method
+    /// returns an `Unsupported` error.
+    pub fn set_write_timeout(
+        &mut self,
+        timeout: Option<std::time::Duration>,
+    ) -> std::io::Result<()> {
+        match &mut self.0 {
+            Inner::Unix(client) => client.sock.set_write_timeout(timeout),
+            Inner::X11(client) => client.set_write_timeout(timeout),
+        }
+    }
+}
+
+/// Exposes file descriptor of the connection.
+///
+/// This allows integrating the client with custom event loops (e.g. to wait
+/// for the socket to become readable).  With Unix socket connection, this is
+/// the socket; with X11 connection, this is the X server connection.
+///
+/// Reading from or writing to the file descriptor directly interferes with
+/// the protocol.  Requests and responses will get out of sync and subsequent
+/// [`Client::eval`] calls will return garbage or fail.
+impl std::os::fd::AsFd for Client {
+    fn as_fd(&self) -> std::os::fd::BorrowedFd<'_> {
+        match &self.0 {
+            Inner::Unix(client) => client.sock.as_fd(),
+            Inner::X11(client) => client.as_fd(),
+        }
+    }
+}
+
+/// See [`AsFd`](std::os::fd::AsFd) implementation for caveats.
+impl std::os::fd::AsRawFd for Client {
+    fn as_raw_fd(&self) -> std::os::fd::RawFd {
+        std::os::fd::AsFd::as_fd(self).as_raw_fd()
+    }
+}
+
+/// Interprets result of probing the Unix socket falling back to X11 if it
+/// failed; see [`Client::probe`].
+fn probe_with(
+    display: &str,
+    res: Result<(), ConnError>,
+) -> Result<bool, ConnError> {
+    match res {
+        Ok(()) => Ok(true),
+        Err(err) => x11::Client::probe(display, err),
+    }
+}
+
+/// Checks result of evaluating `t` form sent by `ping` methods.
+fn check_ping(
+    response: Result<EvalResponse, EvalError>,
+) -> Result<(), EvalError> {
+    match response? {
+        Ok(data) if data == b"t" => Ok(()),
+        response => Err(EvalError::UnexpectedResponse(response)),
+    }
+}
+
+/// Opens a connection to the Sawfish server.
+///
+/// This is a convenience alias for [`Client::open`].
+#[inline]
+pub fn open(display: Option<&str>) -> Result<Client, ConnError> {
+    Client::open(display)
+}
+
+
+/// A connection to the Sawfish window manager using asynchronous I/O.
+#[cfg(feature = "async")]
+pub struct AsyncClient<S>(unix::AsyncClient<S>);
+
+/// An alias for the [`AsyncClient`] which uses Tokio runtime Unix stream.
+///
+/// # Example
+///
+/// ```no_run
+/// use tokio_util::compat::TokioAsyncReadCompatExt;
+///
+/// async fn print_system_name() {
+///     let mut client = sawfish_client::open_tokio(None).await.unwrap();
+///     let sysname = client.eval("(system-name)").await.unwrap().unwrap();
+///     println!("{}", String::from_utf8_lossy(&sysname));
+/// }
+/// ```
+#[cfg(feature = "tokio")]
+pub type TokioClient =
+    AsyncClient<tokio_util::compat::Compat<tokio::net::UnixStream>>;
+
+#[cfg(feature = "tokio")]
+impl AsyncClient<tokio_util::compat::Compat<tokio::net::UnixStream>> {
+    /// Opens a connection to the Sawfish server using the Tokio runtime.
+    ///
+    /// The `display` argument specifies an optional display string, (such as
+    /// `":0"`).  If not provided, the `DISPLAY` environment variable is used.
+    pub async fn open(display: Option<&str>) -> Result<Self, ConnError> {
+        let display = get_display(display, None)?;
+        unix::AsyncClient::open(&display, None).await.map(Self)
+    }
+
+    /// Opens a connection to the Sawfish server using the Tokio runtime with
+    /// a connection timeout.
+    ///
+    /// Behaves like [`Self::open`] except that if the connection isn’t
+    /// established within `timeout`, fails with [`ConnError::Io`] whose kind is
+    /// `TimedOut`.  The runtime must have time driver enabled.
+    pub async fn open_timeout(
+        display: Option<&str>,
+        timeout: std::time::Duration,
+    ) -> Result<Self, ConnError> {
+        let display = get_display(display, None)?;
+        unix::AsyncClient::open(&display, Some(timeout)).await.map(Self)
+    }
+
+    /// Waits for the Sawfish server to start and opens a connection to it
+    /// using the Tokio runtime.
+    ///
+    /// Periodically checks whether the server’s socket exists (with
+    /// increasing delay between checks) and connects once it appears.  This
+    /// is useful when the client is started alongside Sawfish and may race
+    /// with server’s startup.  If the connection isn’t established within
+    /// `timeout`, fails with [`ConnError::Io`] whose kind is `TimedOut`.  The
+    /// runtime must have time driver enabled.
+    ///
+    /// Unlike [`Client::open`], this never falls back to X11 connection.
+    pub async fn open_wait(
+        display: Option<&str>,
+        timeout: std::time::Duration,
+    ) -> Result<Self, ConnError> {
+        let display = get_display(display, None)?;
+        unix::AsyncClient::open_wait(&display, timeout).await.map(Self)
+    }
+}
+
+/// Opens a connection to the Sawfish server using the Tokio runtime.
+///
+/// This is a convenience alias for [`AsyncClient::open`] with the generic
+/// argument `S` set to Tokio Unix stream type.
+#[cfg(feature = "tokio")]
+#[inline]
+pub async fn open_tokio(
+    display: Option<&str>,
+) -> Result<TokioClient, ConnError> {
+    TokioClient::open(display).await
+}
+
+/// An alias for the [`AsyncClient`] which uses smol Unix stream.
+///
+/// # Example
+///
+/// ```no_run
+/// fn print_system_name() {
+///     smol::block_on(async {
+///         let mut client = sawfish_client::open_smol(None).await.unwrap();
+///         let sysname = client.eval("(system-name)").await.unwrap().unwrap();
+///         println!("{}", String::from_utf8_lossy(&sysname));
+///     })
+/// }
+/// ```
+#[cfg(feature = "smol")]
+pub type SmolClient = AsyncClient<smol::Async<std::os::unix::net::UnixStream>>;
+
+/// Opens a connection to the Sawfish server using the smol runtime.
+///
+/// The `display` argument specifies an optional display string, (such as
+/// `":0"`).  If not provided, the `DISPLAY` environment variable is used.
+///
+/// Unlike with [`TokioClient`], there’s no `SmolClient::open` method since it
+/// would make calls to `AsyncClient::open` ambiguous when both `tokio` and
+/// `smol` features are enabled.
+#[cfg(feature = "smol")]
+pub async fn open_smol(display: Option<&str>) -> Result<SmolClient, ConnError> {
+    let display = get_display(display, None)?;
+    unix::AsyncClient::open_smol(&display).await.map(AsyncClient)
+}
+
+/// Waits for the Sawfish server to start and opens a connection to it using
+/// the smol runtime.
+///
+/// This is a smol equivalent of [`TokioClient::open_wait`].
+#[cfg(feature = "smol")]
+pub async fn open_smol_wait(
+    display: Option<&str>,
+    timeout: std::time::Duration,
+) -> Result<SmolClient, ConnError> {
+    let display = get_display(display, None)?;
+    unix::AsyncClient::open_smol_wait(&display, timeout).await.map(AsyncClient)
+}
+
+/// A synchronous connection to the Sawfish window manager which uses
+/// [`TokioClient`] underneath.
+///
+/// The client creates and owns its own current-thread Tokio runtime which is
+/// used to drive the asynchronous client.  This allows mostly-async code to
+/// have a few synchronous entry points without having to open a separate
+/// synchronous connection.
+///
+/// Since the client blocks on its runtime, its methods must not be called from
+/// within an asynchronous context.
+///
+/// # Example
+///
+/// ```no_run
+/// let mut client = sawfish_client::BlockingClient::open(None).unwrap();
+/// let sysname = client.eval("(system-name)").unwrap().unwrap();
+/// println!("{}", String::from_utf8_lossy(&sysname));
+/// ```
+#[cfg(feature = "blocking")]
+pub struct BlockingClient {
+    rt: tokio::runtime::Runtime,
+    client: TokioClient,
+}
+
+#[cfg(feature = "blocking")]
+impl BlockingClient {
+    /// Opens a connection to the Sawfish server.
+    ///
+    /// Creates a new current-thread Tokio runtime and opens a [`TokioClient`]
+    /// connection on it.  The `display` argument specifies an optional
+    /// display string, (such as `":0"`).  If not provided, the `DISPLAY`
+    /// environment variable is used.
+    pub fn open(display: Option<&str>) -> Result<Self, ConnError> {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_io()
+            .build()
+            .map_err(ConnError::Runtime)?;
+        let client = rt.block_on(TokioClient::open(display))?;
+        Ok(Self { rt, client })
+    }
+
+    /// Sends a Lisp `form` to the Sawfish server for evaluation and waits for
+    /// a reply.
+    ///
+    /// This is a blocking version of [`AsyncClient::eval`].
+    pub fn eval(
+        &mut self,
+        form: impl AsRef<[u8]>,
+    ) -> Result<EvalResponse, EvalError> {
+        self.rt.block_on(self.client.eval(form))
+    }
+
+    /// Sends a Lisp `form` to the Sawfish server for evaluation but does not
+    /// wait for a reply.
+    ///
+    /// This is a blocking version of [`AsyncClient::send`].
+    pub fn send(&mut self, form: impl AsRef<[u8]>) -> Result<(), EvalError> {
+        self.rt.block_on(self.client.send(form))
+    }
+}
+
+#[cfg(feature = "async")]
+impl<S: AsyncRead + AsyncWrite + Unpin> AsyncClient<S> {
+    /// Constructs a connection to the Sawfish server over an asynchronous Unix
+    /// socket.
+    ///
+    /// Because the creation of an asynchronous Unix socket depends on the async
+    /// runtime, responsibility to open the connection falls on the caller.  Use
+    /// [`server_path`] to determine path to the Unix Socket the Sawfish server
+    /// is (supposed to be) listening on.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use tokio_util::compat::TokioAsyncReadCompatExt;
+    ///
+    /// type TokioClient = sawfish_client::AsyncClient<
+    ///     tokio_util::compat::Compat<tokio::net::UnixStream>>;
+    ///
+    /// async fn open() -> TokioClient {
+    ///     let path = sawfish_client::server_path(None).unwrap();
+    ///     let sock = tokio::net::UnixStream::connect(path).await.unwrap();
+    ///     sawfish_client::AsyncClient::new(sock.compat())
+    /// }
+    /// ```
+    pub fn new(socket: S) -> Self { Self(unix::AsyncClient::new(socket)) }
+
+    /// Sends a Lisp `form` to the Sawfish server for evaluation and waits for
+    /// a reply.
+    ///
+    /// * If there’s an error sending the `form` to the server (e.g. an I/O
+    ///   error), returns an `Err(error)` value.
+    /// * Otherwise, if the `form` has been successfully sent to the server but
+    ///   evaluation failed (e.g. due to syntax error), returns `Ok(Err(data))`
+    ///   value.
+    /// * Otherwise, if the `form` has been successfully executed by the server,
+    ///   returns `Ok(Ok(data))` value.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use futures_util::{AsyncRead, AsyncWrite};
+    ///
+    /// async fn system_name<S: AsyncRead + AsyncWrite + Unpin>(
+    ///     client: &mut sawfish_client::AsyncClient<S>,
+    /// ) -> Option<String> {
+    ///     match client.eval("(system-name)").await {
+    ///         Ok(Ok(data)) => {
+    ///             Some(String::from_utf8_lossy(&data).into_owned())
+    ///         }
+    ///         Ok(Err(data)) => {
+    ///             println!("Error evaluating form: {}",
+    ///                      String::from_utf8_lossy(&data));
+    ///             None
+    ///         }
+    ///         Err(err) => {
+    ///             println!("Communication error: {err}");
+    ///             None
+    ///         }
+    ///     }
+    /// }
+    /// ```
+    pub async fn eval(
+        &mut self,
+        form: impl AsRef<[u8]>,
+    ) -> Result<EvalResponse, EvalError> {
+        self.0.eval(form.as_ref(), false).await
+    }
+
+    /// Checks whether the Sawfish server is responsive.
+    ///
+    /// This is an asynchronous version of [`Client::ping`].
+    pub async fn ping(&mut self) -> Result<(), EvalError> {
+        check_ping(self.eval("t").await)
+    }
+
+    /// Sends a Lisp `form` to the Sawfish server for evaluation and reads the
+    /// reply into a caller-provided buffer.
+    ///
+    /// `buf` is cleared and response data is read into it.  This allows
+    /// reusing the same buffer across calls and avoids allocating a new vector
+    /// for each response.  Returns whether evaluation succeeded.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use futures_util::{AsyncRead, AsyncWrite};
+    ///
+    /// async fn print_all<S: AsyncRead + AsyncWrite + Unpin>(
+    ///     client: &mut sawfish_client::AsyncClient<S>,
+    ///     forms: &[&str],
+    /// ) -> Result<(), sawfish_client::EvalError> {
+    ///     let mut buf = Vec::new();
+    ///     for form in forms {
+    ///         let ch = if client.eval_into(form, &mut buf).await? {
+    ///             '<'
+    ///         } else {
+    ///             '!'
+    ///         };
+    ///         println!("{ch} {}", String::from_utf8_lossy(&buf));
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn eval_into(
+        &mut self,
+        form: impl AsRef<[u8]>,
+        buf: &mut Vec<u8>,
+    ) -> Result<bool, EvalError> {
+        self.0.send_request(form.as_ref(), false).await?;
+        self.0.read_response_into(buf).await
+    }
+
+    /// Evaluates all `forms` in a single round trip by wrapping them in
+    /// a `progn` form.
+    ///
+    /// The forms are evaluated in order and the response is that of the last
+    /// form.  If evaluation of any form fails, the remaining forms aren’t
+    /// evaluated and the error of the failing form is returned.  Use
+    /// [`Self::eval_pipeline`] to get responses to all forms.
+    ///
+    /// # Example
     ///
-    /// type TokioClient = sawfish_client::AsyncClient<
-    ///     tokio_util::compat::Compat<tokio::net::UnixStream>>;
+    /// ```
+    /// use futures_util::{AsyncRead, AsyncWrite};
     ///
-    /// async fn open() -> TokioClient {
-    ///     let path = sawfish_client::server_path(None).unwrap();
-    ///     let sock = tokio::net::UnixStream::connect(path).await.unwrap();
-    ///     sawfish_client::AsyncClient::new(sock.compat())
+    /// async fn reset<S: AsyncRead + AsyncWrite + Unpin>(
+    ///     client: &mut sawfish_client::AsyncClient<S>,
+    /// ) -> Result<sawfish_client::EvalResponse, sawfish_client::EvalError> {
+    ///     let forms = ["(set-screen-viewport 0 0)", "(select-workspace 0)"];
+    ///     client.eval_progn(forms).await
     /// }
     /// ```
-    pub fn new(socket: S) -> Self { Self(unix::AsyncClient(socket)) }
+    pub async fn eval_progn(
+        &mut self,
+        forms: impl IntoIterator<Item = impl AsRef<[u8]>>,
+    ) -> Result<EvalResponse, EvalError> {
+        let mut progn = b"(progn".to_vec();
+        for form in forms {
+            progn.push(b' ');
+            progn.extend_from_slice(form.as_ref());
+        }
+        progn.push(b')');
+        self.eval(progn).await
+    }
 
-    /// Sends a Lisp `form` to the Sawfish server for evaluation and waits for
-    /// a reply.
+    /// Sends all `forms` to the Sawfish server and then reads their responses.
     ///
-    /// * If there’s an error sending the `form` to the server (e.g. an I/O
-    ///   error), returns an `Err(error)` value.
-    /// * Otherwise, if the `form` has been successfully sent to the server but
-    ///   evaluation failed (e.g. due to syntax error), returns `Ok(Err(data))`
-    ///   value.
-    /// * Otherwise, if the `form` has been successfully executed by the server,
-    ///   returns `Ok(Ok(data))` value.
+    /// Since all requests are sent before any response is read, network
+    /// latency is paid once rather than once per form.  Unlike with
+    /// [`Self::eval_progn`], each form is evaluated independently and the
+    /// result contains a response to each of them in the order the forms
+    /// were given.
+    ///
+    /// Sawfish responds to requests in the order it receives them and the
+    /// method relies on that.  Code implementing pipelining with the
+    /// low-level [`Self::send_request`] and [`Self::read_response`] methods
+    /// or with [`Self::split`] halves must likewise preserve the order and
+    /// read exactly one response per non-async request.
     ///
     /// # Example
     ///
     /// ```
     /// use futures_util::{AsyncRead, AsyncWrite};
     ///
-    /// async fn system_name<S: AsyncRead + AsyncWrite + Unpin>(
+    /// async fn query<S: AsyncRead + AsyncWrite + Unpin>(
     ///     client: &mut sawfish_client::AsyncClient<S>,
-    /// ) -> Option<String> {
-    ///     match client.eval("(system-name)").await {
-    ///         Ok(Ok(data)) => {
-    ///             Some(String::from_utf8_lossy(&data).into_owned())
-    ///         }
-    ///         Ok(Err(data)) => {
-    ///             println!("Error evaluating form: {}",
-    ///                      String::from_utf8_lossy(&data));
-    ///             None
-    ///         }
-    ///         Err(err) => {
-    ///             println!("Communication error: {err}");
-    ///             None
-    ///         }
+    /// ) -> Result<(), sawfish_client::EvalError> {
+    ///     let forms = ["(system-name)", "sawfish-version"];
+    ///     let responses = client.eval_pipeline(forms).await?;
+    ///     for (form, res) in forms.iter().zip(responses) {
+    ///         println!("{form}: {res:?}");
     ///     }
+    ///     Ok(())
     /// }
     /// ```
-    pub async fn eval(
+    pub async fn eval_pipeline(
+        &mut self,
+        forms: impl IntoIterator<Item = impl AsRef<[u8]>>,
+    ) -> Result<Vec<EvalResponse>, EvalError> {
+        let mut count = 0;
+        for form in forms {
+            self.0.send_request(form.as_ref(), false).await?;
+            count += 1;
+        }
+        let mut responses = Vec::with_capacity(count);
+        for _ in 0..count {
+            responses.push(self.0.read_response().await?);
+        }
+        Ok(responses)
+    }
+
+    /// Sends a Lisp `form` to the Sawfish server without reading the response.
+    ///
+    /// This is an asynchronous version of [`Client::send_request`].  The same
+    /// caveats about keeping requests and responses balanced apply.  To send
+    /// requests and read responses from separate tasks, use [`Self::split`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use futures_util::{AsyncRead, AsyncWrite};
+    ///
+    /// async fn pipeline<S: AsyncRead + AsyncWrite + Unpin>(
+    ///     client: &mut sawfish_client::AsyncClient<S>,
+    ///     forms: &[&str],
+    /// ) -> Result<Vec<sawfish_client::EvalResponse>, sawfish_client::EvalError>
+    /// {
+    ///     for form in forms {
+    ///         client.send_request(form, false).await?;
+    ///     }
+    ///     let mut responses = Vec::with_capacity(forms.len());
+    ///     for _ in forms {
+    ///         responses.push(client.read_response().await?);
+    ///     }
+    ///     Ok(responses)
+    /// }
+    /// ```
+    pub async fn send_request(
         &mut self,
         form: impl AsRef<[u8]>,
-    ) -> Result<EvalResponse, EvalError> {
-        self.0.eval(form.as_ref(), false).await
+        is_async: bool,
+    ) -> Result<(), EvalError> {
+        self.0.send_request(form.as_ref(), is_async).await
+    }
+
+    /// Reads a response to a request sent with [`Self::send_request`].
+    pub async fn read_response(&mut self) -> Result<EvalResponse, EvalError> {
+        self.0.read_response().await
+    }
+
+    /// Sets maximum length of response data.
+    ///
+    /// This is an asynchronous version of [`Client::set_max_response`].  The
+    /// limit is inherited by halves created with [`Self::split`].
+    pub fn set_max_response(&mut self, max_response: usize) {
+        self.0.max_response = max_response;
+    }
+
+    /// Sets maximum length of a form.
+    ///
+    /// This is an asynchronous version of [`Client::set_max_request`].  The
+    /// limit is inherited by halves created with [`Self::split`].
+    pub fn set_max_request(&mut self, max_request: usize) {
+        self.0.max_request = max_request;
+    }
+
+    /// Sends a Lisp `form` to the Sawfish server for evaluation and reads the
+    /// reply into a buffer taken from `pool`.
+    ///
+    /// Like [`Self::eval`] but the response data is held in a buffer which is
+    /// returned to the pool once dropped.  See [`BufferPool`] for an example.
+    pub async fn eval_pooled(
+        &mut self,
+        form: impl AsRef<[u8]>,
+        pool: &BufferPool,
+    ) -> Result<PooledResponse, EvalError> {
+        self.0.send_request(form.as_ref(), false).await?;
+        self.0.read_response_pooled(pool).await
     }
 
     /// Sends a Lisp `form` to the Sawfish server for evaluation but does not
@@ -266,7 +1845,9 @@ impl<S: AsyncRead + AsyncWrite + Unpin> AsyncClient<S> {
     ///     x: u32,
     ///     y: u32,
     /// ) {
-    ///     let form = format!("(set-screen-viewport {x} {y})");
+    ///     use sawfish_client::commands::*;
+    ///
+    ///     let form = set_screen_viewport(ViewportX(x), ViewportY(y));
     ///     if let Err(err) = client.send(&form).await {
     ///         println!("Communication error: {err}");
     ///     }
@@ -278,6 +1859,101 @@ impl<S: AsyncRead + AsyncWrite + Unpin> AsyncClient<S> {
     ) -> Result<(), EvalError> {
         self.0.eval(form.as_ref(), true).await.map(|_| ())
     }
+
+    /// Splits the connection into sending and receiving halves.
+    ///
+    /// This allows sending requests from one task and reading responses in
+    /// another.  The halves offer low-level interface and the caller is
+    /// responsible for reading exactly one response for each request sent
+    /// with `is_async` argument set to `false`.  Otherwise, requests and
+    /// responses get out of sync.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use futures_util::{AsyncRead, AsyncWrite};
+    ///
+    /// async fn pipeline<S: AsyncRead + AsyncWrite + Unpin>(
+    ///     client: sawfish_client::AsyncClient<S>,
+    ///     forms: &[&str],
+    /// ) -> Result<Vec<sawfish_client::EvalResponse>, sawfish_client::EvalError>
+    /// {
+    ///     let (mut tx, mut rx) = client.split();
+    ///     for form in forms {
+    ///         tx.send_request(form, false).await?;
+    ///     }
+    ///     let mut responses = Vec::with_capacity(forms.len());
+    ///     for _ in forms {
+    ///         responses.push(rx.read_response().await?);
+    ///     }
+    ///     Ok(responses)
+    /// }
+    /// ```
+    pub fn split(self) -> (AsyncSender<S>, AsyncReceiver<S>) {
+        let (tx, rx) = self.0.split();
+        (AsyncSender(tx), AsyncReceiver(rx))
+    }
+}
+
+/// Exposes file descriptor of the underlying stream.
+///
+/// Reading from or writing to the file descriptor directly interferes with
+/// the protocol.  Requests and responses will get out of sync and subsequent
+/// [`AsyncClient::eval`] calls will return garbage or fail.
+#[cfg(feature = "async")]
+impl<S: std::os::fd::AsFd> std::os::fd::AsFd for AsyncClient<S> {
+    fn as_fd(&self) -> std::os::fd::BorrowedFd<'_> { self.0.sock.as_fd() }
+}
+
+/// Exposes file descriptor of the underlying stream.
+///
+/// This is provided in addition to [`AsFd`](std::os::fd::AsFd) since some
+/// stream adapters (such as `Compat` from `tokio_util`) implement only
+/// `AsRawFd`.  The same caveats apply.
+#[cfg(feature = "async")]
+impl<S: std::os::fd::AsRawFd> std::os::fd::AsRawFd for AsyncClient<S> {
+    fn as_raw_fd(&self) -> std::os::fd::RawFd { self.0.sock.as_raw_fd() }
+}
+
+/// The sending half of an [`AsyncClient`] created by [`AsyncClient::split`].
+#[cfg(feature = "async")]
+pub struct AsyncSender<S>(unix::AsyncClient<futures_util::io::WriteHalf<S>>);
+
+/// The receiving half of an [`AsyncClient`] created by [`AsyncClient::split`].
+#[cfg(feature = "async")]
+pub struct AsyncReceiver<S>(unix::AsyncClient<futures_util::io::ReadHalf<S>>);
+
+#[cfg(feature = "async")]
+impl<S: AsyncWrite> AsyncSender<S> {
+    /// Sends a Lisp `form` to the Sawfish server for evaluation.
+    ///
+    /// If `is_async` is `false`, the server will send a response which must be
+    /// read with [`AsyncReceiver::read_response`].  Otherwise, the server does
+    /// not send a response.
+    pub async fn send_request(
+        &mut self,
+        form: impl AsRef<[u8]>,
+        is_async: bool,
+    ) -> Result<(), EvalError> {
+        self.0.send_request(form.as_ref(), is_async).await
+    }
+}
+
+#[cfg(feature = "async")]
+impl<S: AsyncRead> AsyncReceiver<S> {
+    /// Reads a response to a request sent with [`AsyncSender::send_request`].
+    pub async fn read_response(&mut self) -> Result<EvalResponse, EvalError> {
+        self.0.read_response().await
+    }
+
+    /// Reads a response to a request sent with [`AsyncSender::send_request`]
+    /// into a buffer taken from `pool`; see [`AsyncClient::eval_pooled`].
+    pub async fn read_response_pooled(
+        &mut self,
+        pool: &BufferPool,
+    ) -> Result<PooledResponse, EvalError> {
+        self.0.read_response_pooled(pool).await
+    }
 }
 
 
@@ -292,18 +1968,138 @@ impl<S: AsyncRead + AsyncWrite + Unpin> AsyncClient<S> {
 pub fn server_path(
     display: Option<&str>,
 ) -> Result<std::path::PathBuf, ConnError> {
-    get_display(display).and_then(|display| unix::server_path(&display))
+    get_display(display, None).and_then(|display| unix::server_path(&display))
 }
 
 
-/// Unwraps the option or returns value of $DISPLAY environment variable.
-fn get_display(
+/// Sets the hostname used to determine path of the Unix socket.
+///
+/// The socket Sawfish listens on is named after the display which includes
+/// canonical hostname of the system.  By default, the client determines it
+/// through DNS which, in containers or environments with misconfigured DNS,
+/// may return a name different than the one Sawfish used.  In that case,
+/// connecting fails as if the server weren’t running.
+///
+/// The system name is determined on first use as follows:
+///
+/// 1. the name set with this function, if any;
+/// 2. value of `SAWFISH_HOSTNAME` environment variable if it’s non-empty;
+/// 3. canonical hostname looked up with DNS.
+///
+/// Since the name is determined once, this must be called before opening
+/// any connections.  Returns `false` if the name has already been determined
+/// or `name` is empty and thus the call had no effect.
+///
+/// # Example
+///
+/// ```no_run
+/// sawfish_client::set_system_name("darkstar.example.net");
+/// let client = sawfish_client::Client::open(Some(":0")).unwrap();
+/// assert_eq!("darkstar.example.net:0.0", client.display());
+/// ```
+pub fn set_system_name(name: &str) -> bool { unix::set_system_name(name) }
+
+/// Returns the canonical display name the client would connect to.
+///
+/// If `display` is `None`, uses value of `DISPLAY` environment variable.  The
+/// name is then canonicalised the same way as when opening a connection
+/// (e.g. `":0"` → `"example.com:0.0"`).  The result is what the path of the
+/// Unix socket is derived from and what [`Client::display`] returns for
+/// connections over Unix socket.
+///
+/// This is useful for tools which need to locate the Sawfish server the same
+/// way the client does.
+///
+/// # Example
+///
+/// ```no_run
+/// let display = sawfish_client::resolve_display(None).unwrap();
+/// println!("Sawfish display: {display}");
+/// ```
+pub fn resolve_display(display: Option<&str>) -> Result<String, ConnError> {
+    get_display(display, None).map(|display| unix::canonical_display(&display))
+}
+
+/// Returns file name of the Unix socket the Sawfish server is (or should be)
+/// listening on.
+///
+/// This is the last component of the socket path, i.e. the canonical display
+/// name as returned by [`resolve_display`], without the
+/// `/tmp/.sawfish-$LOGNAME` directory.  This is useful for tools which scan
+/// the socket directory.  Unlike the full path, determining the file name
+/// doesn’t require `LOGNAME` to be set.
+///
+/// # Example
+///
+/// ```no_run
+/// let name = sawfish_client::socket_file_name(Some(":0")).unwrap();
+/// println!("Looking for {}", name.display());
+/// ```
+pub fn socket_file_name(
     display: Option<&str>,
-) -> Result<std::borrow::Cow<'_, str>, ConnError> {
+) -> Result<std::ffi::OsString, ConnError> {
+    resolve_display(display).map(std::ffi::OsString::from)
+}
+
+/// Returns name of the display to connect to.
+///
+/// The name is determined as follows:
+///
+/// 1. `display` if it’s `Some`;
+/// 2. otherwise, value of `DISPLAY` environment variable if it’s set;
+/// 3. if the above yields no or empty name, `fallback` if it’s non-empty.
+///
+/// Fails with [`ConnError::NoDisplay`] if that doesn’t yield a non-empty
+/// name.  Functions opening connections use this with `fallback` of `None`.
+/// Applications which have a default display configured (e.g. in
+/// a configuration file) can use this to determine the display and pass it
+/// to [`Client::open`].
+///
+/// # Example
+///
+/// ```no_run
+/// let config = std::fs::read_to_string("display.conf").ok();
+/// let display =
+///     sawfish_client::get_display(None, config.as_deref().map(str::trim));
+/// let client = sawfish_client::Client::open(Some(&display.unwrap()));
+/// ```
+pub fn get_display<'a>(
+    display: Option<&'a str>,
+    fallback: Option<&'a str>,
+) -> Result<Cow<'a, str>, ConnError> {
+    get_display_with(display, || std::env::var("DISPLAY").ok(), fallback)
+}
+
+/// Name of environment variable specifying default timeout in milliseconds.
+const TIMEOUT_ENV: &str = "SAWFISH_TIMEOUT_MS";
+
+/// Returns default connection timeout read from [`TIMEOUT_ENV`] environment
+/// variable; see [`Client::open`].
+fn default_timeout() -> Option<std::time::Duration> {
+    parse_timeout(std::env::var(TIMEOUT_ENV).ok().as_deref())
+}
+
+/// Parses value of [`TIMEOUT_ENV`] environment variable.  Returns `None` if
+/// the variable is unset, isn’t a number or is zero.
+fn parse_timeout(value: Option<&str>) -> Option<std::time::Duration> {
+    let millis = value?.trim().parse::<u64>().ok()?;
+    (millis != 0).then(|| std::time::Duration::from_millis(millis))
+}
+
+/// Returns name of the display to connect to using `env` to read `DISPLAY`
+/// environment variable; see [`get_display`].
+fn get_display_with<'a>(
+    display: Option<&'a str>,
+    env: impl FnOnce() -> Option<String>,
+    fallback: Option<&'a str>,
+) -> Result<Cow<'a, str>, ConnError> {
     display
         .map(Cow::Borrowed)
-        .or_else(|| std::env::var("DISPLAY").map(Cow::Owned).ok())
+        .or_else(|| env().map(Cow::Owned))
         .filter(|display| !display.is_empty())
+        .or_else(|| {
+            fallback.filter(|display| !display.is_empty()).map(Cow::from)
+        })
         .ok_or(ConnError::NoDisplay)
 }
 
@@ -318,10 +2114,30 @@ mod x11 {
         pub fn fallback(
             _display: &str,
             err: ConnError,
+            _timeout: Option<std::time::Duration>,
         ) -> Result<Self, ConnError> {
             Err(err)
         }
 
+        pub fn probe(
+            _display: &str,
+            err: ConnError,
+        ) -> Result<bool, ConnError> {
+            use std::io::ErrorKind;
+
+            match err {
+                ConnError::Io(_, err)
+                    if matches!(
+                        err.kind(),
+                        ErrorKind::NotFound | ErrorKind::ConnectionRefused
+                    ) =>
+                {
+                    Ok(false)
+                }
+                err => Err(err),
+            }
+        }
+
         pub fn eval(
             &mut self,
             _form: &[u8],
@@ -329,5 +2145,245 @@ mod x11 {
         ) -> Result<EvalResponse, EvalError> {
             match *self {}
         }
+
+        pub fn eval_to_writer(
+            &mut self,
+            _form: &[u8],
+            _out: &mut impl std::io::Write,
+        ) -> Result<bool, EvalError> {
+            match *self {}
+        }
+
+        pub fn eval_multi(
+            &mut self,
+            _form: &[u8],
+            _count: usize,
+        ) -> Result<Vec<EvalResponse>, EvalError> {
+            match *self {}
+        }
+
+        pub fn batch_send(
+            &mut self,
+            _forms: &[impl AsRef<[u8]>],
+        ) -> Result<(), EvalError> {
+            match *self {}
+        }
+
+        pub fn eval_with_progress(
+            &mut self,
+            _form: &[u8],
+            _progress: impl FnMut(usize, usize),
+        ) -> Result<EvalResponse, EvalError> {
+            match *self {}
+        }
+
+        pub fn eval_frame(
+            &mut self,
+            _form: &[u8],
+        ) -> Result<Vec<u8>, EvalError> {
+            match *self {}
+        }
+
+        pub fn drain_pending(
+            &mut self,
+        ) -> Result<Option<EvalResponse>, EvalError> {
+            match *self {}
+        }
+
+        pub fn send_request(
+            &mut self,
+            _form: &[u8],
+            _is_async: bool,
+        ) -> Result<(), EvalError> {
+            match *self {}
+        }
+
+        pub fn read_response(&mut self) -> Result<EvalResponse, EvalError> {
+            match *self {}
+        }
+
+        pub fn set_max_response(&mut self, _max_response: usize) {
+            match *self {}
+        }
+
+        pub fn set_max_request(&mut self, _max_request: usize) {
+            match *self {}
+        }
+
+        pub fn read_timeout(&self) -> Option<std::time::Duration> {
+            match *self {}
+        }
+
+        pub fn set_read_timeout(
+            &mut self,
+            _timeout: Option<std::time::Duration>,
+        ) -> std::io::Result<()> {
+            match *self {}
+        }
+
+        pub fn set_write_timeout(
+            &mut self,
+            _timeout: Option<std::time::Duration>,
+        ) -> std::io::Result<()> {
+            match *self {}
+        }
+
+        pub fn as_fd(&self) -> std::os::fd::BorrowedFd<'_> { match *self {} }
+
+        pub fn display(&self) -> &str { match *self {} }
+
+        pub fn request_window(&self) -> u32 { match *self {} }
+
+        pub fn created_at(&self) -> std::time::Instant { match *self {} }
+
+        pub fn last_used(&self) -> std::time::Instant { match *self {} }
+
+        pub fn last_was_error(&self) -> Option<bool> { match *self {} }
+
+        pub fn io_stats(&self) -> crate::IoStats { match *self {} }
+    }
+}
+
+
+#[test]
+fn test_capabilities() {
+    let unix = Transport::Unix.capabilities();
+    assert!(unix.supports_read_timeout);
+    assert!(unix.supports_write_timeout);
+    assert!(unix.supports_peer_uid);
+    assert!(unix.supports_pipelining);
+    assert!(!unix.is_network_capable);
+
+    let x11 = Transport::X11.capabilities();
+    assert!(x11.supports_read_timeout);
+    assert!(!x11.supports_write_timeout);
+    assert!(!x11.supports_peer_uid);
+    assert!(!x11.supports_pipelining);
+    assert!(x11.is_network_capable);
+}
+
+#[test]
+fn test_eval_response_ext() {
+    let ok: EvalResponse = Ok(b"\"host\"".to_vec());
+    let err: EvalResponse = Err(b"(void-value foo \xff)".to_vec());
+
+    assert!(ok.is_success());
+    assert!(!err.is_success());
+
+    assert_eq!(Some("\"host\""), ok.success_str().as_deref());
+    assert_eq!(None, err.success_str());
+
+    assert_eq!(None, ok.error_str());
+    assert_eq!(Some("(void-value foo \u{fffd})"), err.error_str().as_deref());
+
+    assert!(!ok.looks_like_error());
+    assert!(!err.looks_like_error());
+    let soft: EvalResponse = Ok(br#"(error "Bad argument" 42)"#.to_vec());
+    assert!(soft.looks_like_error());
+    let hard: EvalResponse = Err(b"(void-value foo)".to_vec());
+    assert!(hard.looks_like_error());
+
+    assert_eq!(Ok("\"host\"".into()), ok.clone().into_result_str());
+    assert_eq!(
+        Err("(void-value foo \u{fffd})".into()),
+        err.clone().into_result_str()
+    );
+
+    assert_eq!(Ok(b"\"host\"".to_vec()), ok.or_server_err());
+    let err = err.or_server_err().unwrap_err();
+    assert_eq!("error", err.kind);
+    assert_eq!("error: (void-value foo \u{fffd})", err.to_string());
+
+    let err: EvalResponse = Err(b"(void-value foo)".to_vec());
+    let err = err.or_server_err().unwrap_err();
+    assert_eq!("void-value", err.kind);
+    assert_eq!(None, err.message);
+    assert_eq!("void-value", err.to_string());
+
+    let err: EvalResponse = Err(br#"(error "No such file")"#.to_vec());
+    let err = err.or_server_err().unwrap_err();
+    assert_eq!("error: No such file", err.to_string());
+
+    let err: EvalResponse = Err(b"bogus".to_vec());
+    let err = err.or_server_err().unwrap_err();
+    assert_eq!("error", err.kind);
+    assert_eq!("error: bogus", err.to_string());
+}
+
+#[test]
+fn test_get_display() {
+    #[track_caller]
+    fn check(
+        want: Option<&str>,
+        display: Option<&str>,
+        env: Option<&str>,
+        fallback: Option<&str>,
+    ) {
+        let got = get_display_with(display, || env.map(String::from), fallback);
+        match (want, got) {
+            (Some(want), Ok(got)) => assert_eq!(want, got),
+            (None, Err(ConnError::NoDisplay)) => (),
+            (want, got) => panic!("Expected {want:?} but got {got:?}"),
+        }
     }
+
+    // Argument takes precedence over environment which takes precedence over
+    // fallback.
+    check(Some(":1"), Some(":1"), Some(":2"), Some(":3"));
+    check(Some(":1"), Some(":1"), None, Some(":3"));
+    check(Some(":2"), None, Some(":2"), Some(":3"));
+    check(Some(":3"), None, None, Some(":3"));
+
+    // Empty values are treated as missing.
+    check(Some(":3"), None, Some(""), Some(":3"));
+    check(Some(":3"), Some(""), None, Some(":3"));
+    check(None, None, Some(""), Some(""));
+    check(None, None, None, None);
+}
+
+#[test]
+fn test_parse_timeout() {
+    use std::time::Duration;
+
+    assert_eq!(None, parse_timeout(None));
+    assert_eq!(None, parse_timeout(Some("")));
+    assert_eq!(None, parse_timeout(Some("0")));
+    assert_eq!(None, parse_timeout(Some("-5")));
+    assert_eq!(None, parse_timeout(Some("1.5")));
+    assert_eq!(Some(Duration::from_millis(250)), parse_timeout(Some("250")));
+    assert_eq!(Some(Duration::from_secs(2)), parse_timeout(Some(" 2000\n")));
+}
+
+#[test]
+fn test_resolve_display_no_display() {
+    assert!(matches!(resolve_display(Some("")), Err(ConnError::NoDisplay)));
+}
+
+#[test]
+fn test_form() {
+    let form = Form::new("(system-name)");
+    assert!(matches!(form.0, Cow::Borrowed(_)));
+    assert_eq!(b"(system-name)", form.as_bytes());
+
+    let form = Form::from_bytes(b"(system-name)");
+    assert!(matches!(form.0, Cow::Borrowed(_)));
+    assert_eq!(b"(system-name)", form.as_ref());
+
+    let form = Form::call("display-message", [sexp::quote_string("a b")]);
+    assert_eq!(br#"(display-message "a b")"#, form.as_bytes());
+
+    assert_eq!(Form::new("t"), Form::from("t"));
+    assert_eq!(Form::new("t"), Form::from(&b"t"[..]));
+    assert_eq!(Form::new("t"), Form::from(String::from("t")));
+    assert_eq!(Form::new("t"), Form::from(b"t".to_vec()));
+    assert_eq!(Form::new("t"), Form::from(Cow::Borrowed(&b"t"[..])));
+
+    // Owned vector is moved into the form rather than copied.
+    let data = b"(system-name)".to_vec();
+    let ptr = data.as_ptr();
+    let form = Form::from(data);
+    assert!(matches!(form.0, Cow::Owned(_)));
+    assert_eq!(ptr, form.as_bytes().as_ptr());
+    let form = Form::from(Cow::<[u8]>::Owned(form.0.into_owned()));
+    assert_eq!(ptr, form.as_bytes().as_ptr());
 }
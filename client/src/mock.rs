@@ -0,0 +1,163 @@
+// sawfish-client -- client library to communicate with Sawfish window manager
+// © 2025 by Michał Nazarewicz <mina86@mina86.com>
+
+//! Scripted stream for deterministic tests.
+//!
+//! The stream is used with [`crate::unix::AsyncClient`] and thus the module
+//! is available only with `async` feature.
+
+use std::collections::VecDeque;
+use std::io::{Read, Write};
+
+/// A stream which returns scripted data on reads and records writes.
+///
+/// Each chunk added with [`Self::chunk`] is returned by a separate read call
+/// (split further if the caller’s buffer is smaller) which allows testing
+/// handling of partial reads.  Once all chunks are consumed, reads report
+/// end of file.
+///
+/// Writes are accepted until the limit set with [`Self::write_limit`] is
+/// reached at which point they fail with `BrokenPipe`.  A single write call
+/// accepts at most number of bytes set with [`Self::max_write`] which allows
+/// testing handling of short writes.
+#[derive(Default)]
+pub struct MockStream {
+    chunks: VecDeque<Vec<u8>>,
+    write_limit: Option<usize>,
+    max_write: Option<usize>,
+    /// Data written to the stream.
+    pub written: Vec<u8>,
+}
+
+impl MockStream {
+    /// Constructs a stream with no data to read.
+    pub fn new() -> Self { Self::default() }
+
+    /// Appends a chunk of data returned by a single read.
+    pub fn chunk(mut self, data: &[u8]) -> Self {
+        self.chunks.push_back(data.to_vec());
+        self
+    }
+
+    /// Sets number of bytes after which writes fail.
+    pub fn write_limit(mut self, limit: usize) -> Self {
+        self.write_limit = Some(limit);
+        self
+    }
+
+    /// Sets maximum number of bytes accepted by a single write call.
+    pub fn max_write(mut self, max: usize) -> Self {
+        self.max_write = Some(max);
+        self
+    }
+
+    /// Appends a response with given status and data in native byte order,
+    /// split into chunks of at most `chunk_size` bytes.
+    pub fn response(
+        mut self,
+        status: u8,
+        data: &[u8],
+        chunk_size: usize,
+    ) -> Self {
+        let len = u64::try_from(data.len() + 1).unwrap();
+        let bytes = [&len.to_ne_bytes()[..], &[status], data].concat();
+        for chunk in bytes.chunks(chunk_size) {
+            self = self.chunk(chunk);
+        }
+        self
+    }
+}
+
+impl Read for MockStream {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let Some(mut chunk) = self.chunks.pop_front() else { return Ok(0) };
+        let len = chunk.len().min(buf.len());
+        buf[..len].copy_from_slice(&chunk[..len]);
+        if len < chunk.len() {
+            chunk.drain(..len);
+            self.chunks.push_front(chunk);
+        }
+        Ok(len)
+    }
+}
+
+impl Write for MockStream {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let limit = self.write_limit.unwrap_or(usize::MAX);
+        let len = buf
+            .len()
+            .min(limit - self.written.len())
+            .min(self.max_write.unwrap_or(usize::MAX));
+        if len == 0 && !buf.is_empty() {
+            return Err(std::io::ErrorKind::BrokenPipe.into());
+        }
+        self.written.extend_from_slice(&buf[..len]);
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> { Ok(()) }
+}
+
+impl futures_util::io::AsyncRead for MockStream {
+    fn poll_read(
+        self: core::pin::Pin<&mut Self>,
+        _cx: &mut core::task::Context<'_>,
+        buf: &mut [u8],
+    ) -> core::task::Poll<std::io::Result<usize>> {
+        core::task::Poll::Ready(self.get_mut().read(buf))
+    }
+}
+
+impl futures_util::io::AsyncWrite for MockStream {
+    fn poll_write(
+        self: core::pin::Pin<&mut Self>,
+        _cx: &mut core::task::Context<'_>,
+        buf: &[u8],
+    ) -> core::task::Poll<std::io::Result<usize>> {
+        core::task::Poll::Ready(self.get_mut().write(buf))
+    }
+
+    fn poll_flush(
+        self: core::pin::Pin<&mut Self>,
+        _cx: &mut core::task::Context<'_>,
+    ) -> core::task::Poll<std::io::Result<()>> {
+        core::task::Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(
+        self: core::pin::Pin<&mut Self>,
+        _cx: &mut core::task::Context<'_>,
+    ) -> core::task::Poll<std::io::Result<()>> {
+        core::task::Poll::Ready(Ok(()))
+    }
+}
+
+
+#[test]
+fn test_mock_stream() {
+    let mut stream = MockStream::new().chunk(b"hello").chunk(b"world");
+    let mut buf = [0; 3];
+    assert_eq!(3, stream.read(&mut buf).unwrap());
+    assert_eq!(b"hel", &buf);
+    assert_eq!(2, stream.read(&mut buf).unwrap());
+    assert_eq!(b"lo", &buf[..2]);
+    let mut rest = Vec::new();
+    stream.read_to_end(&mut rest).unwrap();
+    assert_eq!(b"world", rest.as_slice());
+    assert_eq!(0, stream.read(&mut buf).unwrap());
+
+    stream.write_all(b"data").unwrap();
+    assert_eq!(b"data", stream.written.as_slice());
+
+    let mut stream = MockStream::new().write_limit(3);
+    assert_eq!(2, stream.write(b"ab").unwrap());
+    assert_eq!(1, stream.write(b"cd").unwrap());
+    let err = stream.write(b"ef").unwrap_err();
+    assert_eq!(std::io::ErrorKind::BrokenPipe, err.kind());
+    assert_eq!(b"abc", stream.written.as_slice());
+
+    let mut stream = MockStream::new().max_write(2);
+    assert_eq!(2, stream.write(b"abc").unwrap());
+    stream.write_all(b"def").unwrap();
+    assert_eq!(b"abdef", stream.written.as_slice());
+}
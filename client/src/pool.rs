@@ -0,0 +1,156 @@
+// sawfish-client -- client library to communicate with Sawfish window manager
+// © 2025 by Michał Nazarewicz <mina86@mina86.com>
+
+//! Pool of response buffers; see [`BufferPool`].
+
+use std::sync::{Arc, Mutex};
+
+/// Maximum capacity of a buffer kept in the free-list.  Larger buffers are
+/// shrunk when returned to the pool.
+const MAX_POOLED_CAPACITY: usize = 1 << 20;
+
+/// A free-list of response buffers.
+///
+/// Used with [`crate::AsyncClient::eval_pooled`] and
+/// [`crate::AsyncReceiver::read_response_pooled`] to reuse response buffers
+/// rather than allocating a new vector for each response.  This reduces
+/// allocator pressure when pipelining many requests.  Buffers are returned to
+/// the pool when [`PooledBuffer`] is dropped.  Buffers with capacity over
+/// 1 MiB are shrunk at that point so that a single large response doesn’t
+/// pin memory for the lifetime of the pool.
+///
+/// The pool is cheap to clone and clones share the same free-list.
+///
+/// # Example
+///
+/// ```
+/// use futures_util::{AsyncRead, AsyncWrite};
+///
+/// async fn print_all<S: AsyncRead + AsyncWrite + Unpin>(
+///     client: &mut sawfish_client::AsyncClient<S>,
+///     forms: &[&str],
+/// ) -> Result<(), sawfish_client::EvalError> {
+///     let pool = sawfish_client::BufferPool::new(4);
+///     for form in forms {
+///         let (ch, buf) = match client.eval_pooled(form, &pool).await? {
+///             Ok(buf) => ('<', buf),
+///             Err(buf) => ('!', buf),
+///         };
+///         println!("{ch} {}", String::from_utf8_lossy(&buf));
+///     }
+///     Ok(())
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct BufferPool(Arc<PoolInner>);
+
+#[derive(Debug)]
+struct PoolInner {
+    free: Mutex<Vec<Vec<u8>>>,
+    /// Maximum number of buffers kept in the free-list.
+    max_buffers: usize,
+}
+
+/// A response buffer borrowed from a [`BufferPool`].
+///
+/// Dereferences to the response data.  When dropped, the buffer is returned
+/// to the pool (unless the pool is full).
+#[derive(Debug)]
+pub struct PooledBuffer {
+    buf: Vec<u8>,
+    pool: BufferPool,
+}
+
+/// Result of a form evaluation with data held in a pooled buffer; see
+/// [`crate::EvalResponse`].
+pub type PooledResponse = Result<PooledBuffer, PooledBuffer>;
+
+impl BufferPool {
+    /// Constructs a new pool keeping at most `max_buffers` free buffers.
+    pub fn new(max_buffers: usize) -> Self {
+        Self(Arc::new(PoolInner {
+            free: Mutex::new(Vec::with_capacity(max_buffers)),
+            max_buffers,
+        }))
+    }
+
+    /// Takes a buffer from the free-list or allocates a new one.
+    pub(crate) fn get(&self) -> PooledBuffer {
+        let buf = self.0.free.lock().unwrap().pop().unwrap_or_default();
+        PooledBuffer { buf, pool: self.clone() }
+    }
+
+    /// Returns number of buffers in the free-list.
+    #[cfg(test)]
+    fn available(&self) -> usize { self.0.free.lock().unwrap().len() }
+}
+
+impl PooledBuffer {
+    /// Returns the underlying vector.  The vector isn’t returned to the pool.
+    pub fn into_vec(mut self) -> Vec<u8> { core::mem::take(&mut self.buf) }
+
+    /// Returns mutable reference to the underlying vector.
+    pub(crate) fn as_mut_vec(&mut self) -> &mut Vec<u8> { &mut self.buf }
+}
+
+impl core::ops::Deref for PooledBuffer {
+    type Target = [u8];
+    fn deref(&self) -> &[u8] { &self.buf }
+}
+
+impl AsRef<[u8]> for PooledBuffer {
+    fn as_ref(&self) -> &[u8] { &self.buf }
+}
+
+impl Drop for PooledBuffer {
+    fn drop(&mut self) {
+        if self.buf.capacity() == 0 {
+            return;
+        }
+        let mut free = self.pool.0.free.lock().unwrap();
+        if free.len() < self.pool.0.max_buffers {
+            let mut buf = core::mem::take(&mut self.buf);
+            buf.clear();
+            buf.shrink_to(MAX_POOLED_CAPACITY);
+            free.push(buf);
+        }
+    }
+}
+
+
+#[test]
+fn test_buffer_pool() {
+    let pool = BufferPool::new(1);
+    let mut first = pool.get();
+    first.as_mut_vec().extend_from_slice(b"first");
+    let ptr = first.as_ptr();
+    let second = pool.get();
+    assert_eq!(0, pool.available());
+
+    // Returned buffers are cleared and reused.
+    core::mem::drop(first);
+    assert_eq!(1, pool.available());
+    let mut third = pool.get();
+    assert_eq!(0, pool.available());
+    assert_eq!(b"", &*third);
+    third.as_mut_vec().push(b'x');
+    assert_eq!(ptr, third.as_ptr());
+
+    // Pool is full; only one buffer is kept.
+    core::mem::drop(third);
+    core::mem::drop(second);
+    assert_eq!(1, pool.available());
+
+    // Detached buffers aren’t returned.
+    let mut buf = pool.get();
+    buf.as_mut_vec().extend_from_slice(b"data");
+    assert_eq!(b"data", buf.into_vec().as_slice());
+    assert_eq!(0, pool.available());
+
+    // Large buffers are shrunk when returned.
+    let mut buf = pool.get();
+    buf.as_mut_vec().reserve(4 * MAX_POOLED_CAPACITY);
+    core::mem::drop(buf);
+    let buf = pool.get();
+    assert!(buf.buf.capacity() < 4 * MAX_POOLED_CAPACITY);
+}
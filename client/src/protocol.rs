@@ -0,0 +1,316 @@
+// sawfish-client -- client library to communicate with Sawfish window manager
+// © 2025 by Michał Nazarewicz <mina86@mina86.com>
+
+//! Wire format of the Unix socket protocol.
+//!
+//! A request consists of a one-byte request type (`0` if the server is
+//! expected to send a response and `1` otherwise), length of the form encoded
+//! as a 64-bit integer and the form itself.
+//!
+//! A response consists of a 64-bit length, a one-byte status (`1` if
+//! evaluation succeeded) and the response data.  The length includes the
+//! status byte.
+//!
+//! Lengths are encoded in native byte order of the host the Sawfish server
+//! runs on (see [`ByteOrder`]).
+
+use crate::{EvalError, EvalResponse};
+
+/// Length of a request header: one-byte request type followed by a 64-bit
+/// length of the form.
+pub const REQUEST_HEADER_LEN: usize = 1 + 8;
+
+/// Length of a response header: 64-bit length of the response followed by
+/// one-byte status.
+pub const RESPONSE_HEADER_LEN: usize = 8 + 1;
+
+/// Length of the response length which precedes the status byte in
+/// a response header.  The status byte isn’t sent if the length is zero so
+/// the two parts of the header are read separately.
+pub(crate) const RESPONSE_LENGTH_LEN: usize = RESPONSE_HEADER_LEN - 1;
+
+/// Largest plausible length of response data.
+///
+/// Sawfish never sends responses this long so a larger declared length means
+/// the client isn’t reading a response header, i.e. requests and responses
+/// got out of sync.  Such lengths are rejected with [`EvalError::Desync`]
+/// regardless of the configured limit; see [`check_response_len`].
+pub(crate) const MAX_RESPONSE_LEN: u64 = 1 << 32;
+
+/// Byte order used to encode lengths in requests and responses.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum ByteOrder {
+    /// Native byte order of the host.
+    #[default]
+    Native,
+    /// Little-endian byte order.
+    Little,
+    /// Big-endian byte order.
+    Big,
+}
+
+impl ByteOrder {
+    /// Encodes a length using the byte order.
+    pub fn encode(self, value: u64) -> [u8; 8] {
+        match self {
+            Self::Native => value.to_ne_bytes(),
+            Self::Little => value.to_le_bytes(),
+            Self::Big => value.to_be_bytes(),
+        }
+    }
+
+    /// Decodes a length using the byte order.
+    pub fn decode(self, bytes: [u8; 8]) -> u64 {
+        match self {
+            Self::Native => u64::from_ne_bytes(bytes),
+            Self::Little => u64::from_le_bytes(bytes),
+            Self::Big => u64::from_be_bytes(bytes),
+        }
+    }
+}
+
+/// Returns header of a request for evaluation of a form of given length.
+///
+/// Fails with [`EvalError::RequestTooLarge`] if the length doesn’t fit in
+/// a 64-bit integer.
+pub(crate) fn request_header(
+    form_len: usize,
+    is_async: bool,
+    byte_order: ByteOrder,
+) -> Result<[u8; REQUEST_HEADER_LEN], EvalError> {
+    let req_len = u64::try_from(form_len)
+        .map_err(|_| EvalError::RequestTooLarge(form_len))?;
+    let mut buf = [0u8; REQUEST_HEADER_LEN];
+    buf[0] = u8::from(is_async);
+    buf[1..].copy_from_slice(&byte_order.encode(req_len));
+    Ok(buf)
+}
+
+/// Checks that form isn’t empty and its length doesn’t exceed `max_request`.
+pub(crate) fn check_request_len(
+    form_len: usize,
+    max_request: usize,
+) -> Result<(), EvalError> {
+    if form_len == 0 {
+        Err(EvalError::EmptyForm)
+    } else if form_len > max_request {
+        Err(EvalError::RequestTooLarge(form_len))
+    } else {
+        Ok(())
+    }
+}
+
+/// Checks response data length against `max_response`.
+///
+/// The length is compared as a 64-bit integer so that overly long responses
+/// are rejected on all platforms before anything is allocated.  Returns the
+/// length as `usize` or `Ok(None)` if it exceeds the limit.  Fails with
+/// [`EvalError::Desync`] if the length is larger than [`MAX_RESPONSE_LEN`].
+pub(crate) fn check_response_len(
+    data_len: u64,
+    max_response: usize,
+) -> Result<Option<usize>, EvalError> {
+    if data_len > MAX_RESPONSE_LEN {
+        return Err(EvalError::Desync);
+    }
+    let max = u64::try_from(max_response).unwrap_or(u64::MAX);
+    Ok(if data_len > max { None } else { usize::try_from(data_len).ok() })
+}
+
+/// Decodes response status byte; returns whether evaluation succeeded.
+///
+/// Fails with [`EvalError::Desync`] if the status byte is neither zero nor
+/// one.
+pub(crate) fn decode_status(status: u8) -> Result<bool, EvalError> {
+    match status {
+        0 => Ok(false),
+        1 => Ok(true),
+        _ => Err(EvalError::Desync),
+    }
+}
+
+/// Constructs [`EvalResponse`] from response data and evaluation status.
+pub(crate) fn make_response(success: bool, data: Vec<u8>) -> EvalResponse {
+    if success { Ok(data) } else { Err(data) }
+}
+
+/// Decodes response consisting of a status byte followed by response data.
+///
+/// This is the format of the response without its length, i.e. what the
+/// server stores in the property when using X11 protocol or what follows the
+/// length when using Unix socket.  Fails with [`EvalError::NoResponse`] if
+/// `bytes` is empty and with [`EvalError::Desync`] if the status byte is
+/// invalid.
+///
+/// # Example
+///
+/// ```
+/// use sawfish_client::protocol::split_response;
+///
+/// assert_eq!(Ok(b"t".to_vec()), split_response(b"\x01t").unwrap());
+/// assert_eq!(Err(b"oops".to_vec()), split_response(b"\x00oops").unwrap());
+/// ```
+pub fn split_response(bytes: &[u8]) -> Result<EvalResponse, EvalError> {
+    let (status, data) = bytes.split_first().ok_or(EvalError::NoResponse)?;
+    Ok(make_response(decode_status(*status)?, data.to_vec()))
+}
+
+/// Returns the request which would be sent to the server to evaluate `form`.
+///
+/// The length of the form is encoded in native byte order.  This is exactly
+/// what [`crate::Client::eval`] (if `is_async` is `false`) or
+/// [`crate::Client::send`] (if `is_async` is `true`) write to the Unix socket
+/// unless different byte order has been set with
+/// [`crate::Client::set_byte_order`]; see [`render_request_with`] for such
+/// cases.  It’s also what the asynchronous clients, which always use native
+/// byte order, write.  It’s useful for testing and for previewing commands
+/// without a running Sawfish server.
+///
+/// # Panics
+///
+/// Panics if the form is empty since such request is never sent (see
+/// [`EvalError::EmptyForm`]) or if length of the form doesn’t fit in
+/// a 64-bit integer.
+///
+/// # Example
+///
+/// ```
+/// let req = sawfish_client::protocol::render_request(b"(quit)", true);
+/// assert_eq!(1, req[0]);
+/// assert_eq!(6u64.to_ne_bytes(), req[1..9]);
+/// assert_eq!(b"(quit)", &req[9..]);
+/// ```
+pub fn render_request(form: &[u8], is_async: bool) -> Vec<u8> {
+    render_request_with(form, is_async, ByteOrder::Native)
+}
+
+/// Returns the request which would be sent to the server to evaluate `form`
+/// with lengths encoded in given byte order.
+///
+/// This is what [`crate::Client::eval`] and [`crate::Client::send`] write
+/// after [`crate::Client::set_byte_order`] has been called with `byte_order`.
+/// See [`render_request`] for details.
+///
+/// # Panics
+///
+/// Panics if the form is empty since such request is never sent (see
+/// [`EvalError::EmptyForm`]) or if length of the form doesn’t fit in
+/// a 64-bit integer.
+///
+/// # Example
+///
+/// ```
+/// use sawfish_client::protocol::{ByteOrder, render_request_with};
+///
+/// let req = render_request_with(b"(quit)", true, ByteOrder::Big);
+/// assert_eq!(1, req[0]);
+/// assert_eq!(6u64.to_be_bytes(), req[1..9]);
+/// assert_eq!(b"(quit)", &req[9..]);
+/// ```
+pub fn render_request_with(
+    form: &[u8],
+    is_async: bool,
+    byte_order: ByteOrder,
+) -> Vec<u8> {
+    assert!(!form.is_empty(), "Empty form");
+    let header = request_header(form.len(), is_async, byte_order).unwrap();
+    [&header[..], form].concat()
+}
+
+
+#[test]
+fn test_check_response_len() {
+    #[track_caller]
+    fn check(want: Option<usize>, data_len: u64, max_response: usize) {
+        match check_response_len(data_len, max_response) {
+            Ok(got) => assert_eq!(want, got),
+            Err(err) => panic!("Unexpected error: {err:?}"),
+        }
+    }
+
+    check(Some(0), 0, 0);
+    check(Some(8), 8, 8);
+    check(None, 9, 8);
+    check(None, MAX_RESPONSE_LEN, 256 << 20);
+    check(Some(1 << 20), 1 << 20, usize::MAX);
+
+    // Absurd lengths indicate desync regardless of the limit.
+    for data_len in [MAX_RESPONSE_LEN + 1, 1 << 40, u64::MAX] {
+        for max_response in [8, 256 << 20, usize::MAX] {
+            assert!(matches!(
+                check_response_len(data_len, max_response),
+                Err(EvalError::Desync)
+            ));
+        }
+    }
+}
+
+#[test]
+fn test_header_len() {
+    let header = request_header(0, false, ByteOrder::Native).unwrap();
+    assert_eq!(REQUEST_HEADER_LEN, header.len());
+    let req = render_request(b"(system-name)", false);
+    assert_eq!(REQUEST_HEADER_LEN + 13, req.len());
+}
+
+#[cfg(feature = "async")]
+#[test]
+fn test_response_header_len() {
+    use futures_util::FutureExt;
+    use futures_util::io::AsyncReadExt;
+
+    // Response in the format Sawfish sends followed by unrelated data.
+    let frame = [&5u64.to_ne_bytes()[..], b"\x01data"].concat();
+    let stream = crate::mock::MockStream::new().chunk(&frame).chunk(b"rest");
+    let mut client = crate::unix::AsyncClient::new(stream);
+    let res = client.eval(b"ok", false).now_or_never().unwrap();
+    assert_eq!(Ok(b"data".to_vec()), res.unwrap());
+    assert_eq!(RESPONSE_HEADER_LEN, frame.len() - 4);
+
+    // Exactly the frame has been consumed.
+    let mut rest = Vec::new();
+    client.sock.read_to_end(&mut rest).now_or_never().unwrap().unwrap();
+    assert_eq!(b"rest", rest.as_slice());
+}
+
+#[test]
+fn test_render_request() {
+    let mut want = vec![0u8];
+    want.extend_from_slice(&13u64.to_ne_bytes());
+    want.extend_from_slice(b"(system-name)");
+    assert_eq!(want, render_request(b"(system-name)", false));
+
+    want[0] = 1;
+    assert_eq!(want, render_request(b"(system-name)", true));
+
+    let mut want = vec![0u8];
+    want.extend_from_slice(&13u64.to_le_bytes());
+    want.extend_from_slice(b"(system-name)");
+    let got = render_request_with(b"(system-name)", false, ByteOrder::Little);
+    assert_eq!(want, got);
+}
+
+#[test]
+#[should_panic]
+fn test_render_empty_request() { render_request(b"", true); }
+
+#[test]
+fn test_byte_order() {
+    for (order, bytes) in [
+        (ByteOrder::Native, 0x0102_0304_0506_0708u64.to_ne_bytes()),
+        (ByteOrder::Little, [8, 7, 6, 5, 4, 3, 2, 1]),
+        (ByteOrder::Big, [1, 2, 3, 4, 5, 6, 7, 8]),
+    ] {
+        assert_eq!(bytes, order.encode(0x0102_0304_0506_0708), "{order:?}");
+        assert_eq!(0x0102_0304_0506_0708, order.decode(bytes), "{order:?}");
+    }
+}
+
+#[test]
+fn test_split_response() {
+    assert!(matches!(split_response(b""), Err(EvalError::NoResponse)));
+    assert_eq!(Ok(Vec::new()), split_response(b"\x01").unwrap());
+    assert_eq!(Ok(b"data".to_vec()), split_response(b"\x01data").unwrap());
+    assert_eq!(Err(b"data".to_vec()), split_response(b"\x00data").unwrap());
+    assert!(matches!(split_response(b"\x02data"), Err(EvalError::Desync)));
+}
@@ -0,0 +1,118 @@
+// sawfish-client -- client library to communicate with Sawfish window manager
+// © 2025 by Michał Nazarewicz <mina86@mina86.com>
+
+//! [`tower_service::Service`] adaptor for [`AsyncClient`]; see
+//! [`EvalService`].
+
+use std::sync::Arc;
+
+use futures_util::io::{AsyncRead, AsyncWrite};
+use futures_util::lock::Mutex;
+
+use crate::{AsyncClient, EvalError, EvalResponse};
+
+/// An [`AsyncClient`] exposed as a [`tower_service::Service`].
+///
+/// Each call sends the form to the server and resolves to the response, i.e.
+/// behaves like [`AsyncClient::eval`].  This allows using middleware from the
+/// tower ecosystem (such as rate or concurrency limits, timeouts and retries)
+/// with the client.
+///
+/// The service is always ready.  Since there’s a single connection, calls
+/// are serialised: a request is sent only once response to the previous one
+/// has been read.  The service is cheap to clone and clones share the same
+/// connection.
+///
+/// **Note** that dropping a call’s future while the request is in flight
+/// leaves the connection in an unknown state (see [`AsyncClient::eval`]).
+/// Middleware which cancels requests (e.g. timeouts) should be followed by
+/// reconnecting on failure.
+///
+/// # Example
+///
+/// ```
+/// use futures_util::{AsyncRead, AsyncWrite};
+/// use tower_service::Service;
+///
+/// async fn system_name<S>(
+///     service: &mut sawfish_client::EvalService<S>,
+/// ) -> Result<sawfish_client::EvalResponse, sawfish_client::EvalError>
+/// where
+///     S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+/// {
+///     core::future::poll_fn(|cx| service.poll_ready(cx)).await?;
+///     service.call(b"(system-name)".to_vec()).await
+/// }
+/// ```
+pub struct EvalService<S>(Arc<Mutex<AsyncClient<S>>>);
+
+impl<S> EvalService<S> {
+    /// Constructs a service sending requests over given client.
+    pub fn new(client: AsyncClient<S>) -> Self {
+        Self(Arc::new(Mutex::new(client)))
+    }
+}
+
+impl<S> Clone for EvalService<S> {
+    fn clone(&self) -> Self { Self(self.0.clone()) }
+}
+
+impl<S> From<AsyncClient<S>> for EvalService<S> {
+    fn from(client: AsyncClient<S>) -> Self { Self::new(client) }
+}
+
+impl<S> tower_service::Service<Vec<u8>> for EvalService<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    type Response = EvalResponse;
+    type Error = EvalError;
+    type Future = futures_util::future::BoxFuture<
+        'static,
+        Result<EvalResponse, EvalError>,
+    >;
+
+    fn poll_ready(
+        &mut self,
+        _cx: &mut core::task::Context<'_>,
+    ) -> core::task::Poll<Result<(), EvalError>> {
+        core::task::Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, form: Vec<u8>) -> Self::Future {
+        let client = self.0.clone();
+        Box::pin(async move { client.lock().await.eval(form).await })
+    }
+}
+
+
+#[test]
+fn test_eval_service() {
+    use futures_util::FutureExt;
+    use tower_service::Service;
+
+    let stream = crate::mock::MockStream::new()
+        .response(1, b"first", 3)
+        .response(0, b"second", 100);
+    let mut service = EvalService::new(AsyncClient::new(stream));
+
+    let mut cx = core::task::Context::from_waker(core::task::Waker::noop());
+    assert!(service.poll_ready(&mut cx).is_ready());
+
+    // Calls are serialised even if both are started before either completes.
+    let first = service.call(b"(first)".to_vec());
+    let second = service.clone().call(b"(second)".to_vec());
+    assert_eq!(Ok(b"first".to_vec()), first.now_or_never().unwrap().unwrap());
+    assert_eq!(
+        Err(b"second".to_vec()),
+        second.now_or_never().unwrap().unwrap()
+    );
+
+    let client = Arc::into_inner(service.0).unwrap().into_inner();
+    let want = [
+        crate::protocol::render_request(b"(first)", false),
+        crate::protocol::render_request(b"(second)", false),
+    ]
+    .concat();
+    assert_eq!(want, client.0.sock.written);
+}
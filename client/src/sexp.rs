@@ -0,0 +1,776 @@
+// sawfish-client -- client library to communicate with Sawfish window manager
+// © 2025 by Michał Nazarewicz <mina86@mina86.com>
+
+//! Helpers for dealing with Lisp S-expressions exchanged with the server.
+
+/// An error reported by the Sawfish server; see [`parse_error`].
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ServerError {
+    /// The error symbol, e.g. `error` or `void-value`.
+    pub kind: String,
+    /// The error message if the first argument of the error form is a string.
+    pub message: Option<String>,
+}
+
+impl core::fmt::Display for ServerError {
+    fn fmt(&self, fmtr: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match &self.message {
+            Some(message) => write!(fmtr, "{}: {}", self.kind, message),
+            None => self.kind.fmt(fmtr),
+        }
+    }
+}
+
+impl std::error::Error for ServerError {}
+
+/// Parses an error response from the Sawfish server.
+///
+/// When evaluation fails, Sawfish responds with an error form such as
+/// `(error "No such file")` or `(void-value foo)`.  This extracts the leading
+/// symbol and the first argument if it is a string.  Returns `None` if the
+/// data isn’t a list starting with a symbol.
+///
+/// # Example
+///
+/// ```
+/// use sawfish_client::sexp::{ServerError, parse_error};
+///
+/// let err = parse_error(br#"(error "Bad argument" 42)"#).unwrap();
+/// assert_eq!("error", err.kind);
+/// assert_eq!(Some("Bad argument"), err.message.as_deref());
+///
+/// let err = parse_error(b"(void-value foo)").unwrap();
+/// assert_eq!("void-value", err.kind);
+/// assert_eq!(None, err.message);
+/// ```
+pub fn parse_error(data: &[u8]) -> Option<ServerError> {
+    let rest = core::str::from_utf8(data).ok()?.trim_start();
+    let rest = rest.strip_prefix('(')?.trim_start();
+    let end = rest.find(|ch| !is_symbol_char(ch)).unwrap_or(rest.len());
+    let (kind, rest) = rest.split_at(end);
+    if kind.is_empty() {
+        return None;
+    }
+    let message = rest.trim_start().strip_prefix('"').and_then(parse_string);
+    Some(ServerError { kind: kind.into(), message })
+}
+
+/// Returns whether the form is valid UTF-8.
+///
+/// Forms are sent to the server as bytes but Sawfish’s reader expects them to
+/// be UTF-8.  Invalid sequences (e.g. from bytes of a file in a legacy
+/// encoding) may be misread.  Forms built from `&str` are always valid; see
+/// [`crate::Client::eval_utf8`].
+///
+/// # Example
+///
+/// ```
+/// use sawfish_client::sexp::is_valid_form_encoding;
+///
+/// assert!(is_valid_form_encoding("(display-message \"zażółć\")".as_bytes()));
+/// assert!(!is_valid_form_encoding(b"(display-message \"\xff\")"));
+/// ```
+pub fn is_valid_form_encoding(form: &[u8]) -> bool {
+    core::str::from_utf8(form).is_ok()
+}
+
+/// Error symbols signalled by Sawfish and the underlying librep interpreter.
+const ERROR_SYMBOLS: &[&str] = &[
+    "error",
+    "arith-error",
+    "bad-arg",
+    "end-of-stream",
+    "file-error",
+    "invalid-autoload",
+    "invalid-function",
+    "invalid-lambda-list",
+    "invalid-macro",
+    "invalid-read-syntax",
+    "memory-error",
+    "missing-arg",
+    "no-catcher",
+    "premature-end-of-stream",
+    "process-error",
+    "setting-constant",
+    "stack-error",
+    "term-interrupt",
+    "user-interrupt",
+    "void-function",
+    "void-value",
+    "window-error",
+];
+
+/// Returns whether the data is an error form with a known error symbol.
+///
+/// Unlike [`parse_error`], which accepts any list starting with a symbol,
+/// this checks that the leading symbol is one of the errors Sawfish signals
+/// (e.g. `error` or `void-value`).  This helps detect errors which ended up
+/// being returned as a successful value, e.g. when a form catches an error
+/// and returns it.
+///
+/// # Example
+///
+/// ```
+/// use sawfish_client::sexp::is_error_form;
+///
+/// assert!(is_error_form(br#"(error "No such file")"#));
+/// assert!(is_error_form(b"(void-value foo)"));
+/// assert!(!is_error_form(b"(1 2 3)"));
+/// assert!(!is_error_form(br#""error""#));
+/// ```
+pub fn is_error_form(data: &[u8]) -> bool {
+    parse_error(data).is_some_and(|err| ERROR_SYMBOLS.contains(&&*err.kind))
+}
+
+/// Returns Lisp string literal representing given string.
+///
+/// Surrounds the string with double quotes and escapes double quotes and
+/// backslashes inside of it.
+///
+/// # Example
+///
+/// ```
+/// use sawfish_client::sexp::quote_string;
+///
+/// assert_eq!(r#""hello world""#, quote_string("hello world"));
+/// assert_eq!(r#""say \"hi\"""#, quote_string(r#"say "hi""#));
+/// ```
+pub fn quote_string(value: &str) -> String {
+    let mut out = String::with_capacity(value.len() + 2);
+    out.push('"');
+    for ch in value.chars() {
+        if ch == '"' || ch == '\\' {
+            out.push('\\');
+        }
+        out.push(ch);
+    }
+    out.push('"');
+    out
+}
+
+/// Returns a function call form.
+///
+/// Arguments are Lisp expressions and are included verbatim; use
+/// [`quote_string`] to pass strings.
+///
+/// # Example
+///
+/// ```
+/// use sawfish_client::sexp::{call, quote_string};
+///
+/// assert_eq!("(system-name)", call("system-name", [""; 0]));
+/// let msg = quote_string("Hello, world!");
+/// assert_eq!(r#"(display-message "Hello, world!")"#,
+///            call("display-message", [msg]));
+/// ```
+pub fn call(
+    func: &str,
+    args: impl IntoIterator<Item = impl AsRef<str>>,
+) -> String {
+    let mut out = format!("({func}");
+    for arg in args {
+        out.push(' ');
+        out.push_str(arg.as_ref());
+    }
+    out.push(')');
+    out
+}
+
+/// Writer which builds a form directly in an [`std::io::Write`] sink.
+///
+/// Tokens are separated by spaces as necessary and strings are escaped as
+/// they are written.  This avoids allocating intermediate strings when
+/// building large or deeply nested forms.  The writer doesn’t check whether
+/// lists are balanced.
+///
+/// # Example
+///
+/// ```
+/// use sawfish_client::sexp::FormWriter;
+///
+/// let form = FormWriter::to_vec(|w| {
+///     w.open_list()?.symbol("display-message")?.string("Hello")?;
+///     w.close_list()?;
+///     Ok(())
+/// })
+/// .unwrap();
+/// assert_eq!(br#"(display-message "Hello")"#, form.as_slice());
+/// ```
+#[derive(Debug)]
+pub struct FormWriter<W> {
+    out: W,
+    /// Whether the next token needs to be preceded by a space.
+    need_space: bool,
+}
+
+impl<W: std::io::Write> FormWriter<W> {
+    /// Constructs a new writer writing to `out`.
+    pub fn new(out: W) -> Self { Self { out, need_space: false } }
+
+    /// Returns the underlying writer.
+    pub fn into_inner(self) -> W { self.out }
+
+    /// Starts a list, i.e. writes opening parenthesis.
+    pub fn open_list(&mut self) -> std::io::Result<&mut Self> {
+        self.separate()?;
+        self.out.write_all(b"(")?;
+        self.need_space = false;
+        Ok(self)
+    }
+
+    /// Ends a list, i.e. writes closing parenthesis.
+    pub fn close_list(&mut self) -> std::io::Result<&mut Self> {
+        self.out.write_all(b")")?;
+        self.need_space = true;
+        Ok(self)
+    }
+
+    /// Writes a symbol.  The symbol is written verbatim.
+    pub fn symbol(&mut self, symbol: &str) -> std::io::Result<&mut Self> {
+        self.atom(format_args!("{symbol}"))
+    }
+
+    /// Writes a string literal; see [`quote_string`].
+    pub fn string(&mut self, value: &str) -> std::io::Result<&mut Self> {
+        self.separate()?;
+        self.out.write_all(b"\"")?;
+        let mut rest = value.as_bytes();
+        while let Some(pos) =
+            rest.iter().position(|b| matches!(b, b'"' | b'\\'))
+        {
+            self.out.write_all(&rest[..pos])?;
+            self.out.write_all(&[b'\\', rest[pos]])?;
+            rest = &rest[pos + 1..];
+        }
+        self.out.write_all(rest)?;
+        self.out.write_all(b"\"")?;
+        self.need_space = true;
+        Ok(self)
+    }
+
+    /// Writes an integer.
+    pub fn int(&mut self, value: i64) -> std::io::Result<&mut Self> {
+        self.atom(format_args!("{value}"))
+    }
+
+    /// Writes a floating point number.
+    ///
+    /// The number always includes a decimal point or an exponent so that
+    /// Lisp reads it as a float.  Fails with `InvalidInput` error if the
+    /// number isn’t finite since Lisp has no syntax for such values.
+    pub fn float(&mut self, value: f64) -> std::io::Result<&mut Self> {
+        if !value.is_finite() {
+            return Err(std::io::ErrorKind::InvalidInput.into());
+        }
+        self.atom(format_args!("{value:?}"))
+    }
+
+    /// Writes an atom separating it from the previous token if necessary.
+    fn atom(
+        &mut self,
+        atom: core::fmt::Arguments<'_>,
+    ) -> std::io::Result<&mut Self> {
+        self.separate()?;
+        self.out.write_fmt(atom)?;
+        self.need_space = true;
+        Ok(self)
+    }
+
+    /// Writes a space if the previous token requires it.
+    fn separate(&mut self) -> std::io::Result<()> {
+        if self.need_space {
+            self.out.write_all(b" ")?;
+        }
+        Ok(())
+    }
+}
+
+impl FormWriter<Vec<u8>> {
+    /// Builds a form in a new vector.
+    ///
+    /// Calls `build` with a writer writing into a vector and returns the
+    /// vector once `build` finishes.
+    pub fn to_vec(
+        build: impl FnOnce(&mut Self) -> std::io::Result<()>,
+    ) -> std::io::Result<Vec<u8>> {
+        let mut writer = Self::new(Vec::new());
+        build(&mut writer)?;
+        Ok(writer.out)
+    }
+}
+
+/// Error reading a form; see [`read_form`].
+#[derive(Debug, derive_more::From)]
+#[non_exhaustive]
+pub enum SexpError {
+    /// An I/O error reading the input.
+    Io(std::io::Error),
+    /// The input ended in the middle of a form, e.g. with unbalanced opening
+    /// parenthesis or unterminated string.
+    UnexpectedEof,
+    /// Closing parenthesis without matching opening one.
+    UnbalancedParen,
+}
+
+impl core::fmt::Display for SexpError {
+    fn fmt(&self, fmtr: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Io(err) => err.fmt(fmtr),
+            Self::UnexpectedEof => "Incomplete form at end of input".fmt(fmtr),
+            Self::UnbalancedParen => "Unbalanced closing parenthesis".fmt(fmtr),
+        }
+    }
+}
+
+impl std::error::Error for SexpError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+/// Reads a single complete top-level form.
+///
+/// Skips whitespace and comments preceding the form and reads bytes until
+/// the form is complete, i.e. until its parentheses are balanced or, for
+/// atoms, until a delimiter.  Parentheses inside of strings, comments and
+/// character literals (such as `?\(`) are ignored.  Nothing past the end of
+/// the form is consumed so the function can be called repeatedly to read
+/// successive forms.
+///
+/// Returns `None` if the input ends before a form starts.  Fails with
+/// [`SexpError::UnexpectedEof`] if it ends in the middle of a form.
+///
+/// # Example
+///
+/// ```
+/// use sawfish_client::sexp::read_form;
+///
+/// let mut input = &b"(display-message\n  \"(a)\") ; comment\nt"[..];
+/// let form = read_form(&mut input).unwrap();
+/// assert_eq!(Some(&b"(display-message\n  \"(a)\")"[..]), form.as_deref());
+/// assert_eq!(Some(&b"t"[..]), read_form(&mut input).unwrap().as_deref());
+/// assert_eq!(None, read_form(&mut input).unwrap());
+/// ```
+pub fn read_form<R: std::io::BufRead + ?Sized>(
+    rd: &mut R,
+) -> Result<Option<Vec<u8>>, SexpError> {
+    let mut scanner = FormScanner::default();
+    let mut form = Vec::new();
+    loop {
+        let buf = match rd.fill_buf() {
+            Ok(buf) => buf,
+            Err(err) if err.kind() == std::io::ErrorKind::Interrupted => {
+                continue;
+            }
+            Err(err) => return Err(err.into()),
+        };
+        if buf.is_empty() {
+            return scanner
+                .finish()
+                .map(|()| (!form.is_empty()).then_some(form));
+        }
+        let mut consumed = 0;
+        let mut res = None;
+        for &byte in buf {
+            let step = scanner.step(byte);
+            if !matches!(step, Ok(Step::End)) {
+                consumed += 1;
+            }
+            match step {
+                Ok(Step::Skip) => (),
+                Ok(Step::Take) => form.push(byte),
+                Ok(Step::Last) => {
+                    form.push(byte);
+                    res = Some(Ok(()));
+                    break;
+                }
+                Ok(Step::End) => {
+                    res = Some(Ok(()));
+                    break;
+                }
+                Err(err) => {
+                    res = Some(Err(err));
+                    break;
+                }
+            }
+        }
+        // On error, the offending byte is consumed so that the caller may
+        // continue reading past it.
+        rd.consume(consumed);
+        if let Some(res) = res {
+            return res.map(|()| Some(form));
+        }
+    }
+}
+
+/// What to do with a byte processed by [`FormScanner::step`].
+enum Step {
+    /// The byte precedes the form (whitespace or comment) and is consumed.
+    Skip,
+    /// The byte is part of the form.
+    Take,
+    /// The byte is the last one of the form.
+    Last,
+    /// The form ended before the byte which isn’t consumed.
+    End,
+}
+
+/// State of [`read_form`] scanning a form.
+#[derive(Default)]
+struct FormScanner {
+    /// Lexical context the scanner is in.
+    state: ScanState,
+    /// Nesting depth of lists.
+    depth: usize,
+    /// Whether the form has started.
+    started: bool,
+    /// Whether a top-level atom is being read.
+    atom: bool,
+    /// Whether previous byte was a delimiter, i.e. `?` would start
+    /// a character literal.
+    at_token_start: bool,
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+enum ScanState {
+    #[default]
+    Normal,
+    /// Inside of a string literal.
+    String,
+    /// After a backslash.  Holds whether it’s inside of a string.
+    Escape(bool),
+    /// After `?` starting a character literal.
+    Char,
+    /// Inside of a comment which lasts until end of line.
+    Comment,
+}
+
+impl FormScanner {
+    /// Processes a single byte of input.
+    fn step(&mut self, byte: u8) -> Result<Step, SexpError> {
+        let step = match self.state {
+            ScanState::String => {
+                match byte {
+                    b'\\' => self.state = ScanState::Escape(true),
+                    b'"' => {
+                        self.state = ScanState::Normal;
+                        return Ok(self.close_token());
+                    }
+                    _ => (),
+                }
+                Step::Take
+            }
+            ScanState::Escape(in_string) => {
+                self.state = if in_string {
+                    ScanState::String
+                } else {
+                    ScanState::Normal
+                };
+                Step::Take
+            }
+            ScanState::Char => {
+                self.state = if byte == b'\\' {
+                    ScanState::Escape(false)
+                } else {
+                    ScanState::Normal
+                };
+                Step::Take
+            }
+            ScanState::Comment => {
+                if byte == b'\n' {
+                    self.state = ScanState::Normal;
+                    self.at_token_start = true;
+                }
+                if self.started { Step::Take } else { Step::Skip }
+            }
+            ScanState::Normal => return self.step_normal(byte),
+        };
+        Ok(step)
+    }
+
+    /// Processes a single byte outside of strings, comments and escapes.
+    fn step_normal(&mut self, byte: u8) -> Result<Step, SexpError> {
+        let is_delimiter =
+            byte.is_ascii_whitespace() || b"();\"".contains(&byte);
+        if self.atom && is_delimiter {
+            return Ok(Step::End);
+        }
+        if !self.started {
+            if byte.is_ascii_whitespace() {
+                return Ok(Step::Skip);
+            } else if byte == b';' {
+                self.state = ScanState::Comment;
+                return Ok(Step::Skip);
+            }
+            self.started = true;
+            self.at_token_start = true;
+        }
+        let at_token_start = core::mem::replace(
+            &mut self.at_token_start,
+            is_delimiter || b"'`,#".contains(&byte),
+        );
+        match byte {
+            b'(' => self.depth += 1,
+            b')' => {
+                self.depth = self
+                    .depth
+                    .checked_sub(1)
+                    .ok_or(SexpError::UnbalancedParen)?;
+                return Ok(self.close_token());
+            }
+            b'"' => self.state = ScanState::String,
+            b';' => self.state = ScanState::Comment,
+            b'\\' => {
+                self.state = ScanState::Escape(false);
+                self.atom = self.depth == 0;
+            }
+            b'?' if at_token_start => {
+                self.state = ScanState::Char;
+                self.atom = self.depth == 0;
+            }
+            b'\'' | b'`' | b',' | b'#' | b'@' if at_token_start => (),
+            _ if byte.is_ascii_whitespace() => (),
+            _ => self.atom = self.depth == 0,
+        }
+        Ok(Step::Take)
+    }
+
+    /// Returns step for a byte closing a list or a string.
+    fn close_token(&self) -> Step {
+        if self.depth == 0 { Step::Last } else { Step::Take }
+    }
+
+    /// Checks whether the input may end in the current state.
+    fn finish(&self) -> Result<(), SexpError> {
+        let complete = self.depth == 0 &&
+            matches!(self.state, ScanState::Normal | ScanState::Comment);
+        if !self.started || complete {
+            Ok(())
+        } else {
+            Err(SexpError::UnexpectedEof)
+        }
+    }
+}
+
+/// Returns whether the character may appear in a symbol.
+fn is_symbol_char(ch: char) -> bool {
+    !ch.is_whitespace() && !"()[]\"';`,#".contains(ch)
+}
+
+/// Parses body of a string literal (i.e. what follows the opening quote).
+///
+/// Returns `None` if the string isn’t terminated.
+fn parse_string(body: &str) -> Option<String> {
+    let mut out = String::new();
+    let mut chars = body.chars();
+    loop {
+        match chars.next()? {
+            '"' => return Some(out),
+            '\\' => out.push(match chars.next()? {
+                'n' => '\n',
+                't' => '\t',
+                'r' => '\r',
+                'f' => '\x0c',
+                'a' => '\x07',
+                ch => ch,
+            }),
+            ch => out.push(ch),
+        }
+    }
+}
+
+
+#[test]
+fn test_parse_error() {
+    #[track_caller]
+    fn check(want: Option<(&str, Option<&str>)>, data: &[u8]) {
+        let got = parse_error(data);
+        let got = got.as_ref().map(|err| (&*err.kind, err.message.as_deref()));
+        assert_eq!(want, got);
+    }
+
+    check(Some(("error", Some("No such file"))), br#"(error "No such file")"#);
+    check(
+        Some(("file-error", Some("No such file or directory"))),
+        br#" (file-error "No such file or directory" "/nonexistent")"#,
+    );
+    check(Some(("error", Some("a \"b\"\n"))), br#"(error "a \"b\"\n")"#);
+    check(Some(("void-value", None)), b"(void-value foo)");
+    check(Some(("end-of-stream", None)), b"(end-of-stream)");
+    check(Some(("error", None)), br#"(error "unterminated)"#);
+
+    check(None, b"");
+    check(None, b"foo");
+    check(None, b"()");
+    check(None, br#"("error")"#);
+    check(None, b"(\xff)");
+}
+
+#[test]
+fn test_is_valid_form_encoding() {
+    for form in [&b""[..], b"(system-name)", "(foo \"gęślą\")".as_bytes()] {
+        assert!(is_valid_form_encoding(form), "{form:?}");
+    }
+    for form in [
+        &b"\xff"[..],
+        b"(foo \"\xc0\xaf\")",
+        b"(foo \"\xe2\x82\")",
+        b"(foo \"\xed\xa0\x80\")",
+        b"(foo \"\xf4\x90\x80\x80\")",
+    ] {
+        assert!(!is_valid_form_encoding(form), "{form:?}");
+    }
+}
+
+#[test]
+fn test_is_error_form() {
+    assert!(is_error_form(br#"(error "No such file")"#));
+    assert!(is_error_form(br#" (file-error "No such file" "/foo")"#));
+    assert!(is_error_form(b"(void-function foo)"));
+    assert!(is_error_form(b"(end-of-stream)"));
+
+    assert!(!is_error_form(b""));
+    assert!(!is_error_form(b"nil"));
+    assert!(!is_error_form(b"error"));
+    assert!(!is_error_form(br#""(error)""#));
+    assert!(!is_error_form(b"(errors 1)"));
+    assert!(!is_error_form(b"(foo bar)"));
+    assert!(!is_error_form(b"(error\xff)"));
+}
+
+#[test]
+fn test_quote_string() {
+    for (want, value) in [
+        (r#""""#, ""),
+        (r#""foo""#, "foo"),
+        (r#""hello world""#, "hello world"),
+        (r#""a \"b\" \\ c""#, r#"a "b" \ c"#),
+        ("\"zażółć\ngęślą\"", "zażółć\ngęślą"),
+    ] {
+        assert_eq!(want, quote_string(value));
+        let err = parse_error(format!("(error {want})").as_bytes()).unwrap();
+        assert_eq!(Some(value), err.message.as_deref());
+    }
+}
+
+#[test]
+fn test_call() {
+    assert_eq!("(foo)", call("foo", [""; 0]));
+    assert_eq!("(+ 1 2)", call("+", ["1", "2"]));
+    assert_eq!(
+        r#"(foo "a b" (bar))"#,
+        call("foo", [quote_string("a b"), "(bar)".into()])
+    );
+}
+
+#[test]
+fn test_form_writer() {
+    let form = FormWriter::to_vec(|w| {
+        w.open_list()?.symbol("foo")?.int(-42)?.float(1.0)?.float(2.5e-3)?;
+        w.open_list()?.close_list()?;
+        w.open_list()?.symbol("bar")?.string(r#"a "b" \ c"#)?.close_list()?;
+        w.string("")?.close_list()?;
+        Ok(())
+    })
+    .unwrap();
+    assert_eq!(
+        r#"(foo -42 1.0 0.0025 () (bar "a \"b\" \\ c") "")"#,
+        String::from_utf8(form).unwrap()
+    );
+
+    for value in ["", "foo", r#"a "b" \ c"#, "zażółć\ngęślą"] {
+        let form = FormWriter::to_vec(|w| {
+            w.string(value)?;
+            Ok(())
+        });
+        assert_eq!(quote_string(value).into_bytes(), form.unwrap());
+    }
+
+    // Floats stay floats and integers stay integers; very large and very
+    // small floats keep their exponent.
+    for (want, value) in
+        [("3.0", 3.0), ("-0.0", -0.0), ("1e20", 1e20), ("1e-7", 1e-7)]
+    {
+        let form = FormWriter::to_vec(|w| {
+            w.float(value)?;
+            Ok(())
+        });
+        assert_eq!(want.as_bytes(), form.unwrap().as_slice());
+    }
+    let form = FormWriter::to_vec(|w| {
+        w.int(3)?;
+        Ok(())
+    });
+    assert_eq!(b"3", form.unwrap().as_slice());
+
+    for value in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+        let mut w = FormWriter::new(Vec::new());
+        let err = w.float(value).unwrap_err();
+        assert_eq!(std::io::ErrorKind::InvalidInput, err.kind());
+    }
+}
+
+#[test]
+fn test_read_form() {
+    #[track_caller]
+    fn check(want: &[&str], input: &str) {
+        let mut rd = input.as_bytes();
+        let mut got = Vec::new();
+        while let Some(form) = read_form(&mut rd).unwrap() {
+            got.push(String::from_utf8(form).unwrap());
+        }
+        assert_eq!(want, got.as_slice());
+    }
+
+    check(&[], "");
+    check(&[], "  \n; just a comment\n  ");
+    check(&["(system-name)"], "(system-name)");
+    check(&["(a)", "(b)", "c", "d"], "(a)(b) c\nd");
+    check(&["(foo\n  (bar (baz))\n  qux)"], "\n(foo\n  (bar (baz))\n  qux)\n");
+    check(&["(a ; (\n b)", "c"], "(a ; (\n b) ; )\nc");
+    check(&[r#"(msg "(\")")"#, r#""str""#], r#"(msg "(\")") "str""#);
+    check(&[r"(list ?( ?\) ?\\)", "?a"], r"(list ?( ?\) ?\\) ?a");
+    check(
+        &["'(a b)", "`(a ,@b)", "#(1 2)", "'sym", "foo?"],
+        "'(a b) `(a ,@b) #(1 2) 'sym foo?",
+    );
+    check(&[r"foo\ bar", "x"], r"foo\ bar x");
+    check(&["sawfish-version"], "sawfish-version");
+    check(&["zażółć", "(gęślą)"], "zażółć (gęślą)");
+
+    // Bytes following the form aren’t consumed.
+    let mut rd = &b"foo(bar)"[..];
+    assert_eq!(b"foo", read_form(&mut rd).unwrap().unwrap().as_slice());
+    assert_eq!(b"(bar)", rd);
+
+    // Reading a byte at a time works the same.
+    let mut rd = std::io::BufReader::with_capacity(1, &b"(a \"b\")\n c"[..]);
+    assert_eq!(b"(a \"b\")", read_form(&mut rd).unwrap().unwrap().as_slice());
+    assert_eq!(b"c", read_form(&mut rd).unwrap().unwrap().as_slice());
+    assert_eq!(None, read_form(&mut rd).unwrap());
+}
+
+#[test]
+fn test_read_form_errors() {
+    for input in ["(foo (bar)", "(foo \"bar)", "\"foo", "?", "foo\\", "(a ; b)"]
+    {
+        let mut rd = input.as_bytes();
+        match read_form(&mut rd) {
+            Err(SexpError::UnexpectedEof) => (),
+            res => panic!("Unexpected result for {input:?}: {res:?}"),
+        }
+    }
+
+    // The complete forms are read before the incomplete trailing one.
+    let mut rd = &b"(a)\n(b"[..];
+    assert_eq!(b"(a)", read_form(&mut rd).unwrap().unwrap().as_slice());
+    assert!(matches!(read_form(&mut rd), Err(SexpError::UnexpectedEof)));
+
+    // Stray closing parenthesis is consumed.
+    let mut rd = &b") (a)"[..];
+    assert!(matches!(read_form(&mut rd), Err(SexpError::UnbalancedParen)));
+    assert_eq!(b"(a)", read_form(&mut rd).unwrap().unwrap().as_slice());
+}
@@ -7,12 +7,121 @@ use std::io::{Read, Write};
 use std::os::unix::net::UnixStream;
 
 #[cfg(feature = "async")]
-use futures_util::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use futures_util::io::{
+    AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, ReadHalf, WriteHalf,
+};
 
-use crate::{ConnError, EvalError, EvalResponse};
+use crate::{ConnError, EvalError, EvalResponse, protocol};
+
+/// Maximum capacity allocated for response data before any data is read.
+///
+/// Response buffer is allocated based on the length declared in response
+/// header but no more than this.  Beyond that, the buffer grows as data
+/// arrives so that a bogus length doesn’t cause a huge allocation.
+const INITIAL_CAPACITY: usize = 64 << 10;
+
+/// Size of chunks response data is read in when reporting progress; see
+/// [`Client::eval_with_progress`].
+const PROGRESS_CHUNK: usize = 64 << 10;
+
+/// Maximum length of response data prefix captured in
+/// [`EvalError::ResponseTooLarge`].
+const TOO_LARGE_PREFIX_LEN: u64 = 256;
+
+/// Initial delay between checks whether server socket exists when waiting for
+/// it to appear.  The delay is doubled after each check.
+#[cfg(any(feature = "tokio", feature = "smol"))]
+const WAIT_INITIAL_DELAY: std::time::Duration =
+    std::time::Duration::from_millis(10);
+
+/// Maximum delay between checks whether server socket exists.
+#[cfg(any(feature = "tokio", feature = "smol"))]
+const WAIT_MAX_DELAY: std::time::Duration =
+    std::time::Duration::from_millis(500);
 
 /// A Unix-socket-based connection to the Sawfish server.
-pub struct Client(std::os::unix::net::UnixStream);
+pub struct Client {
+    pub sock: UnixStream,
+    /// Canonical name of the display the client is connected to or empty
+    /// string if unknown.
+    pub display: String,
+    pub byte_order: protocol::ByteOrder,
+    /// Maximum length of response data.  Reading longer responses fails with
+    /// [`EvalError::ResponseTooLarge`].
+    pub max_response: usize,
+    /// Maximum length of a form.  Sending longer forms fails with
+    /// [`EvalError::RequestTooLarge`].
+    pub max_request: usize,
+    /// Time the client was created.
+    pub created_at: std::time::Instant,
+    /// Time the last request was sent.
+    pub last_used: std::time::Instant,
+    /// Whether the last response read was an error or `None` if no response
+    /// has been read yet.
+    pub last_was_error: Option<bool>,
+    /// Number of bytes sent and received.
+    pub io_stats: crate::IoStats,
+    pending: Pending,
+}
+
+/// Number of sent requests the server is going to respond to but whose
+/// responses haven’t been read yet.
+///
+/// The counter is maintained in debug builds only and is used to detect
+/// requests and responses getting out of sync.  In release builds this is
+/// a zero-sized type with no-op methods.
+#[derive(Clone, Default)]
+struct Pending(
+    #[cfg(debug_assertions)] std::sync::Arc<std::sync::atomic::AtomicUsize>,
+);
+
+impl Pending {
+    /// Records a request was sent.
+    fn sent(&self, _is_async: bool) {
+        #[cfg(debug_assertions)]
+        if !_is_async {
+            self.0.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        }
+    }
+
+    /// Records that additional `count` responses are expected.
+    fn expect(&self, _count: usize) {
+        #[cfg(debug_assertions)]
+        self.0.fetch_add(_count, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    /// Records a response is being drained.
+    ///
+    /// Unlike [`Self::received`], doesn’t panic if there are no requests
+    /// awaiting a response since draining is used to recover from requests
+    /// and responses getting out of sync.
+    fn drained(&self) {
+        #[cfg(debug_assertions)]
+        let _ = self.0.fetch_update(
+            std::sync::atomic::Ordering::Relaxed,
+            std::sync::atomic::Ordering::Relaxed,
+            |count| count.checked_sub(1),
+        );
+    }
+
+    /// Records a response is being read.
+    ///
+    /// Panics in debug builds if there are no requests awaiting a response.
+    fn received(&self) {
+        #[cfg(debug_assertions)]
+        assert!(
+            self.0
+                .fetch_update(
+                    std::sync::atomic::Ordering::Relaxed,
+                    std::sync::atomic::Ordering::Relaxed,
+                    |count| count.checked_sub(1),
+                )
+                .is_ok(),
+            "Reading response with no pending requests; requests and \
+             responses are out of sync"
+        );
+    }
+}
 
 /// Returns path to the Unix socket Sawfish server is listening on.
 ///
@@ -20,12 +129,27 @@ pub struct Client(std::os::unix::net::UnixStream);
 /// `{logname}` is value of `LOGNAME` environment variable and `{display}`
 /// is a canonical display name.
 pub fn server_path(display: &str) -> Result<std::path::PathBuf, ConnError> {
+    server_path_with(display, system_name())
+}
+
+/// Returns path to the Unix socket Sawfish server is listening on assuming
+/// given system name; see [`server_path`].
+fn server_path_with(
+    display: &str,
+    system_name: Option<&str>,
+) -> Result<std::path::PathBuf, ConnError> {
+    socket_path(&canonical_display_with(display, system_name))
+}
+
+/// Returns path to the Unix socket for given canonical display name; see
+/// [`server_path`].
+fn socket_path(canonical: &str) -> Result<std::path::PathBuf, ConnError> {
     let username = std::env::var_os("LOGNAME").ok_or(ConnError::NoLogname)?;
     let path = [
         "/tmp/.sawfish-".as_bytes(),
         username.as_encoded_bytes(),
         "/".as_bytes(),
-        canonical_display(display).as_bytes(),
+        canonical.as_bytes(),
     ]
     .concat();
     // SAFETY: Concatenating Strings and OsStrings produces valid OsStrings.
@@ -33,13 +157,117 @@ pub fn server_path(display: &str) -> Result<std::path::PathBuf, ConnError> {
     Ok(std::path::PathBuf::from(path))
 }
 
+/// Checks whether Sawfish server accepts connections on Unix socket at given
+/// path; see [`crate::Client::probe`].
+pub fn probe_path(path: std::path::PathBuf) -> Result<(), ConnError> {
+    match UnixStream::connect(path.as_path()) {
+        Ok(_) => Ok(()),
+        Err(err) => Err(ConnError::Io(path, err)),
+    }
+}
+
 impl Client {
+    /// Constructs a new client communicating over given socket.
+    pub fn new(sock: UnixStream) -> Self {
+        let now = std::time::Instant::now();
+        Self {
+            sock,
+            display: String::new(),
+            byte_order: Default::default(),
+            max_response: crate::DEFAULT_MAX_RESPONSE,
+            max_request: crate::DEFAULT_MAX_REQUEST,
+            created_at: now,
+            last_used: now,
+            last_was_error: None,
+            io_stats: Default::default(),
+            pending: Pending::default(),
+        }
+    }
+
     /// Opens connection to Sawfish through a Unix socket at given location.
-    pub fn open(display: &str) -> Result<Self, ConnError> {
-        let path = server_path(display)?;
-        UnixStream::connect(path.as_path())
-            .map(Self)
-            .map_err(|err| ConnError::Io(path, err))
+    ///
+    /// Returns the client and path of the socket it connected to.
+    pub fn open(
+        display: &str,
+    ) -> Result<(Self, std::path::PathBuf), ConnError> {
+        // Canonicalising may involve DNS lookups so do it only once.
+        let display = canonical_display(display);
+        let path = socket_path(&display)?;
+        Self::connect(path.clone(), display).map(|client| (client, path))
+    }
+
+    /// Opens connection to Sawfish through a Unix socket at given location
+    /// retrying on transient errors; see [`crate::Client::open_retry`].
+    pub fn open_retry(
+        display: &str,
+        attempts: usize,
+        delay: std::time::Duration,
+    ) -> Result<Self, ConnError> {
+        let display = canonical_display(display);
+        let path = socket_path(&display)?;
+        Self::connect_retry(path, display, attempts, delay)
+    }
+
+    /// Opens connection to Sawfish server listening at given path making up
+    /// to `attempts` attempts.
+    ///
+    /// Connecting is retried if it fails with `ConnectionRefused` or
+    /// `NotFound` error which happen when the server hasn’t started listening
+    /// yet.  Returns the last error if all attempts fail.
+    fn connect_retry(
+        path: std::path::PathBuf,
+        display: String,
+        attempts: usize,
+        delay: std::time::Duration,
+    ) -> Result<Self, ConnError> {
+        use std::io::ErrorKind;
+
+        for _ in 1..attempts {
+            match Self::connect(path.clone(), display.clone()) {
+                Err(ConnError::Io(_, err))
+                    if matches!(
+                        err.kind(),
+                        ErrorKind::ConnectionRefused | ErrorKind::NotFound
+                    ) =>
+                {
+                    std::thread::sleep(delay)
+                }
+                res => return res,
+            }
+        }
+        Self::connect(path, display)
+    }
+
+    /// Opens connection to Sawfish server listening at given path.
+    ///
+    /// `display` is the canonical name of the display the server is running
+    /// on.
+    fn connect(
+        path: std::path::PathBuf,
+        display: String,
+    ) -> Result<Self, ConnError> {
+        #[cfg(feature = "tracing")]
+        {
+            // The macro shadows `display` so it can’t be referred to directly.
+            let name = display.as_str();
+            tracing::debug!(
+                display = name,
+                path = %path.display(),
+                "connecting to Sawfish"
+            );
+        }
+        match UnixStream::connect(path.as_path()) {
+            Ok(sock) => Ok(Self { display, ..Self::new(sock) }),
+            Err(err) => {
+                #[cfg(feature = "tracing")]
+                tracing::debug!(
+                    path = %path.display(),
+                    kind = %err.kind(),
+                    "connecting to Sawfish failed"
+                );
+                Err(ConnError::Io(path, err))
+            }
+        }
     }
 
     /// Sends form to the server for evaluation and waits for response if
@@ -58,58 +286,386 @@ impl Client {
     /// If `is_async` is `false`, the caller is responsible for calling
     /// [`Self::read_response`].  Otherwise, the requests and responses will get
     /// out of sync.
-    fn send_request(
+    pub fn send_request(
         &mut self,
         form: &[u8],
         is_async: bool,
     ) -> Result<(), EvalError> {
-        let req_type = u8::from(is_async);
-        let req_len = u64::try_from(form.len()).unwrap();
-        let mut buf = [0u8; 9];
-        buf[0] = req_type;
-        buf[1..].copy_from_slice(&req_len.to_ne_bytes());
-        self.0.write_all(&buf)?;
-        self.0.write_all(form)?;
+        protocol::check_request_len(form.len(), self.max_request)?;
+        let buf =
+            protocol::request_header(form.len(), is_async, self.byte_order)?;
+        self.last_used = std::time::Instant::now();
+        self.pending.sent(is_async);
+        self.sock
+            .write_all(&buf)
+            .and_then(|()| self.sock.write_all(form))
+            .map_err(EvalError::from_write)?;
+        self.io_stats.bytes_sent +=
+            u64::try_from(buf.len() + form.len()).unwrap();
+        Ok(())
+    }
+
+    /// Sends async requests for all forms with a single write; see
+    /// [`crate::Client::batch_send`].
+    pub fn batch_send(
+        &mut self,
+        forms: &[impl AsRef<[u8]>],
+    ) -> Result<(), EvalError> {
+        let mut buf = Vec::new();
+        for form in forms {
+            let form = form.as_ref();
+            protocol::check_request_len(form.len(), self.max_request)?;
+            buf.extend_from_slice(&protocol::request_header(
+                form.len(),
+                true,
+                self.byte_order,
+            )?);
+            buf.extend_from_slice(form);
+        }
+        if buf.is_empty() {
+            return Ok(());
+        }
+        self.last_used = std::time::Instant::now();
+        self.sock.write_all(&buf).map_err(EvalError::from_write)?;
+        self.io_stats.bytes_sent += u64::try_from(buf.len()).unwrap();
         Ok(())
     }
 
     /// Reads response from the server.
-    fn read_response(&mut self) -> Result<EvalResponse, EvalError> {
-        let mut buf = [0u8; 8];
-        self.0.read_exact(&mut buf)?;
-        let res_len = u64::from_ne_bytes(buf);
+    pub fn read_response(&mut self) -> Result<EvalResponse, EvalError> {
+        let (success, data_len) = self.read_response_header()?;
+        self.read_response_data(success, data_len)
+    }
+
+    /// Reads response data of given length from the server.
+    fn read_response_data(
+        &mut self,
+        success: bool,
+        data_len: usize,
+    ) -> Result<EvalResponse, EvalError> {
+        // Read into spare capacity of the vector rather than into a zeroed
+        // buffer.  The response may be large and zeroing it is wasteful.
+        // Don’t trust the length for the initial allocation.  The vector
+        // grows as data arrives.
+        let mut response = Vec::with_capacity(data_len.min(INITIAL_CAPACITY));
+        (&mut self.sock)
+            .take(u64::try_from(data_len).unwrap())
+            .read_to_end(&mut response)?;
+        if response.len() != data_len {
+            return Err(std::io::ErrorKind::UnexpectedEof.into());
+        }
+        self.io_stats.bytes_received += u64::try_from(data_len).unwrap();
+        Ok(protocol::make_response(success, response))
+    }
+
+    /// Sends form to the server for evaluation and reads response calling
+    /// `progress` with number of bytes of response data read so far and the
+    /// total length of the data after each chunk is read.
+    pub fn eval_with_progress(
+        &mut self,
+        form: &[u8],
+        mut progress: impl FnMut(usize, usize),
+    ) -> Result<EvalResponse, EvalError> {
+        self.send_request(form, false)?;
+        let (success, data_len) = self.read_response_header()?;
+        let mut response = Vec::with_capacity(data_len.min(INITIAL_CAPACITY));
+        loop {
+            let want = (data_len - response.len()).min(PROGRESS_CHUNK);
+            let got = (&mut self.sock)
+                .take(u64::try_from(want).unwrap())
+                .read_to_end(&mut response)?;
+            if got != want {
+                return Err(std::io::ErrorKind::UnexpectedEof.into());
+            }
+            progress(response.len(), data_len);
+            if response.len() == data_len {
+                break;
+            }
+        }
+        self.io_stats.bytes_received += u64::try_from(data_len).unwrap();
+        Ok(protocol::make_response(success, response))
+    }
+
+    /// Reads a single response if one is available; see
+    /// [`crate::Client::drain_pending`].
+    pub fn drain_pending(&mut self) -> Result<Option<EvalResponse>, EvalError> {
+        use std::io::ErrorKind;
+
+        let mut buf = [0u8; protocol::RESPONSE_LENGTH_LEN];
+        let len = loop {
+            match self.sock.read(&mut buf) {
+                Ok(0) => return Err(EvalError::Disconnected),
+                Ok(len) => break len,
+                Err(err) if err.kind() == ErrorKind::Interrupted => (),
+                Err(err)
+                    if matches!(
+                        err.kind(),
+                        ErrorKind::WouldBlock | ErrorKind::TimedOut
+                    ) =>
+                {
+                    return Ok(None);
+                }
+                Err(err) => return Err(err.into()),
+            }
+        };
+        self.sock.read_exact(&mut buf[len..])?;
+        self.pending.drained();
+        let (success, data_len) = self.read_response_status(buf)?;
+        self.read_response_data(success, data_len).map(Some)
+    }
+
+    /// Sends form to the server for evaluation and reads `count` responses.
+    ///
+    /// If `count` is zero, the form is sent as an async request.
+    pub fn eval_multi(
+        &mut self,
+        form: &[u8],
+        count: usize,
+    ) -> Result<Vec<EvalResponse>, EvalError> {
+        self.send_request(form, count == 0)?;
+        self.pending.expect(count.saturating_sub(1));
+        (0..count).map(|_| self.read_response()).collect()
+    }
+
+    /// Sends form to the server for evaluation and copies response data into
+    /// `out`.
+    ///
+    /// Returns whether evaluation succeeded.  If writing to `out` fails, the
+    /// rest of the response is not read and the connection is out of sync.
+    pub fn eval_to_writer(
+        &mut self,
+        form: &[u8],
+        out: &mut impl Write,
+    ) -> Result<bool, EvalError> {
+        self.send_request(form, false)?;
+        let (success, data_len) = self.read_response_header()?;
+        let data_len = u64::try_from(data_len).unwrap();
+        let copied = std::io::copy(&mut (&mut self.sock).take(data_len), out)?;
+        if copied != data_len {
+            return Err(std::io::ErrorKind::UnexpectedEof.into());
+        }
+        self.io_stats.bytes_received += data_len;
+        Ok(success)
+    }
+
+    /// Sends form to the server for evaluation and returns the response frame,
+    /// i.e. response length, status byte and response data.
+    pub fn eval_frame(&mut self, form: &[u8]) -> Result<Vec<u8>, EvalError> {
+        self.send_request(form, false)?;
+        let (success, data_len) = self.read_response_header()?;
+        // The header has been validated so encoding it again produces the
+        // same bytes as the ones received.
+        let mut frame = Vec::with_capacity(
+            protocol::RESPONSE_HEADER_LEN + data_len.min(INITIAL_CAPACITY),
+        );
+        frame.extend_from_slice(
+            &self.byte_order.encode(u64::try_from(data_len).unwrap() + 1),
+        );
+        frame.push(u8::from(success));
+        (&mut self.sock)
+            .take(u64::try_from(data_len).unwrap())
+            .read_to_end(&mut frame)?;
+        if frame.len() != protocol::RESPONSE_HEADER_LEN + data_len {
+            return Err(std::io::ErrorKind::UnexpectedEof.into());
+        }
+        self.io_stats.bytes_received += u64::try_from(data_len).unwrap();
+        Ok(frame)
+    }
+
+    /// Reads response header from the server, i.e. response length and the
+    /// status byte.
+    ///
+    /// Returns whether evaluation succeeded and length of the response data.
+    /// The caller is responsible for reading the data.
+    fn read_response_header(&mut self) -> Result<(bool, usize), EvalError> {
+        self.pending.received();
+        let mut buf = [0u8; protocol::RESPONSE_LENGTH_LEN];
+        self.sock.read_exact(&mut buf)?;
+        self.read_response_status(buf)
+    }
+
+    /// Decodes response length and reads the status byte which follows it.
+    ///
+    /// Returns whether evaluation succeeded and length of the response data.
+    fn read_response_status(
+        &mut self,
+        buf: [u8; protocol::RESPONSE_LENGTH_LEN],
+    ) -> Result<(bool, usize), EvalError> {
+        let res_len = self.byte_order.decode(buf);
         if res_len == 0 {
             return Err(EvalError::NoResponse);
         }
-        let data_len = usize::try_from(res_len - 1)
-            .map_err(|_| EvalError::ResponseTooLarge(res_len - 1))?;
 
+        // Check status before the length.  If it’s invalid, the length is
+        // most likely bogus as well.
         let mut state = 0u8;
-        self.0.read_exact(core::slice::from_mut(&mut state))?;
+        self.sock.read_exact(core::slice::from_mut(&mut state))?;
+        let success = protocol::decode_status(state)?;
+
+        let data_len = res_len - 1;
+        let Some(len) =
+            protocol::check_response_len(data_len, self.max_response)?
+        else {
+            // The connection is unusable at this point anyway so read start
+            // of the response for diagnostics.
+            let mut prefix = Vec::new();
+            let prefix = (&mut self.sock)
+                .take(data_len.min(TOO_LARGE_PREFIX_LEN))
+                .read_to_end(&mut prefix)
+                .ok()
+                .map(|_| prefix);
+            return Err(EvalError::ResponseTooLarge(data_len, prefix));
+        };
+        self.last_was_error = Some(!success);
+        self.io_stats.bytes_received +=
+            u64::try_from(protocol::RESPONSE_HEADER_LEN).unwrap();
+        Ok((success, len))
+    }
+}
+
 
-        let mut response = vec![0u8; data_len];
-        self.0.read_exact(&mut response)?;
-        Ok(if state == 1 { Ok(response) } else { Err(response) })
+/// Awaits `fut` failing with `TimedOut` error if it doesn’t complete within
+/// `timeout`.
+#[cfg(feature = "tokio")]
+async fn with_timeout<T>(
+    timeout: Option<std::time::Duration>,
+    fut: impl Future<Output = std::io::Result<T>>,
+) -> std::io::Result<T> {
+    match timeout {
+        None => fut.await,
+        Some(timeout) => tokio::time::timeout(timeout, fut)
+            .await
+            .unwrap_or_else(|_| Err(std::io::ErrorKind::TimedOut.into())),
     }
 }
 
 
 /// A Unix-socket-based connection to the Sawfish server using async I/O.
 #[cfg(feature = "async")]
-pub struct AsyncClient<S>(pub S);
+pub struct AsyncClient<S> {
+    pub sock: S,
+    /// Maximum length of response data.  Reading longer responses fails with
+    /// [`EvalError::ResponseTooLarge`].
+    pub max_response: usize,
+    /// Maximum length of a form.  Sending longer forms fails with
+    /// [`EvalError::RequestTooLarge`].
+    pub max_request: usize,
+    pending: Pending,
+}
+
+#[cfg(feature = "async")]
+impl<S> AsyncClient<S> {
+    /// Constructs a new client communicating over given socket.
+    pub fn new(sock: S) -> Self {
+        Self {
+            sock,
+            max_response: crate::DEFAULT_MAX_RESPONSE,
+            max_request: crate::DEFAULT_MAX_REQUEST,
+            pending: Pending::default(),
+        }
+    }
+}
 
 #[cfg(feature = "tokio")]
 impl AsyncClient<tokio_util::compat::Compat<tokio::net::UnixStream>> {
     /// Opens a connection to the Sawfish server.
-    pub async fn open(display: &str) -> Result<Self, ConnError> {
+    ///
+    /// If `timeout` is given, fails with `TimedOut` error if connection isn’t
+    /// established within that time.
+    pub async fn open(
+        display: &str,
+        timeout: Option<std::time::Duration>,
+    ) -> Result<Self, ConnError> {
+        Self::connect(server_path(display)?, timeout).await
+    }
+
+    /// Opens a connection to the Sawfish server listening at given path.
+    async fn connect(
+        path: std::path::PathBuf,
+        timeout: Option<std::time::Duration>,
+    ) -> Result<Self, ConnError> {
         use tokio_util::compat::TokioAsyncReadCompatExt;
 
+        let connect = tokio::net::UnixStream::connect(path.as_path());
+        with_timeout(timeout, connect)
+            .await
+            .map(|socket| Self::new(socket.compat()))
+            .map_err(|err| ConnError::Io(path, err))
+    }
+
+    /// Waits for the Sawfish server socket to appear and opens a connection
+    /// to it.
+    ///
+    /// Fails with `TimedOut` error if the socket doesn’t appear or the
+    /// connection isn’t established within `timeout`.
+    pub async fn open_wait(
+        display: &str,
+        timeout: std::time::Duration,
+    ) -> Result<Self, ConnError> {
+        Self::connect_wait(server_path(display)?, timeout).await
+    }
+
+    /// Waits for socket at given path to appear and opens a connection to it.
+    async fn connect_wait(
+        path: std::path::PathBuf,
+        timeout: std::time::Duration,
+    ) -> Result<Self, ConnError> {
+        let deadline = tokio::time::Instant::now() + timeout;
+        let mut delay = WAIT_INITIAL_DELAY;
+        loop {
+            let remaining =
+                deadline.saturating_duration_since(tokio::time::Instant::now());
+            if tokio::fs::metadata(&path).await.is_ok() {
+                return Self::connect(path, Some(remaining)).await;
+            } else if remaining.is_zero() {
+                let err = std::io::ErrorKind::TimedOut.into();
+                return Err(ConnError::Io(path, err));
+            }
+            tokio::time::sleep(delay.min(remaining)).await;
+            delay = (delay * 2).min(WAIT_MAX_DELAY);
+        }
+    }
+}
+
+#[cfg(feature = "smol")]
+impl AsyncClient<smol::Async<UnixStream>> {
+    /// Opens a connection to the Sawfish server.
+    pub async fn open_smol(display: &str) -> Result<Self, ConnError> {
         let path = server_path(display)?;
-        tokio::net::UnixStream::connect(path.as_path())
+        smol::Async::<UnixStream>::connect(path.as_path())
             .await
-            .map(|socket| Self(socket.compat()))
+            .map(Self::new)
             .map_err(|err| ConnError::Io(path, err))
     }
+
+    /// Waits for the Sawfish server socket to appear and opens a connection
+    /// to it.
+    ///
+    /// Fails with `TimedOut` error if the socket doesn’t appear within
+    /// `timeout`.
+    pub async fn open_smol_wait(
+        display: &str,
+        timeout: std::time::Duration,
+    ) -> Result<Self, ConnError> {
+        let path = server_path(display)?;
+        let deadline = std::time::Instant::now() + timeout;
+        let mut delay = WAIT_INITIAL_DELAY;
+        loop {
+            let remaining =
+                deadline.saturating_duration_since(std::time::Instant::now());
+            if smol::fs::metadata(&path).await.is_ok() {
+                return smol::Async::<UnixStream>::connect(path.as_path())
+                    .await
+                    .map(Self::new)
+                    .map_err(|err| ConnError::Io(path, err));
+            } else if remaining.is_zero() {
+                let err = std::io::ErrorKind::TimedOut.into();
+                return Err(ConnError::Io(path, err));
+            }
+            smol::Timer::after(delay.min(remaining)).await;
+            delay = (delay * 2).min(WAIT_MAX_DELAY);
+        }
+    }
 }
 
 #[cfg(feature = "async")]
@@ -125,43 +681,137 @@ impl<S: AsyncRead + AsyncWrite + Unpin> AsyncClient<S> {
         if is_async { Ok(Ok(Vec::new())) } else { self.read_response().await }
     }
 
+    /// Splits the connection into sending and receiving halves.
+    pub fn split(
+        self,
+    ) -> (AsyncClient<WriteHalf<S>>, AsyncClient<ReadHalf<S>>) {
+        let (rd, wr) = self.sock.split();
+        let Self { max_response, max_request, pending, .. } = self;
+        (
+            AsyncClient {
+                sock: wr,
+                max_response,
+                max_request,
+                pending: pending.clone(),
+            },
+            AsyncClient { sock: rd, max_response, max_request, pending },
+        )
+    }
+}
+
+#[cfg(feature = "async")]
+impl<S: AsyncWrite + Unpin> AsyncClient<S> {
     /// Sends request to the server.
     ///
     /// If `is_async` is `false`, the caller is responsible for calling
     /// [`Self::read_response`].  Otherwise, the requests and responses will get
     /// out of sync.
-    async fn send_request(
+    pub async fn send_request(
         &mut self,
         form: &[u8],
         is_async: bool,
     ) -> Result<(), EvalError> {
-        let req_type = u8::from(is_async);
-        let req_len = u64::try_from(form.len()).unwrap();
-        let mut buf = [0u8; 9];
-        buf[0] = req_type;
-        buf[1..].copy_from_slice(&req_len.to_ne_bytes());
+        protocol::check_request_len(form.len(), self.max_request)?;
+        // Unlike the synchronous client, byte order isn’t configurable.
+        let buf = protocol::request_header(
+            form.len(),
+            is_async,
+            protocol::ByteOrder::Native,
+        )?;
         let mut bufs =
             [std::io::IoSlice::new(&buf), std::io::IoSlice::new(form)];
-        self.0.write_all_vectored(&mut bufs).await.map_err(EvalError::from)
+        let mut bufs = &mut bufs[..];
+        self.pending.sent(is_async);
+
+        // Track how much has been written so that a request interrupted
+        // half-way can be reported as such.
+        let mut written = 0;
+        while !bufs.is_empty() {
+            let err = match self.sock.write_vectored(bufs).await {
+                Ok(0) => std::io::ErrorKind::WriteZero.into(),
+                Ok(len) => {
+                    written += len;
+                    std::io::IoSlice::advance_slices(&mut bufs, len);
+                    continue;
+                }
+                Err(err) if err.kind() == std::io::ErrorKind::Interrupted => {
+                    continue;
+                }
+                Err(err) => err,
+            };
+            return Err(if written == 0 {
+                EvalError::from_write(err)
+            } else {
+                EvalError::PartialRequest(written, err)
+            });
+        }
+        Ok(())
     }
+}
 
+#[cfg(feature = "async")]
+impl<S: AsyncRead + Unpin> AsyncClient<S> {
     /// Reads response from the server.
-    async fn read_response(&mut self) -> Result<EvalResponse, EvalError> {
-        let mut buf = [0u8; 8];
-        self.0.read_exact(&mut buf).await?;
+    pub async fn read_response(&mut self) -> Result<EvalResponse, EvalError> {
+        let mut response = Vec::new();
+        let success = self.read_response_into(&mut response).await?;
+        Ok(protocol::make_response(success, response))
+    }
+
+    /// Reads response from the server into a buffer taken from the pool.
+    pub async fn read_response_pooled(
+        &mut self,
+        pool: &crate::BufferPool,
+    ) -> Result<crate::PooledResponse, EvalError> {
+        let mut buf = pool.get();
+        let success = self.read_response_into(buf.as_mut_vec()).await?;
+        Ok(if success { Ok(buf) } else { Err(buf) })
+    }
+
+    /// Reads response from the server into given buffer.
+    ///
+    /// Clears the buffer and reads response data into it.  Returns whether
+    /// evaluation succeeded.
+    pub async fn read_response_into(
+        &mut self,
+        response: &mut Vec<u8>,
+    ) -> Result<bool, EvalError> {
+        self.pending.received();
+        response.clear();
+        let mut buf = [0u8; protocol::RESPONSE_LENGTH_LEN];
+        self.sock.read_exact(&mut buf).await?;
+        // Like when sending, native byte order is always used.
         let res_len = u64::from_ne_bytes(buf);
         if res_len == 0 {
             return Err(EvalError::NoResponse);
         }
-        let data_len = usize::try_from(res_len - 1)
-            .map_err(|_| EvalError::ResponseTooLarge(res_len - 1))?;
 
         let mut state = 0u8;
-        self.0.read_exact(core::slice::from_mut(&mut state)).await?;
+        self.sock.read_exact(core::slice::from_mut(&mut state)).await?;
+        let success = protocol::decode_status(state)?;
+
+        let data_len = res_len - 1;
+        let Some(data_len) =
+            protocol::check_response_len(data_len, self.max_response)?
+        else {
+            // The connection is unusable at this point anyway so read start
+            // of the response for diagnostics.
+            let mut prefix = Vec::new();
+            let prefix = (&mut self.sock)
+                .take(data_len.min(TOO_LARGE_PREFIX_LEN))
+                .read_to_end(&mut prefix)
+                .await
+                .ok()
+                .map(|_| prefix);
+            return Err(EvalError::ResponseTooLarge(data_len, prefix));
+        };
 
-        let mut response = vec![0u8; data_len];
-        self.0.read_exact(&mut response).await?;
-        Ok(if state == 1 { Ok(response) } else { Err(response) })
+        response.reserve(data_len.min(INITIAL_CAPACITY));
+        (&mut self.sock).take(res_len - 1).read_to_end(response).await?;
+        if response.len() != data_len {
+            return Err(std::io::ErrorKind::UnexpectedEof.into());
+        }
+        Ok(success)
     }
 }
 
@@ -172,56 +822,101 @@ mod test_eval {
 
     use super::*;
 
-    fn server_thread(mut server: UnixStream) -> () {
-        let mut buf = [0; 32];
-        let mut pos = 0;
+    /// Test server loop.
+    ///
+    /// Reads requests and responds to each with whatever `handler` returns
+    /// for it.  `handler` is called with request’s `is_async` flag and the
+    /// form and returns response to send or `None` if the server should not
+    /// respond.  Handles any number of requests, including ones sent
+    /// back-to-back without waiting for responses.
+    fn server_thread(
+        mut server: UnixStream,
+        handler: impl Fn(bool, &[u8]) -> Option<EvalResponse>,
+    ) {
+        let mut buf = Vec::new();
+        let mut chunk = [0; 4096];
         loop {
-            match server.read(&mut buf[pos..]) {
+            match server.read(&mut chunk) {
                 Ok(0) => break,
-                Ok(n) => pos += n,
+                Ok(n) => buf.extend_from_slice(&chunk[..n]),
                 Err(err) => {
                     if err.kind() != std::io::ErrorKind::WouldBlock &&
                         err.kind() != std::io::ErrorKind::TimedOut
                     {
                         panic!("{err}");
                     }
-                    assert_eq!(
-                        0,
-                        pos,
-                        "Server timed out with data left: {:?}",
-                        &buf[..pos]
+                    assert!(
+                        buf.is_empty(),
+                        "Server timed out with data left: {buf:?}"
                     );
                     break;
                 }
             }
-            if pos < 9 {
-                continue;
+            while let Some(len) = handle_request(&mut server, &buf, &handler) {
+                buf.drain(..len);
             }
+        }
+    }
 
-            let len = u64::from_ne_bytes(buf[1..9].try_into().unwrap());
-            let len = usize::try_from(len).unwrap();
-            let response = match (buf[0], buf[9..].get(..len)) {
-                (_, None) => continue,
-                (0, Some(b"ok")) => Some(Ok(())),
-                (0, Some(b"err")) => Some(Err(())),
-                (1, Some(b"async")) => None,
-                (is_async, Some(form)) => panic!(
-                    "Invalid requset: is_async: {is_async}; form: {form:?}"
-                ),
+    /// Handles a single request at the front of the buffer if it’s complete.
+    /// Returns length of the request.
+    fn handle_request(
+        server: &mut UnixStream,
+        buf: &[u8],
+        handler: impl Fn(bool, &[u8]) -> Option<EvalResponse>,
+    ) -> Option<usize> {
+        const HEADER_LEN: usize = protocol::REQUEST_HEADER_LEN;
+        let len =
+            u64::from_ne_bytes(buf.get(1..HEADER_LEN)?.try_into().unwrap());
+        let len = usize::try_from(len).unwrap();
+        let form = buf[HEADER_LEN..].get(..len)?;
+        assert!(buf[0] <= 1, "Invalid request type: {}", buf[0]);
+
+        if let Some(response) = handler(buf[0] == 1, form) {
+            let (ok, data) = match response {
+                Ok(data) => (true, data),
+                Err(data) => (false, data),
             };
+            let len = u64::try_from(data.len() + 1).unwrap();
+            server.write_all(&len.to_ne_bytes()).unwrap();
+            server.write_all(&[u8::from(ok)]).unwrap();
+            server.write_all(&data).unwrap();
+        }
+        Some(HEADER_LEN + len)
+    }
 
-            if let Some(response) = response {
-                let mut buf = *b"\x09\0\0\0\0\0\0\0\xffresponse";
-                buf[8] = response.is_ok() as u8;
-                server.write_all(&buf).unwrap();
+    /// Responses of the test server started with [`start_test`].
+    fn default_handler(is_async: bool, form: &[u8]) -> Option<EvalResponse> {
+        match (is_async, form) {
+            (false, b"ok") => Some(Ok(b"response".to_vec())),
+            (false, b"err") => Some(Err(b"response".to_vec())),
+            (false, b"big") => Some(Ok(big_response().into_bytes())),
+            (false, b"t") => Some(Ok(b"t".to_vec())),
+            (false, b"empty") => Some(Err(Vec::new())),
+            (false, b"sawfish-version") => Some(Ok(b"\"1.13.0\"".to_vec())),
+            (false, b"hang") => None,
+            (true, b"async") => None,
+            (is_async, form) => {
+                panic!("Invalid requset: is_async: {is_async}; form: {form:?}")
             }
-
-            buf.copy_within(len + 9.., 0);
-            pos -= len + 9;
         }
     }
 
+    /// Returns a multi-megabyte response sent by the server for `big` form.
+    fn big_response() -> String {
+        (0..4 << 20).map(|idx| char::from(b'a' + (idx % 26) as u8)).collect()
+    }
+
     fn start_test(name: &str) -> (UnixStream, std::thread::JoinHandle<()>) {
+        start_test_with(name, default_handler)
+    }
+
+    /// Starts test server which responds to requests using `handler`; see
+    /// [`server_thread`].
+    fn start_test_with(
+        name: &str,
+        handler: impl Fn(bool, &[u8]) -> Option<EvalResponse> + Send + 'static,
+    ) -> (UnixStream, std::thread::JoinHandle<()>) {
         const SECOND: std::time::Duration = std::time::Duration::new(1, 0);
 
         let (client, server) = UnixStream::pair().unwrap();
@@ -232,7 +927,7 @@ mod test_eval {
 
         let server = std::thread::Builder::new()
             .name(format!("test-{name}-server"))
-            .spawn(move || server_thread(server))
+            .spawn(move || server_thread(server, handler))
             .unwrap();
 
         (client, server)
@@ -241,9 +936,9 @@ mod test_eval {
     #[track_caller]
     fn do_test(want: Result<&str, &str>, form: &str, is_async: bool) {
         let (client, server) = start_test(form);
-        let mut client = Client(client);
+        let mut client = Client::new(client);
         let got = client.eval(form.as_bytes(), is_async);
-        client.0.shutdown(std::net::Shutdown::Both).unwrap();
+        client.sock.shutdown(std::net::Shutdown::Both).unwrap();
         core::mem::drop(client);
         server.join().unwrap();
 
@@ -260,124 +955,1789 @@ mod test_eval {
     #[test]
     fn test_eval_err() { do_test(Err("response"), "err", false); }
 
+    #[test]
+    fn test_eval_big() { do_test(Ok(&big_response()), "big", false); }
+
     #[test]
     fn test_send() { do_test(Ok(""), "async", true); }
 
-    #[cfg(feature = "async")]
-    #[track_caller]
-    fn do_async_test(want: Result<&str, &str>, form: &str, is_async: bool) {
-        use tokio_util::compat::TokioAsyncReadCompatExt;
+    #[test]
+    fn test_back_to_back_requests() {
+        let (client, server) =
+            start_test_with("back-to-back", |is_async, form| {
+                (!is_async).then(|| Ok([b"echo: ", form].concat()))
+            });
+        let mut client = Client::new(client);
+        let forms: Vec<_> = (0..100)
+            .map(|idx| format!("(form {idx} {})", "x".repeat(idx * 10)))
+            .collect();
+        for (idx, form) in forms.iter().enumerate() {
+            client.send_request(form.as_bytes(), idx % 3 == 0).unwrap();
+        }
+        for (idx, form) in forms.iter().enumerate() {
+            if idx % 3 != 0 {
+                let want = format!("echo: {form}").into_bytes();
+                assert_eq!(Ok(want), client.read_response().unwrap());
+            }
+        }
+        client.sock.shutdown(std::net::Shutdown::Both).unwrap();
+        core::mem::drop(client);
+        server.join().unwrap();
+    }
 
-        let (client, server) = start_test(form);
-        client.set_nonblocking(true).unwrap();
+    #[test]
+    fn test_send_request_matches_render_request() {
+        let (client, mut server) = UnixStream::pair().unwrap();
+        let mut client = Client::new(client);
+        client.send_request(b"(system-name)", false).unwrap();
+        client.send_request(b"(quit)", true).unwrap();
+        core::mem::drop(client);
 
-        let got = {
-            let rt = tokio::runtime::Builder::new_current_thread()
-                .enable_io()
-                .build()
-                .unwrap();
-            let _guerd = rt.enter();
+        let mut got = Vec::new();
+        server.read_to_end(&mut got).unwrap();
+        let want = [
+            protocol::render_request(b"(system-name)", false),
+            protocol::render_request(b"(quit)", true),
+        ]
+        .concat();
+        assert_eq!(want, got);
+    }
 
-            let client = tokio::net::UnixStream::from_std(client).unwrap();
-            let mut client = AsyncClient(client.compat());
-            rt.block_on(async {
-                let got = client.eval(form.as_bytes(), is_async).await;
-                client
-                    .0
-                    .into_inner()
-                    .into_std()
-                    .unwrap()
-                    .shutdown(std::net::Shutdown::Both)
-                    .unwrap();
-                got
-            })
-        };
-        server.join().unwrap();
+    #[test]
+    fn test_quit_restart() {
+        let (client, mut server) = UnixStream::pair().unwrap();
+        let mut client = crate::Client(crate::Inner::Unix(Client::new(client)));
+        client.quit().unwrap();
+        client.restart().unwrap();
+        core::mem::drop(client);
 
-        let got = got
-            .unwrap()
-            .map(|bytes| String::from_utf8(bytes).unwrap())
-            .map_err(|bytes| String::from_utf8(bytes).unwrap());
-        assert_eq!(want, got.as_deref().map_err(String::as_str));
+        let mut got = Vec::new();
+        server.read_to_end(&mut got).unwrap();
+        let want = [
+            protocol::render_request(b"(quit)", true),
+            protocol::render_request(b"(restart)", true),
+        ]
+        .concat();
+        assert_eq!(want, got);
     }
 
-    #[cfg(feature = "async")]
     #[test]
-    fn test_async_eval_ok() { do_async_test(Ok("response"), "ok", false); }
+    fn test_display() {
+        let dir = std::env::temp_dir()
+            .join(format!("sawfish-client-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("display");
+        let _ = std::fs::remove_file(&path);
+        let listener = std::os::unix::net::UnixListener::bind(&path).unwrap();
 
-    #[cfg(feature = "async")]
-    #[test]
-    fn test_async_eval_err() { do_async_test(Err("response"), "err", false); }
+        let display = canonical_display(":1");
+        let client = Client::connect(path.clone(), display).unwrap();
+        let client = crate::Client(crate::Inner::Unix(client));
+        assert_eq!("host.local:1.0", client.display());
+
+        core::mem::drop(listener);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
 
-    #[cfg(feature = "async")]
     #[test]
-    fn test_async_send() { do_async_test(Ok(""), "async", true); }
-}
+    fn test_connect_retry() {
+        const DELAY: std::time::Duration = std::time::Duration::from_millis(50);
 
+        let dir = std::env::temp_dir()
+            .join(format!("sawfish-client-retry-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("socket");
+        let _ = std::fs::remove_file(&path);
 
+        // No server; all attempts fail.
+        let res = Client::connect_retry(path.clone(), String::new(), 3, DELAY);
+        match res {
+            Err(ConnError::Io(got, err)) => {
+                assert_eq!(path, got);
+                assert_eq!(std::io::ErrorKind::NotFound, err.kind());
+            }
+            res => panic!("Unexpected result: {:?}", res.err()),
+        }
 
-/// System's canonical hostname.
-static SYSTEM_NAME: std::sync::LazyLock<Option<String>> =
-    std::sync::LazyLock::new(get_system_name);
+        // Server starts listening after the second attempt.
+        let server = std::thread::spawn({
+            let path = path.clone();
+            move || {
+                std::thread::sleep(DELAY * 3 / 2);
+                let listener =
+                    std::os::unix::net::UnixListener::bind(&path).unwrap();
+                listener.accept().unwrap();
+            }
+        });
+        let res = Client::connect_retry(path.clone(), String::new(), 10, DELAY);
+        assert!(res.is_ok(), "{:?}", res.err());
+        server.join().unwrap();
 
-/// Returns canonical system name, i.e. a fully-qualified hostname of the host.
-fn get_system_name() -> Option<String> {
-    if cfg!(test) {
-        Some("host.local".into())
-    } else {
-        let host = dns_lookup::get_hostname().ok()?;
-        if !host.contains('.') &&
-            let Some(host) = canonical_host_impl(&host)
-        {
-            return Some(host);
-        }
-        Some(host)
+        std::fs::remove_dir_all(&dir).unwrap();
     }
-}
 
-/// Returns the canonical, fully-qualified, lowercase version of the hostname.
-fn canonical_host(host: &str) -> String {
-    canonical_host_impl(host).as_deref().unwrap_or(host).to_lowercase()
-}
+    #[test]
+    fn test_transport() {
+        let (client, _server) = UnixStream::pair().unwrap();
+        let client = crate::Client(crate::Inner::Unix(Client::new(client)));
+        assert_eq!(crate::Transport::Unix, client.transport());
+        assert_eq!(
+            crate::Transport::Unix.capabilities(),
+            client.capabilities()
+        );
+        assert_eq!(None, client.x11_request_window());
+    }
 
-fn canonical_host_impl(host: &str) -> Option<String> {
-    if cfg!(test) {
-        Some(if host == "nofq" {
-            host.into()
-        } else if host.contains('.') {
-            host.to_lowercase()
-        } else {
+    #[test]
+    fn test_as_fd() {
+        use std::os::fd::{AsFd, AsRawFd};
+
+        let (client, server) = UnixStream::pair().unwrap();
+        let fd = client.as_raw_fd();
+        let client = crate::Client(crate::Inner::Unix(Client::new(client)));
+        assert_eq!(fd, client.as_raw_fd());
+        assert_eq!(fd, client.as_fd().as_raw_fd());
+
+        // The descriptor refers to the connected socket.
+        let mut sock =
+            UnixStream::from(client.as_fd().try_clone_to_owned().unwrap());
+        sock.write_all(b"x").unwrap();
+        let mut buf = [0u8];
+        (&server).read_exact(&mut buf).unwrap();
+        assert_eq!(b"x", &buf);
+    }
+
+    #[test]
+    fn test_byte_order() {
+        use protocol::ByteOrder;
+
+        let (client, mut server) = UnixStream::pair().unwrap();
+        let mut client = Client::new(client);
+        client.byte_order = ByteOrder::Big;
+        client.send_request(b"t", false).unwrap();
+        let mut buf = [0u8; 10];
+        server.read_exact(&mut buf).unwrap();
+        assert_eq!(b"\0\0\0\0\0\0\0\0\x01t", &buf);
+
+        client.byte_order = ByteOrder::Little;
+        server.write_all(b"\x02\0\0\0\0\0\0\0\x01t").unwrap();
+        assert_eq!(Ok(b"t".to_vec()), client.read_response().unwrap());
+    }
+
+    #[test]
+    fn test_disconnected() {
+        let (client, server) = UnixStream::pair().unwrap();
+        core::mem::drop(server);
+        let mut client = Client::new(client);
+        match client.eval(b"ok", false) {
+            Err(EvalError::Disconnected) => (),
+            res => panic!("Unexpected result: {res:?}"),
+        }
+    }
+
+    #[test]
+    fn test_eval_to_writer() {
+        let (client, server) = start_test("writer");
+        let mut client = Client::new(client);
+        let mut out = Vec::new();
+        assert!(client.eval_to_writer(b"big", &mut out).unwrap());
+        assert_eq!(big_response().as_bytes(), out.as_slice());
+        out.clear();
+        assert!(!client.eval_to_writer(b"err", &mut out).unwrap());
+        assert_eq!(b"response", out.as_slice());
+        core::mem::drop(client);
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn test_eval_status() {
+        let (client, server) = start_test("eval_status");
+        let mut client = crate::Client(crate::Inner::Unix(Client::new(client)));
+        assert!(client.eval_status("ok").unwrap());
+        assert!(!client.eval_status("err").unwrap());
+
+        assert!(client.eval_status("big").unwrap());
+
+        // The connection stays in sync.
+        assert_eq!(Ok(b"t".to_vec()), client.eval("t").unwrap());
+
+        core::mem::drop(client);
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn test_max_response() {
+        let (client, server) = start_test("max-response");
+        let mut client = Client::new(client);
+        client.max_response = 8;
+        assert_eq!(
+            Ok(b"response".to_vec()),
+            client.eval(b"ok", false).unwrap()
+        );
+        client.max_response = 7;
+        // The response is consumed in its entirety as part of the prefix.
+        match client.eval(b"ok", false) {
+            Err(EvalError::ResponseTooLarge(8, Some(prefix))) => {
+                assert_eq!(b"response", prefix.as_slice())
+            }
+            res => panic!("Unexpected result: {res:?}"),
+        }
+        client.max_response = 10;
+        assert_eq!(
+            Ok(b"response".to_vec()),
+            client.eval(b"ok", false).unwrap()
+        );
+
+        // With large response, only the prefix is read.
+        client.max_response = 1000;
+        match client.eval(b"big", false) {
+            Err(EvalError::ResponseTooLarge(len, Some(prefix))) => {
+                let want = big_response();
+                assert_eq!(u64::try_from(want.len()).unwrap(), len);
+                assert_eq!(&want.as_bytes()[..256], prefix.as_slice());
+                // Consume rest of the response so the server doesn’t get an
+                // error.
+                let mut rest = (&mut client.sock).take(len - 256);
+                std::io::copy(&mut rest, &mut std::io::sink()).unwrap();
+            }
+            res => panic!("Unexpected result: {res:?}"),
+        }
+        core::mem::drop(client);
+        server.join().unwrap();
+    }
+
+    /// Responses with lengths at the boundary and expected results.
+    /// Expected result of reading a response; `None` means `NoResponse`.
+    type Want = Option<Result<&'static [u8], &'static [u8]>>;
+
+    const SHORT_RESPONSES: [(&[u8], Want); 5] = [
+        (&[], None),
+        (&[1], Some(Ok(b""))),
+        (&[0], Some(Err(b""))),
+        (&[1, b'x'], Some(Ok(b"x"))),
+        (&[0, b'x'], Some(Err(b"x"))),
+    ];
+
+    #[test]
+    fn test_eval_multi() {
+        let (client, mut server) = UnixStream::pair().unwrap();
+        let mut client = Client::new(client);
+        for (ok, data) in [(1u8, &b"first"[..]), (0, b"second")] {
+            let len = u64::try_from(data.len() + 1).unwrap();
+            server.write_all(&len.to_ne_bytes()).unwrap();
+            server.write_all(&[ok]).unwrap();
+            server.write_all(data).unwrap();
+        }
+
+        let got = client.eval_multi(b"(multi)", 2).unwrap();
+        assert_eq!([Ok(b"first".to_vec()), Err(b"second".to_vec())], *got);
+        assert_eq!(
+            Vec::<EvalResponse>::new(),
+            client.eval_multi(b"t", 0).unwrap()
+        );
+        core::mem::drop(client);
+
+        let mut got = Vec::new();
+        server.read_to_end(&mut got).unwrap();
+        let want = [
+            protocol::render_request(b"(multi)", false),
+            protocol::render_request(b"t", true),
+        ]
+        .concat();
+        assert_eq!(want, got);
+    }
+
+    #[test]
+    fn test_short_responses() {
+        for (bytes, want) in SHORT_RESPONSES {
+            let (client, mut server) = UnixStream::pair().unwrap();
+            let mut client = Client::new(client);
+            client.send_request(b"ok", false).unwrap();
+            let len = u64::try_from(bytes.len()).unwrap();
+            server.write_all(&len.to_ne_bytes()).unwrap();
+            server.write_all(bytes).unwrap();
+            match (want, client.read_response()) {
+                (None, Err(EvalError::NoResponse)) => (),
+                (Some(want), Ok(got)) => {
+                    assert_eq!(want, got.as_deref().map_err(Vec::as_slice))
+                }
+                (want, got) => panic!("{bytes:?}: {want:?} ≠ {got:?}"),
+            }
+        }
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_async_short_responses() {
+        for (bytes, want) in SHORT_RESPONSES {
+            let len = u64::try_from(bytes.len()).unwrap();
+            let stream = crate::mock::MockStream::new()
+                .chunk(&len.to_ne_bytes())
+                .chunk(bytes);
+            match (want, eval_mock(stream, b"ok").0) {
+                (None, Err(EvalError::NoResponse)) => (),
+                (Some(want), Ok(got)) => {
+                    assert_eq!(want, got.as_deref().map_err(Vec::as_slice))
+                }
+                (want, got) => panic!("{bytes:?}: {want:?} ≠ {got:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_desync() {
+        let (client, mut server) = UnixStream::pair().unwrap();
+        let mut client = Client::new(client);
+        client.send_request(b"ok", false).unwrap();
+        server.write_all(&2u64.to_ne_bytes()).unwrap();
+        server.write_all(b"\x07x").unwrap();
+        match client.read_response() {
+            Err(EvalError::Desync) => (),
+            res => panic!("Unexpected result: {res:?}"),
+        }
+
+        // Absurd length is reported as desync even with valid status byte.
+        // This is what reading from the middle of a response looks like.
+        client.max_response = usize::MAX;
+        client.send_request(b"ok", false).unwrap();
+        server.write_all(b"(system-").unwrap();
+        server.write_all(b"\x01").unwrap();
+        match client.read_response() {
+            Err(EvalError::Desync) => (),
+            res => panic!("Unexpected result: {res:?}"),
+        }
+    }
+
+    /// Returns response frame with given status and data in native byte
+    /// order.
+    fn response_frame(success: bool, data: &[u8]) -> Vec<u8> {
+        let len = u64::try_from(data.len() + 1).unwrap();
+        [&len.to_ne_bytes()[..], &[u8::from(success)], data].concat()
+    }
+
+    /// Splits `data` into pieces of given sizes.  Whatever remains after the
+    /// last size is returned as the final piece.
+    fn split_at_sizes<'a>(
+        mut data: &'a [u8],
+        sizes: &[usize],
+    ) -> Vec<&'a [u8]> {
+        let mut pieces = Vec::new();
+        for &size in sizes {
+            let (head, tail) = data.split_at(size.min(data.len()));
+            pieces.push(head);
+            data = tail;
+        }
+        if !data.is_empty() {
+            pieces.push(data);
+        }
+        pieces
+    }
+
+    /// Ways in which response frames are split into separate writes (or
+    /// reads with the mock stream) in fragmentation tests.
+    const FRAGMENTS: &[&[usize]] = &[
+        // Length, status and payload separately.
+        &[8, 1],
+        // Split in the middle of the length.
+        &[3, 5, 1],
+        &[7],
+        // Status together with the length or with the payload.
+        &[9],
+        &[8],
+        // Split in the middle of the payload.
+        &[9, 4],
+        &[12, 1, 1, 1],
+        // One byte at a time.
+        &[1; 32],
+    ];
+
+    /// Starts a server which writes `data` in pieces of given sizes pausing
+    /// between them so the client observes each piece in a separate read.
+    fn start_fragmented(
+        data: Vec<u8>,
+        sizes: &'static [usize],
+    ) -> (UnixStream, std::thread::JoinHandle<()>) {
+        let (client, mut server) = UnixStream::pair().unwrap();
+        client.set_read_timeout(Some(std::time::Duration::new(1, 0))).unwrap();
+        let server = std::thread::spawn(move || {
+            for piece in split_at_sizes(&data, sizes) {
+                server.write_all(piece).unwrap();
+                server.flush().unwrap();
+                std::thread::sleep(std::time::Duration::from_millis(2));
+            }
+            // Consume requests until client disconnects.
+            std::io::copy(&mut server, &mut std::io::sink()).unwrap();
+        });
+        (client, server)
+    }
+
+    #[test]
+    fn test_fragmented_response() {
+        for sizes in FRAGMENTS {
+            let frame = response_frame(true, b"response");
+            let (client, server) = start_fragmented(frame, sizes);
+            let mut client = Client::new(client);
+            let got = client.eval(b"ok", false).unwrap();
+            assert_eq!(Ok(b"response".to_vec()), got, "{sizes:?}");
+            core::mem::drop(client);
+            server.join().unwrap();
+
+            let frame = response_frame(false, b"response");
+            let (client, server) = start_fragmented(frame, sizes);
+            let mut client = Client::new(client);
+            let mut out = Vec::new();
+            let got = client.eval_to_writer(b"err", &mut out).unwrap();
+            assert!(!got, "{sizes:?}");
+            assert_eq!(b"response", out.as_slice(), "{sizes:?}");
+            core::mem::drop(client);
+            server.join().unwrap();
+
+            let frame = response_frame(true, b"response");
+            let (client, server) = start_fragmented(frame.clone(), sizes);
+            let mut client = Client::new(client);
+            assert_eq!(frame, client.eval_frame(b"ok").unwrap(), "{sizes:?}");
+            core::mem::drop(client);
+            server.join().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_fragmented_pipelined_responses() {
+        // Second response’s header split across pieces at different points,
+        // including pieces holding end of one response and start of the next.
+        const SIZES: &[&[usize]] =
+            &[&[14], &[16], &[17], &[18, 3], &[20, 9], &[1; 64]];
+        let data = [
+            response_frame(true, b"first"),
+            response_frame(false, b"second"),
+            response_frame(true, b""),
+        ]
+        .concat();
+        for sizes in SIZES {
+            let (client, server) = start_fragmented(data.clone(), sizes);
+            let mut client = Client::new(client);
+            for _ in 0..3 {
+                client.send_request(b"form", false).unwrap();
+            }
+            let got = (0..3)
+                .map(|_| client.read_response().unwrap())
+                .collect::<Vec<_>>();
+            let want = [
+                Ok(b"first".to_vec()),
+                Err(b"second".to_vec()),
+                Ok(Vec::new()),
+            ];
+            assert_eq!(&want[..], got.as_slice(), "{sizes:?}");
+            core::mem::drop(client);
+            server.join().unwrap();
+        }
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_mock_fragmented_response() {
+        use crate::mock::MockStream;
+
+        let data = [
+            response_frame(true, b"response"),
+            response_frame(false, b"second"),
+        ]
+        .concat();
+        for sizes in FRAGMENTS {
+            let stream = split_at_sizes(&data, sizes)
+                .into_iter()
+                .fold(MockStream::new(), MockStream::chunk);
+            let mut client = AsyncClient::new(stream);
+            let mut eval = |form: &[u8]| {
+                let res = client.eval(form, false);
+                futures_util::FutureExt::now_or_never(res).unwrap().unwrap()
+            };
+            assert_eq!(Ok(b"response".to_vec()), eval(b"ok"), "{sizes:?}");
+            assert_eq!(Err(b"second".to_vec()), eval(b"err"), "{sizes:?}");
+        }
+    }
+
+    /// Evaluates `form` with an async client reading from `stream`.
+    #[cfg(feature = "async")]
+    fn eval_mock(
+        stream: crate::mock::MockStream,
+        form: &[u8],
+    ) -> (Result<EvalResponse, EvalError>, Vec<u8>) {
+        let mut client = AsyncClient::new(stream);
+        let res =
+            futures_util::FutureExt::now_or_never(client.eval(form, false));
+        (res.unwrap(), client.sock.written)
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_mock_partial_reads() {
+        use crate::mock::MockStream;
+
+        for chunk_size in [1, 3, 8, 9, 100] {
+            let stream = MockStream::new().response(1, b"response", chunk_size);
+            let (res, written) = eval_mock(stream, b"ok");
+            assert_eq!(Ok(b"response".to_vec()), res.unwrap(), "{chunk_size}");
+            assert_eq!(protocol::render_request(b"ok", false), written);
+        }
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_async_eval_pooled() {
+        use crate::mock::MockStream;
+
+        let stream = MockStream::new()
+            .response(1, b"first", 100)
+            .response(0, b"second", 100)
+            .response(1, b"third", 100);
+        let mut client = crate::AsyncClient(AsyncClient::new(stream));
+        let pool = crate::BufferPool::new(2);
+        let mut eval = |form| {
+            let res = client.eval_pooled(form, &pool);
+            futures_util::FutureExt::now_or_never(res).unwrap().unwrap()
+        };
+
+        let first = eval("(first)").unwrap();
+        assert_eq!(b"first", &*first);
+        let ptr = first.as_ptr();
+        core::mem::drop(first);
+
+        // The buffer is recycled.
+        let second = eval("(second)").unwrap_err();
+        assert_eq!(b"second", &*second);
+        assert_eq!(ptr, second.as_ptr());
+
+        // While the buffer is held, another one is used.
+        let third = eval("(third)").unwrap();
+        assert_eq!(b"third", &*third);
+        assert_ne!(ptr, third.as_ptr());
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_async_short_writes() {
+        use crate::mock::MockStream;
+
+        // Streams which accept only a few bytes per write call so the header
+        // and the form are split at various points.  With one byte per call,
+        // at every possible point.
+        for max_write in [1, 2, 5, 9, 10] {
+            let stream = MockStream::new().max_write(max_write).response(
+                1,
+                b"response",
+                100,
+            );
+            let (res, written) = eval_mock(stream, b"(system-name)");
+            assert_eq!(Ok(b"response".to_vec()), res.unwrap(), "{max_write}");
+            assert_eq!(
+                protocol::render_request(b"(system-name)", false),
+                written,
+                "{max_write}"
+            );
+        }
+
+        // Pipelined requests arrive intact and in order.
+        let stream = MockStream::new()
+            .max_write(1)
+            .response(1, b"first", 100)
+            .response(1, b"second", 100);
+        let mut client = crate::AsyncClient(AsyncClient::new(stream));
+        let res = client.eval_pipeline(["(first)", "(second)"]);
+        futures_util::FutureExt::now_or_never(res).unwrap().unwrap();
+        let want = [
+            protocol::render_request(b"(first)", false),
+            protocol::render_request(b"(second)", false),
+        ]
+        .concat();
+        assert_eq!(want, client.0.sock.written);
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_async_eval_progn() {
+        use crate::mock::MockStream;
+
+        let stream = MockStream::new().response(1, b"last", 3);
+        let mut client = crate::AsyncClient(AsyncClient::new(stream));
+        let res = client.eval_progn(["(first)", "(second)", "(last)"]);
+        let res = futures_util::FutureExt::now_or_never(res).unwrap();
+        assert_eq!(Ok(b"last".to_vec()), res.unwrap());
+        assert_eq!(
+            protocol::render_request(b"(progn (first) (second) (last))", false),
+            client.0.sock.written
+        );
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_async_eval_pipeline() {
+        use crate::mock::MockStream;
+
+        let stream = MockStream::new()
+            .response(1, b"first", 5)
+            .response(0, b"second", 100)
+            .response(1, b"", 1);
+        let mut client = crate::AsyncClient(AsyncClient::new(stream));
+        let res = client.eval_pipeline(["(first)", "(second)", "(third)"]);
+        let res = futures_util::FutureExt::now_or_never(res).unwrap();
+        let want =
+            [Ok(b"first".to_vec()), Err(b"second".to_vec()), Ok(Vec::new())];
+        assert_eq!(&want[..], res.unwrap().as_slice());
+        // All requests are written before responses are read.
+        let want = [
+            protocol::render_request(b"(first)", false),
+            protocol::render_request(b"(second)", false),
+            protocol::render_request(b"(third)", false),
+        ]
+        .concat();
+        assert_eq!(want, client.0.sock.written);
+
+        // Response missing.
+        let stream = MockStream::new().response(1, b"first", 100);
+        let mut client = crate::AsyncClient(AsyncClient::new(stream));
+        let res = client.eval_pipeline(["(first)", "(second)"]);
+        match futures_util::FutureExt::now_or_never(res).unwrap() {
+            Err(EvalError::Io(err)) => {
+                assert_eq!(std::io::ErrorKind::UnexpectedEof, err.kind())
+            }
+            res => panic!("Unexpected result: {res:?}"),
+        }
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_async_empty_form() {
+        let (res, written) = eval_mock(crate::mock::MockStream::new(), b"");
+        match res {
+            Err(EvalError::EmptyForm) => (),
+            res => panic!("Unexpected result: {res:?}"),
+        }
+        assert_eq!(b"", written.as_slice());
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_async_partial_request() {
+        use crate::mock::MockStream;
+
+        // Server closes connection before anything is written.
+        let (res, written) = eval_mock(MockStream::new().write_limit(0), b"ok");
+        match res {
+            Err(EvalError::Disconnected) => (),
+            res => panic!("Unexpected result: {res:?}"),
+        }
+        assert_eq!(b"", written.as_slice());
+
+        // Server closes connection in the middle of the header and in the
+        // middle of the form.
+        for limit in [5, 11] {
+            let stream = MockStream::new().write_limit(limit);
+            let (res, written) = eval_mock(stream, b"(form)");
+            match res {
+                Err(EvalError::PartialRequest(len, err)) => {
+                    assert_eq!(limit, len);
+                    assert_eq!(std::io::ErrorKind::BrokenPipe, err.kind());
+                }
+                res => panic!("Unexpected result: {res:?}"),
+            }
+            let request = crate::protocol::render_request(b"(form)", false);
+            assert_eq!(&request[..limit], written.as_slice());
+        }
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_mock_eof() {
+        use crate::mock::MockStream;
+
+        #[track_caller]
+        fn check_eof(stream: MockStream) {
+            match eval_mock(stream, b"ok").0 {
+                Err(EvalError::Io(err)) => {
+                    assert_eq!(std::io::ErrorKind::UnexpectedEof, err.kind())
+                }
+                res => panic!("Unexpected result: {res:?}"),
+            }
+        }
+
+        // No response, truncated length, missing status and truncated data.
+        check_eof(MockStream::new());
+        check_eof(MockStream::new().chunk(&[9, 0, 0]));
+        check_eof(MockStream::new().chunk(&9u64.to_ne_bytes()));
+        check_eof(
+            MockStream::new().chunk(&9u64.to_ne_bytes()).chunk(b"\x01res"),
+        );
+
+        let stream = MockStream::new().chunk(&0u64.to_ne_bytes());
+        match eval_mock(stream, b"ok").0 {
+            Err(EvalError::NoResponse) => (),
+            res => panic!("Unexpected result: {res:?}"),
+        }
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_mock_huge_length() {
+        // Length over the limit is rejected before anything is allocated.
+        let stream = crate::mock::MockStream::new()
+            .chunk(&(1u64 << 32).to_ne_bytes())
+            .chunk(b"\x01")
+            .chunk(b"data");
+        let mut client = AsyncClient::new(stream);
+        client.pending.sent(false);
+        let mut buf = Vec::new();
+        let res = futures_util::FutureExt::now_or_never(
+            client.read_response_into(&mut buf),
+        );
+        match res.unwrap() {
+            Err(EvalError::ResponseTooLarge(len, Some(prefix))) => {
+                assert_eq!((1u64 << 32) - 1, len);
+                assert_eq!(b"data", prefix.as_slice());
+            }
+            res => panic!("Unexpected result: {res:?}"),
+        }
+        assert_eq!(0, buf.capacity());
+
+        // Length within the limit but larger than the data doesn’t cause
+        // a huge allocation.
+        let stream = crate::mock::MockStream::new()
+            .chunk(&(1u64 << 32).to_ne_bytes())
+            .chunk(b"\x01");
+        let mut client = AsyncClient::new(stream);
+        client.max_response = usize::MAX;
+        client.pending.sent(false);
+        let res = futures_util::FutureExt::now_or_never(
+            client.read_response_into(&mut buf),
+        );
+        match res.unwrap() {
+            Err(EvalError::Io(err)) => {
+                assert_eq!(std::io::ErrorKind::UnexpectedEof, err.kind())
+            }
+            res => panic!("Unexpected result: {res:?}"),
+        }
+        assert!(buf.capacity() <= INITIAL_CAPACITY, "{}", buf.capacity());
+    }
+
+    #[test]
+    fn test_huge_length() {
+        let (client, mut server) = UnixStream::pair().unwrap();
+        let mut client = Client::new(client);
+        server.write_all(&(1u64 << 32).to_ne_bytes()).unwrap();
+        server.write_all(b"\x01data").unwrap();
+        core::mem::drop(server);
+        client.pending.sent(false);
+        match client.read_response() {
+            Err(EvalError::ResponseTooLarge(len, Some(prefix))) => {
+                assert_eq!((1u64 << 32) - 1, len);
+                assert_eq!(b"data", prefix.as_slice());
+            }
+            res => panic!("Unexpected result: {res:?}"),
+        }
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_async_desync() {
+        let mut stream = Vec::new();
+        stream.extend_from_slice(&2u64.to_ne_bytes());
+        stream.extend_from_slice(b"\x07x");
+        let mut client =
+            AsyncClient::new(futures_util::io::Cursor::new(stream));
+        client.pending.sent(false);
+        let res = futures_util::FutureExt::now_or_never(client.read_response());
+        match res.unwrap() {
+            Err(EvalError::Desync) => (),
+            res => panic!("Unexpected result: {res:?}"),
+        }
+
+        let mut stream = Vec::new();
+        stream.extend_from_slice(&u64::MAX.to_ne_bytes());
+        stream.extend_from_slice(b"\x01x");
+        let mut client =
+            AsyncClient::new(futures_util::io::Cursor::new(stream));
+        client.max_response = usize::MAX;
+        client.pending.sent(false);
+        let res = futures_util::FutureExt::now_or_never(client.read_response());
+        match res.unwrap() {
+            Err(EvalError::Desync) => (),
+            res => panic!("Unexpected result: {res:?}"),
+        }
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_async_max_request() {
+        use futures_util::FutureExt;
+
+        let stream = crate::mock::MockStream::new().response(1, b"ok", 100);
+        let mut client = AsyncClient::new(stream);
+        client.max_request = 1;
+        // Nothing is sent for forms over the limit.
+        match client.eval(b"ok", false).now_or_never().unwrap() {
+            Err(EvalError::RequestTooLarge(2)) => (),
+            res => panic!("Unexpected result: {res:?}"),
+        }
+        match client.eval(b"async", true).now_or_never().unwrap() {
+            Err(EvalError::RequestTooLarge(5)) => (),
+            res => panic!("Unexpected result: {res:?}"),
+        }
+        assert_eq!(Vec::<u8>::new(), client.sock.written);
+
+        // The limit is inherited by split halves.
+        client.max_request = 2;
+        let (mut wr, _rd) = client.split();
+        assert_eq!(2, wr.max_request);
+        match wr.send_request(b"big", true).now_or_never().unwrap() {
+            Err(EvalError::RequestTooLarge(3)) => (),
+            res => panic!("Unexpected result: {res:?}"),
+        }
+        // Form at the limit is sent.
+        wr.send_request(b"ok", true).now_or_never().unwrap().unwrap();
+    }
+
+    #[test]
+    fn test_max_request() {
+        let (client, server) = start_test("max-request");
+        let mut client = Client::new(client);
+        client.max_request = 2;
+        assert_eq!(
+            Ok(b"response".to_vec()),
+            client.eval(b"ok", false).unwrap()
+        );
+        // Nothing is sent so the server doesn’t see the requests.
+        client.max_request = 1;
+        match client.eval(b"ok", false) {
+            Err(EvalError::RequestTooLarge(2)) => (),
+            res => panic!("Unexpected result: {res:?}"),
+        }
+        match client.eval(b"async", true) {
+            Err(EvalError::RequestTooLarge(5)) => (),
+            res => panic!("Unexpected result: {res:?}"),
+        }
+        client.max_request = 2;
+        assert_eq!(
+            Ok(b"response".to_vec()),
+            client.eval(b"ok", false).unwrap()
+        );
+        client.sock.shutdown(std::net::Shutdown::Both).unwrap();
+        core::mem::drop(client);
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn test_probe() {
+        // Use a temporary directory rather than the user’s socket directory
+        // which Sawfish may be using.
+        let dir = std::env::temp_dir()
+            .join(format!("sawfish-client-probe-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("socket");
+        let _ = std::fs::remove_file(&path);
+
+        let display = ":4243.7";
+        let probe = || crate::probe_with(display, probe_path(path.clone()));
+        let listener = std::os::unix::net::UnixListener::bind(&path).unwrap();
+        let res = probe();
+        core::mem::drop(listener);
+        // The socket is left behind but nothing listens on it.
+        let stale = probe();
+        std::fs::remove_file(&path).unwrap();
+        let missing = probe();
+        std::fs::remove_dir(&dir).unwrap();
+
+        assert!(res.unwrap());
+        // With X11 support, the display is checked and connecting to it
+        // fails since it doesn’t exist.
+        if cfg!(feature = "experimental-xcb") {
+            assert!(stale.is_err() && missing.is_err());
+        } else {
+            assert!(!stale.unwrap());
+            assert!(!missing.unwrap());
+        }
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_eval_async_alias() {
+        let (client, server) = UnixStream::pair().unwrap();
+        let mut client = crate::Client(crate::Inner::Unix(Client::new(client)));
+        client.eval_async("(quit)").unwrap();
+        core::mem::drop(client);
+        let mut got = Vec::new();
+        (&server).read_to_end(&mut got).unwrap();
+        assert_eq!(protocol::render_request(b"(quit)", true), got);
+    }
+
+    #[test]
+    fn test_send_request_read_response() {
+        let (client, server) = start_test("low-level");
+        let mut client = crate::Client(crate::Inner::Unix(Client::new(client)));
+        client.send_request("ok", false).unwrap();
+        client.send_request("async", true).unwrap();
+        client.send_request("err", false).unwrap();
+        assert_eq!(Ok(b"response".to_vec()), client.read_response().unwrap());
+        assert_eq!(Err(b"response".to_vec()), client.read_response().unwrap());
+
+        core::mem::drop(client);
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn test_age_idle_time() {
+        const DELAY: std::time::Duration = std::time::Duration::from_millis(20);
+
+        let (client, server) = start_test("age");
+        let mut client = crate::Client(crate::Inner::Unix(Client::new(client)));
+        std::thread::sleep(DELAY);
+        client.eval("ok").unwrap().unwrap();
+        let idle = client.idle_time();
+        assert!(client.age() >= DELAY + idle);
+
+        // Idle time grows between evals and is reset by them.
+        std::thread::sleep(DELAY);
+        let grown = client.idle_time();
+        assert!(grown >= idle + DELAY);
+        client.eval("ok").unwrap().unwrap();
+        assert!(client.idle_time() < grown);
+        assert!(client.age() >= 2 * DELAY);
+
+        core::mem::drop(client);
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn test_run() {
+        const DELAY: std::time::Duration = std::time::Duration::from_millis(50);
+
+        let (client, server) = start_test_with("run", |is_async, form| {
+            std::thread::sleep(DELAY);
+            (!is_async).then(|| Err(form.to_vec()))
+        });
+        let mut client = crate::Client(crate::Inner::Unix(Client::new(client)));
+        let start = std::time::Instant::now();
+        let () = client.run("(form)").unwrap();
+        assert!(start.elapsed() >= DELAY);
+        // The response has been read so the connection stays in sync.
+        assert_eq!(Err(b"(next)".to_vec()), client.eval("(next)").unwrap());
+
+        core::mem::drop(client);
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn test_last_was_error() {
+        let (client, server) = start_test("last_was_error");
+        let mut client = crate::Client(crate::Inner::Unix(Client::new(client)));
+        assert_eq!(None, client.last_was_error());
+        client.eval("ok").unwrap().unwrap();
+        assert_eq!(Some(false), client.last_was_error());
+        client.eval("err").unwrap().unwrap_err();
+        assert_eq!(Some(true), client.last_was_error());
+        // Async requests have no response so they don’t change the state.
+        client.send("async").unwrap();
+        assert_eq!(Some(true), client.last_was_error());
+        client.eval("ok").unwrap().unwrap();
+        assert_eq!(Some(false), client.last_was_error());
+
+        core::mem::drop(client);
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn test_io_stats() {
+        let (client, server) = start_test("io_stats");
+        let mut client = crate::Client(crate::Inner::Unix(Client::new(client)));
+        assert_eq!(crate::IoStats::default(), client.io_stats());
+
+        // Both request and response have a 9-byte header.
+        client.eval("ok").unwrap().unwrap();
+        let stats = client.io_stats();
+        assert_eq!(11, stats.bytes_sent);
+        assert_eq!(17, stats.bytes_received);
+
+        // Async requests have no response.
+        client.send("async").unwrap();
+        let stats = client.io_stats();
+        assert_eq!(25, stats.bytes_sent);
+        assert_eq!(17, stats.bytes_received);
+
+        core::mem::drop(client);
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn test_eval_frame() {
+        let (client, server) = start_test("eval_frame");
+        let mut client = crate::Client(crate::Inner::Unix(Client::new(client)));
+
+        // The test server writes length in native byte order.
+        let frame = |status: u8| {
+            [&9u64.to_ne_bytes()[..], &[status], b"response"].concat()
+        };
+        assert_eq!(frame(1), client.eval_frame("ok").unwrap());
+        assert_eq!(frame(0), client.eval_frame("err").unwrap());
+        // The connection stays in sync.
+        assert_eq!(Ok(b"t".to_vec()), client.eval("t").unwrap());
+
+        core::mem::drop(client);
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn test_eval_with_progress() {
+        let (client, server) = start_test("eval_with_progress");
+        let mut client = crate::Client(crate::Inner::Unix(Client::new(client)));
+
+        let mut calls = Vec::new();
+        let res = client
+            .eval_with_progress("big", |read, total| calls.push((read, total)))
+            .unwrap();
+        let total = big_response().len();
+        assert_eq!(Ok(big_response().into_bytes()), res);
+        assert_eq!(total.div_ceil(PROGRESS_CHUNK), calls.len());
+        assert!(calls.iter().all(|&(_, got)| got == total), "{calls:?}");
+        assert!(calls.windows(2).all(|w| w[0].0 < w[1].0), "{calls:?}");
+        assert_eq!(Some(&(total, total)), calls.last());
+
+        // Empty response reports completion once.
+        calls.clear();
+        let res = client
+            .eval_with_progress("empty", |read, total| {
+                calls.push((read, total))
+            })
+            .unwrap();
+        assert_eq!(Err(Vec::new()), res);
+        assert_eq!(vec![(0, 0)], calls);
+
+        core::mem::drop(client);
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn test_batch_send() {
+        use std::io::Read;
+
+        let (client, mut server) = UnixStream::pair().unwrap();
+        let mut client = crate::Client(crate::Inner::Unix(Client::new(client)));
+
+        // Nothing is sent if any of the forms is invalid.
+        let res = client.batch_send(&["t", ""]);
+        assert!(matches!(res, Err(EvalError::EmptyForm)), "{res:?}");
+        client.batch_send(&[] as &[&str]).unwrap();
+
+        let forms = ["(foo)", "(bar 42)", "(baz)"];
+        client.batch_send(&forms).unwrap();
+        let want = forms
+            .iter()
+            .flat_map(|form| {
+                let header = protocol::request_header(
+                    form.len(),
+                    true,
+                    Default::default(),
+                )
+                .unwrap();
+                [&header[..], form.as_bytes()].concat()
+            })
+            .collect::<Vec<u8>>();
+        assert_eq!(
+            u64::try_from(want.len()).unwrap(),
+            client.io_stats().bytes_sent
+        );
+
+        // All requests arrive in a single read since they were written at
+        // once.
+        let mut buf = [0; 256];
+        let len = server.read(&mut buf).unwrap();
+        assert_eq!(want, &buf[..len]);
+    }
+
+    #[test]
+    fn test_default_timeout() {
+        let (client, server) = start_test("default_timeout");
+        let client = crate::Client(crate::Inner::Unix(Client::new(client)));
+        let timeout = crate::parse_timeout(Some("2000"));
+        let mut client = client.with_default_timeout(timeout);
+        let crate::Inner::Unix(inner) = &client.0 else { unreachable!() };
+        assert_eq!(timeout, inner.sock.read_timeout().unwrap());
+        assert_eq!(timeout, inner.sock.write_timeout().unwrap());
+
+        // Explicit calls override the default.
+        let explicit = Some(std::time::Duration::from_secs(3));
+        client.set_read_timeout(explicit).unwrap();
+        client.set_write_timeout(None).unwrap();
+        let crate::Inner::Unix(inner) = &client.0 else { unreachable!() };
+        assert_eq!(explicit, inner.sock.read_timeout().unwrap());
+        assert_eq!(None, inner.sock.write_timeout().unwrap());
+
+        // Without the variable, timeouts aren’t changed.
+        let client = client.with_default_timeout(crate::parse_timeout(None));
+        let crate::Inner::Unix(inner) = &client.0 else { unreachable!() };
+        assert_eq!(explicit, inner.sock.read_timeout().unwrap());
+
+        core::mem::drop(client);
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn test_eval_deadline() {
+        let (client, server) = start_test_with("eval_deadline", |_, form| {
+            if form == b"slow" {
+                std::thread::sleep(std::time::Duration::from_millis(200));
+            }
+            Some(Ok(form.to_vec()))
+        });
+        let mut client = crate::Client(crate::Inner::Unix(Client::new(client)));
+        let prev = std::time::Duration::from_secs(5);
+        client.set_read_timeout(Some(prev)).unwrap();
+        let now = std::time::Instant::now;
+        let short = std::time::Duration::from_millis(50);
+
+        let res = client.eval_deadline("fast", now() + prev).unwrap();
+        assert_eq!(Ok(b"fast".to_vec()), res);
+
+        let start = now();
+        match client.eval_deadline("slow", start + short) {
+            Err(EvalError::Timeout) => (),
+            res => panic!("Unexpected result: {res:?}"),
+        }
+        assert!(start.elapsed() >= short);
+        assert!(start.elapsed() < prev);
+
+        // Deadline in the past fails without sending anything.
+        match client.eval_deadline("never", start) {
+            Err(EvalError::Timeout) => (),
+            res => panic!("Unexpected result: {res:?}"),
+        }
+
+        // The previous timeout is restored.
+        let crate::Inner::Unix(inner) = &client.0 else { unreachable!() };
+        assert_eq!(Some(prev), inner.sock.read_timeout().unwrap());
+
+        // The late response can still be drained.
+        let res = client.drain_pending().unwrap();
+        assert_eq!(Some(Ok(b"slow".to_vec())), res);
+
+        core::mem::drop(client);
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn test_eval_opt() {
+        let (client, server) = start_test("eval_opt");
+        let mut client = crate::Client(crate::Inner::Unix(Client::new(client)));
+        let res = client.eval_opt("ok", true).unwrap();
+        assert_eq!(Some(Ok(b"response".to_vec())), res);
+        let res = client.eval_opt("err", true).unwrap();
+        assert_eq!(Some(Err(b"response".to_vec())), res);
+        assert_eq!(None, client.eval_opt("async", false).unwrap());
+        // The connection stays in sync.
+        assert_eq!(Ok(b"t".to_vec()), client.eval("t").unwrap());
+
+        core::mem::drop(client);
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn test_drain_pending() {
+        // Server which responds to async requests as well.
+        let (client, server) = start_test_with("drain", |is_async, form| {
+            Some(if is_async { Ok(form.to_vec()) } else { Ok(b"ok".to_vec()) })
+        });
+        let mut client = crate::Client(crate::Inner::Unix(Client::new(client)));
+        client
+            .set_read_timeout(Some(std::time::Duration::from_millis(100)))
+            .unwrap();
+
+        client.send("(effect)").unwrap();
+        assert_eq!(
+            Some(Ok(b"(effect)".to_vec())),
+            client.drain_pending().unwrap()
+        );
+        assert_eq!(None, client.drain_pending().unwrap());
+        // The connection is back in sync.
+        assert_eq!(Ok(b"ok".to_vec()), client.eval("(form)").unwrap());
+
+        core::mem::drop(client);
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn test_empty_form() {
+        let (client, server) = UnixStream::pair().unwrap();
+        let mut client = crate::Client(crate::Inner::Unix(Client::new(client)));
+        match client.eval("") {
+            Err(EvalError::EmptyForm) => (),
+            res => panic!("Unexpected result: {res:?}"),
+        }
+        match client.send(b"") {
+            Err(EvalError::EmptyForm) => (),
+            res => panic!("Unexpected result: {res:?}"),
+        }
+        // Nothing is sent to the server.
+        core::mem::drop(client);
+        let mut buf = Vec::new();
+        (&server).read_to_end(&mut buf).unwrap();
+        assert_eq!(b"", buf.as_slice());
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "requests and responses are out of sync")]
+    fn test_read_response_without_request() {
+        let (client, _server) = UnixStream::pair().unwrap();
+        let mut client = Client::new(client);
+        client.send_request(b"async", true).unwrap();
+        let _ = client.read_response();
+    }
+
+    #[test]
+    fn test_server_info() {
+        let (client, server) = start_test("server-info");
+        let mut client = crate::Client(crate::Inner::Unix(Client::new(client)));
+        let info = client.server_info();
+        assert_eq!(Some("1.13.0"), info.version.as_deref());
+        core::mem::drop(client);
+        server.join().unwrap();
+
+        // Failure to query the version results in None.
+        let (client, server) = UnixStream::pair().unwrap();
+        core::mem::drop(server);
+        let mut client = crate::Client(crate::Inner::Unix(Client::new(client)));
+        assert_eq!(crate::ServerInfo::default(), client.server_info());
+    }
+
+    #[test]
+    fn test_set_screen_viewport() {
+        use crate::commands::{ViewportX, ViewportY};
+
+        let (client, server) = start_test_with("viewport", |_, form| {
+            let form = core::str::from_utf8(form).unwrap();
+            Some(if form.contains(" 99 ") {
+                Err(b"(bad-arg 99)".to_vec())
+            } else {
+                Ok(b"t".to_vec())
+            })
+        });
+        let mut client = crate::Client(crate::Inner::Unix(Client::new(client)));
+        assert_eq!(
+            Ok(b"t".to_vec()),
+            client.set_screen_viewport(ViewportX(1), ViewportY(0)).unwrap()
+        );
+        // Errors reported by Sawfish aren’t hidden.
+        assert_eq!(
+            Err(b"(bad-arg 99)".to_vec()),
+            client.set_screen_viewport(ViewportX(99), ViewportY(0)).unwrap()
+        );
+        core::mem::drop(client);
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn test_ping() {
+        let (client, server) = start_test("ping");
+        let mut client = crate::Client(crate::Inner::Unix(Client::new(client)));
+        client.ping().unwrap();
+        core::mem::drop(client);
+        server.join().unwrap();
+
+        assert!(crate::check_ping(Ok(Ok(b"t".to_vec()))).is_ok());
+        for res in [Ok(b"nil".to_vec()), Err(b"t".to_vec())] {
+            match crate::check_ping(Ok(res.clone())) {
+                Err(EvalError::UnexpectedResponse(got)) => assert_eq!(res, got),
+                res => panic!("Unexpected result: {res:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_read_timeout() {
+        let (client, server) = start_test("timeout");
+        let mut client = crate::Client(crate::Inner::Unix(Client::new(client)));
+        let timeout = std::time::Duration::from_millis(10);
+        client.set_read_timeout(Some(timeout)).unwrap();
+        client.set_write_timeout(Some(timeout)).unwrap();
+        match client.eval("hang") {
+            Err(EvalError::Io(err)) => assert!(
+                matches!(
+                    err.kind(),
+                    std::io::ErrorKind::WouldBlock |
+                        std::io::ErrorKind::TimedOut
+                ),
+                "{err:?}"
+            ),
+            res => panic!("Unexpected result: {res:?}"),
+        }
+        core::mem::drop(client);
+        server.join().unwrap();
+    }
+
+    #[cfg(feature = "async")]
+    #[track_caller]
+    fn do_async_test(want: Result<&str, &str>, form: &str, is_async: bool) {
+        use tokio_util::compat::TokioAsyncReadCompatExt;
+
+        let (client, server) = start_test(form);
+        client.set_nonblocking(true).unwrap();
+
+        let got = {
+            let rt = tokio::runtime::Builder::new_current_thread()
+                .enable_io()
+                .build()
+                .unwrap();
+            let _guerd = rt.enter();
+
+            let client = tokio::net::UnixStream::from_std(client).unwrap();
+            let mut client = AsyncClient::new(client.compat());
+            rt.block_on(async {
+                let got = client.eval(form.as_bytes(), is_async).await;
+                client
+                    .sock
+                    .into_inner()
+                    .into_std()
+                    .unwrap()
+                    .shutdown(std::net::Shutdown::Both)
+                    .unwrap();
+                got
+            })
+        };
+        server.join().unwrap();
+
+        let got = got
+            .unwrap()
+            .map(|bytes| String::from_utf8(bytes).unwrap())
+            .map_err(|bytes| String::from_utf8(bytes).unwrap());
+        assert_eq!(want, got.as_deref().map_err(String::as_str));
+    }
+
+    #[cfg(feature = "blocking")]
+    #[test]
+    fn test_blocking_eval() {
+        use tokio_util::compat::TokioAsyncReadCompatExt;
+
+        let (client, server) = start_test("blocking");
+        client.set_nonblocking(true).unwrap();
+
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_io()
+            .build()
+            .unwrap();
+        let client = {
+            let _guard = rt.enter();
+            tokio::net::UnixStream::from_std(client).unwrap()
+        };
+        let client = crate::AsyncClient(AsyncClient::new(client.compat()));
+        let mut client = crate::BlockingClient { rt, client };
+
+        let got = client.eval("ok").unwrap();
+        assert_eq!(Ok(b"response".to_vec()), got);
+        let got = client.eval("err").unwrap();
+        assert_eq!(Err(b"response".to_vec()), got);
+        client.send("async").unwrap();
+        core::mem::drop(client);
+        server.join().unwrap();
+    }
+
+    #[cfg(feature = "smol")]
+    #[test]
+    fn test_smol_eval() {
+        let (client, server) = start_test("smol");
+        let client = smol::Async::new(client).unwrap();
+        let mut client: crate::SmolClient = crate::AsyncClient::new(client);
+        let got = smol::block_on(async {
+            [
+                client.eval("ok").await.unwrap(),
+                client.eval("err").await.unwrap(),
+            ]
+        });
+        core::mem::drop(client);
+        server.join().unwrap();
+
+        let ok = Ok(b"response".to_vec());
+        let err = Err(b"response".to_vec());
+        assert_eq!([ok, err], got);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn test_tokio_connect_timeout() {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let path = std::path::PathBuf::from("/nonexistent/sawfish/socket");
+        let timeout = Some(std::time::Duration::from_millis(10));
+        match rt.block_on(AsyncClient::connect(path.clone(), timeout)) {
+            Err(ConnError::Io(got, err)) => {
+                assert_eq!(path, got);
+                assert_eq!(std::io::ErrorKind::NotFound, err.kind());
+            }
+            Err(err) => panic!("Unexpected error: {err}"),
+            Ok(_) => panic!("Unexpectedly connected"),
+        }
+
+        // Connecting to a Unix socket fails immediately rather than stalls
+        // when the listener’s backlog is full so check the timeout with
+        // a future which never completes.
+        let fut = core::future::pending::<std::io::Result<()>>();
+        let err = rt.block_on(with_timeout(timeout, fut)).unwrap_err();
+        assert_eq!(std::io::ErrorKind::TimedOut, err.kind());
+        let fut = core::future::ready(Ok(42));
+        assert_eq!(42, rt.block_on(with_timeout(timeout, fut)).unwrap());
+        let fut = core::future::ready(Ok(42));
+        assert_eq!(42, rt.block_on(with_timeout(None, fut)).unwrap());
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn test_tokio_connect_wait() {
+        const DELAY: std::time::Duration = std::time::Duration::from_millis(50);
+
+        let dir = std::env::temp_dir()
+            .join(format!("sawfish-client-wait-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("socket");
+        let _ = std::fs::remove_file(&path);
+
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+
+        // No server; waiting times out.
+        match rt.block_on(AsyncClient::connect_wait(path.clone(), DELAY)) {
+            Err(ConnError::Io(got, err)) => {
+                assert_eq!(path, got);
+                assert_eq!(std::io::ErrorKind::TimedOut, err.kind());
+            }
+            Err(err) => panic!("Unexpected error: {err}"),
+            Ok(_) => panic!("Unexpectedly connected"),
+        }
+
+        // Server creates the socket while client is waiting.
+        let server = std::thread::spawn({
+            let path = path.clone();
+            move || {
+                std::thread::sleep(DELAY * 2);
+                let listener =
+                    std::os::unix::net::UnixListener::bind(&path).unwrap();
+                listener.accept().unwrap();
+            }
+        });
+        let timeout = std::time::Duration::from_secs(10);
+        let res = rt.block_on(AsyncClient::connect_wait(path.clone(), timeout));
+        assert!(res.is_ok(), "{:?}", res.err());
+        server.join().unwrap();
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_async_split() {
+        use tokio_util::compat::TokioAsyncReadCompatExt;
+
+        let (client, server) = start_test("split");
+        client.set_nonblocking(true).unwrap();
+
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_io()
+            .build()
+            .unwrap();
+        let client = {
+            let _guard = rt.enter();
+            tokio::net::UnixStream::from_std(client).unwrap()
+        };
+        let client = crate::AsyncClient(AsyncClient::new(client.compat()));
+        let (mut tx, mut rx) = client.split();
+
+        let forms =
+            [("ok", false), ("async", true), ("err", false), ("ok", false)];
+        let got = rt.block_on(async {
+            for (form, is_async) in forms {
+                tx.send_request(form, is_async).await.unwrap();
+            }
+            let mut got = Vec::new();
+            for _ in 0..3 {
+                got.push(rx.read_response().await.unwrap());
+            }
+            got
+        });
+        core::mem::drop((tx, rx));
+        server.join().unwrap();
+
+        let ok = Ok(b"response".to_vec());
+        let err = Err(b"response".to_vec());
+        assert_eq!([ok.clone(), err, ok], got.as_slice());
+    }
+
+    #[cfg(all(feature = "async", debug_assertions))]
+    #[test]
+    #[should_panic(expected = "requests and responses are out of sync")]
+    fn test_async_read_response_without_request() {
+        let (client, _server) = UnixStream::pair().unwrap();
+        let client =
+            AsyncClient::new(futures_util::io::AllowStdIo::new(client));
+        let (mut tx, mut rx) = client.split();
+        futures_util::FutureExt::now_or_never(async {
+            tx.send_request(b"async", true).await.unwrap();
+            let _ = rx.read_response().await;
+        });
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_async_eval_into() {
+        use tokio_util::compat::TokioAsyncReadCompatExt;
+
+        let (client, server) = start_test("eval-into");
+        client.set_nonblocking(true).unwrap();
+
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_io()
+            .build()
+            .unwrap();
+        let client = {
+            let _guard = rt.enter();
+            tokio::net::UnixStream::from_std(client).unwrap()
+        };
+        let mut client = crate::AsyncClient::new(client.compat());
+
+        let mut buf = Vec::new();
+        let capacities = rt.block_on(async {
+            let mut capacities = Vec::new();
+            for (form, want) in [("ok", true), ("err", false), ("ok", true)] {
+                let got = client.eval_into(form, &mut buf).await.unwrap();
+                assert_eq!((want, &b"response"[..]), (got, buf.as_slice()));
+                capacities.push(buf.capacity());
+            }
+            capacities
+        });
+        core::mem::drop(client);
+        server.join().unwrap();
+
+        assert_eq!(capacities[1], capacities[2]);
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_async_ping() {
+        use tokio_util::compat::TokioAsyncReadCompatExt;
+
+        let (client, server) = start_test("async-ping");
+        client.set_nonblocking(true).unwrap();
+
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_io()
+            .build()
+            .unwrap();
+        let client = {
+            let _guard = rt.enter();
+            tokio::net::UnixStream::from_std(client).unwrap()
+        };
+        let mut client = crate::AsyncClient::new(client.compat());
+        rt.block_on(client.ping()).unwrap();
+        core::mem::drop(client);
+        server.join().unwrap();
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_async_disconnected() {
+        let (client, server) = UnixStream::pair().unwrap();
+        core::mem::drop(server);
+        let mut client =
+            AsyncClient::new(futures_util::io::AllowStdIo::new(client));
+        let res =
+            futures_util::FutureExt::now_or_never(client.eval(b"ok", false));
+        match res.unwrap() {
+            Err(EvalError::Disconnected) => (),
+            res => panic!("Unexpected result: {res:?}"),
+        }
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_async_eval_ok() { do_async_test(Ok("response"), "ok", false); }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_async_eval_err() { do_async_test(Err("response"), "err", false); }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_async_eval_big() {
+        do_async_test(Ok(&big_response()), "big", false);
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_async_send() { do_async_test(Ok(""), "async", true); }
+}
+
+
+
+/// Name of environment variable overriding the system name.
+const SYSTEM_NAME_ENV: &str = "SAWFISH_HOSTNAME";
+
+/// System's canonical hostname.
+static SYSTEM_NAME: std::sync::OnceLock<Option<String>> =
+    std::sync::OnceLock::new();
+
+/// Returns canonical system name determining it on first use.
+fn system_name() -> Option<&'static str> {
+    SYSTEM_NAME.get_or_init(get_system_name).as_deref()
+}
+
+/// Sets the system name unless it has already been determined; see
+/// [`crate::set_system_name`].
+pub fn set_system_name(name: &str) -> bool {
+    set_system_name_in(&SYSTEM_NAME, name)
+}
+
+/// Sets the system name stored in `cell`; see [`set_system_name`].  Empty
+/// name is rejected like it’s ignored when read from [`SYSTEM_NAME_ENV`].
+fn set_system_name_in(
+    cell: &std::sync::OnceLock<Option<String>>,
+    name: &str,
+) -> bool {
+    !name.is_empty() && cell.set(Some(name.to_lowercase())).is_ok()
+}
+
+/// Returns canonical system name, i.e. a fully-qualified hostname of the host.
+///
+/// If [`SYSTEM_NAME_ENV`] environment variable is set and non-empty, its
+/// value is used instead of looking up the name.
+fn get_system_name() -> Option<String> {
+    if let Some(name) =
+        system_name_from_env(std::env::var(SYSTEM_NAME_ENV).ok())
+    {
+        Some(name)
+    } else if cfg!(test) {
+        Some("host.local".into())
+    } else {
+        let host = hostname()?;
+        if !host.contains('.') &&
+            let Some(host) = canonical_host_impl(&host)
+        {
+            return Some(host);
+        }
+        Some(host)
+    }
+}
+
+/// Parses value of [`SYSTEM_NAME_ENV`] environment variable.  Returns `None`
+/// if the variable is unset or empty.
+fn system_name_from_env(value: Option<String>) -> Option<String> {
+    value.filter(|name| !name.is_empty()).map(|name| name.to_lowercase())
+}
+
+/// Returns hostname of the system.
+#[cfg(feature = "dns")]
+fn hostname() -> Option<String> { dns_lookup::get_hostname().ok() }
+
+/// Returns hostname of the system.
+#[cfg(not(feature = "dns"))]
+fn hostname() -> Option<String> {
+    nix::unistd::gethostname().ok()?.into_string().ok()
+}
+
+/// Returns the canonical, fully-qualified, lowercase version of the hostname.
+fn canonical_host(host: &str) -> String {
+    canonical_host_impl(host).as_deref().unwrap_or(host).to_lowercase()
+}
+
+/// Returns canonical name of the host as reported by DNS.
+///
+/// Without `dns` feature, no look up is performed and this always returns
+/// `None`.
+fn canonical_host_impl(host: &str) -> Option<String> {
+    if cfg!(test) {
+        Some(if host == "nofq" {
+            host.into()
+        } else if host.contains('.') {
+            host.to_lowercase()
+        } else {
             host.to_lowercase() + ".local"
         })
     } else {
-        let hints = dns_lookup::AddrInfoHints {
-            flags: libc::AI_CANONNAME,
-            address: 0,
-            socktype: 0,
-            protocol: 0,
-        };
-        let iter = dns_lookup::getaddrinfo(Some(host), None, Some(hints));
-        if let Ok(iter) = iter {
-            for info in iter {
-                if let Some(name) = info.ok().and_then(|info| info.canonname) &&
-                    name.contains('.')
-                {
-                    return Some(name);
-                }
-            }
+        #[cfg(feature = "dns")]
+        return canonical_host_dns(host);
+        #[cfg(not(feature = "dns"))]
+        return None;
+    }
+}
+
+/// Looks up canonical name of the host.
+#[cfg(feature = "dns")]
+fn canonical_host_dns(host: &str) -> Option<String> {
+    let hints = dns_lookup::AddrInfoHints {
+        flags: libc::AI_CANONNAME,
+        address: 0,
+        socktype: 0,
+        protocol: 0,
+    };
+    let iter = dns_lookup::getaddrinfo(Some(host), None, Some(hints)).ok()?;
+    for info in iter {
+        if let Some(name) = info.ok().and_then(|info| info.canonname) &&
+            name.contains('.')
+        {
+            return Some(name);
         }
-        None
     }
+    None
+}
+
+#[test]
+fn test_hostname() {
+    let host = hostname().unwrap();
+    assert!(!host.is_empty());
+    // Both implementations must agree.
+    let want = nix::unistd::gethostname().unwrap();
+    assert_eq!(want.to_str(), Some(host.as_str()));
+}
+
+/// Returns whether the host refers to the local system.
+///
+/// Sawfish names its socket after the canonical system name no matter how the
+/// display was specified.  `localhost` on the other hand often doesn’t resolve
+/// to a fully-qualified name, so canonicalising it would produce a socket name
+/// which doesn’t match the one Sawfish uses.  Instead, `localhost`,
+/// `localhost.localdomain` and loopback addresses are treated the same way as
+/// an empty host.
+///
+/// The system name itself (or its short form) is treated the same way which
+/// avoids a DNS round trip and guarantees the result matches the system name
+/// even if DNS returns a different canonical form.
+fn is_local_host(host: &str, system_name: Option<&str>) -> bool {
+    host.is_empty() ||
+        host.eq_ignore_ascii_case("localhost") ||
+        host.eq_ignore_ascii_case("localhost.localdomain") ||
+        host.parse::<std::net::IpAddr>().is_ok_and(|addr| addr.is_loopback()) ||
+        system_name.is_some_and(|name| is_system_name(host, name))
+}
+
+/// Returns whether the host is the system name or its short form.
+fn is_system_name(host: &str, system_name: &str) -> bool {
+    let short = system_name.split_once('.').map_or(system_name, |(s, _)| s);
+    host.eq_ignore_ascii_case(system_name) || host.eq_ignore_ascii_case(short)
 }
 
 /// Returns the canonical display string (e.g. `":0"` → `"example.com:0.0"`).
-fn canonical_display(mut name: &str) -> String {
-    if name.starts_with("unix:") {
-        name = &name[4..];
-    }
+///
+/// `unix:` and `unix/` prefixes which explicitly request a local connection
+/// are stripped and the display is treated as local one.
+pub fn canonical_display(name: &str) -> String {
+    canonical_display_with(name, system_name())
+}
+
+/// Returns the canonical display string assuming given system name; see
+/// [`canonical_display`].
+fn canonical_display_with(name: &str, system_name: Option<&str>) -> String {
+    let name = strip_unix_prefix(name);
     let (host, rest) = name.split_once(':').unwrap_or((name, "0"));
-    let host = if host.is_empty() {
-        SYSTEM_NAME.as_deref().map(Cow::Borrowed)
+    let host = if is_local_host(host, system_name) {
+        system_name.map(Cow::Borrowed)
     } else {
         Some(Cow::Owned(canonical_host(host)))
     };
@@ -386,6 +2746,22 @@ fn canonical_display(mut name: &str) -> String {
     format!("{host}:{display}.{screen}")
 }
 
+/// Strips the `unix` protocol prefix from a display name.
+///
+/// Recognises the `unix:<display>` form where `unix` is used as host name as
+/// well as `unix/<host>:<display>` form where it’s a protocol name.
+fn strip_unix_prefix(name: &str) -> &str {
+    if let Some(rest) = name.strip_prefix("unix/") {
+        rest
+    } else if let Some(rest) =
+        name.strip_prefix("unix").filter(|rest| rest.starts_with(':'))
+    {
+        rest
+    } else {
+        name
+    }
+}
+
 #[test]
 fn test_canonical_dispaly() {
     for (display, canonical) in [
@@ -396,7 +2772,175 @@ fn test_canonical_dispaly() {
         ("host.example.com:0", "host.example.com:0.0"),
         ("nofq:0", "nofq:0.0"),
         ("bogus", "bogus.local:0.0"),
+        ("localhost:0", "host.local:0.0"),
+        ("LocalHost:0.1", "host.local:0.1"),
+        ("localhost.localdomain:0", "host.local:0.0"),
+        ("127.0.0.1:0", "host.local:0.0"),
+        ("127.1.2.3:0", "host.local:0.0"),
+        ("10.0.0.1:0", "10.0.0.1:0.0"),
+        ("host.local:0", "host.local:0.0"),
+        ("HOST.Local:1", "host.local:1.0"),
+        ("unix:0", "host.local:0.0"),
+        ("unix:1.2", "host.local:1.2"),
+        ("unix/:0", "host.local:0.0"),
+        ("unix/:1.2", "host.local:1.2"),
+        ("unix/localhost:1", "host.local:1.0"),
+        ("unixbox:0", "unixbox.local:0.0"),
     ] {
         assert_eq!(canonical, canonical_display(display), "{display}");
+        if !display.is_empty() {
+            let resolved = crate::resolve_display(Some(display)).unwrap();
+            assert_eq!(canonical, resolved, "{display}");
+            let name = crate::socket_file_name(Some(display)).unwrap();
+            assert_eq!(name, canonical, "{display}");
+        }
+    }
+}
+
+#[cfg(feature = "tracing")]
+#[test]
+fn test_connect_tracing() {
+    use std::sync::{Arc, Mutex};
+
+    use tracing::field::{Field, Visit};
+    use tracing::span;
+
+    /// Subscriber which records fields of all events as strings.
+    #[derive(Clone, Default)]
+    struct Recorder(Arc<Mutex<Vec<String>>>);
+
+    /// Visitor which formats fields as ` name=value`.
+    struct Line(String);
+
+    impl Visit for Line {
+        fn record_debug(
+            &mut self,
+            field: &Field,
+            value: &dyn core::fmt::Debug,
+        ) {
+            use core::fmt::Write;
+            write!(self.0, " {}={:?}", field.name(), value).unwrap();
+        }
+    }
+
+    impl tracing::Subscriber for Recorder {
+        fn enabled(&self, _: &tracing::Metadata<'_>) -> bool { true }
+        fn new_span(&self, _: &span::Attributes<'_>) -> span::Id {
+            span::Id::from_u64(1)
+        }
+        fn record(&self, _: &span::Id, _: &span::Record<'_>) {}
+        fn record_follows_from(&self, _: &span::Id, _: &span::Id) {}
+        fn event(&self, event: &tracing::Event<'_>) {
+            let mut line = Line(String::new());
+            event.record(&mut line);
+            self.0.lock().unwrap().push(line.0);
+        }
+        fn enter(&self, _: &span::Id) {}
+        fn exit(&self, _: &span::Id) {}
+    }
+
+    let recorder = Recorder::default();
+    let path = std::path::PathBuf::from("/nonexistent/sawfish/socket");
+    let res = tracing::subscriber::with_default(recorder.clone(), || {
+        Client::connect(path.clone(), "host.local:0.0".into())
+    });
+    assert!(matches!(res, Err(ConnError::Io(..))));
+
+    let events = recorder.0.lock().unwrap();
+    assert_eq!(
+        [
+            " message=connecting to Sawfish display=\"host.local:0.0\" \
+             path=/nonexistent/sawfish/socket",
+            " message=connecting to Sawfish failed \
+             path=/nonexistent/sawfish/socket kind=entity not found",
+        ],
+        events.as_slice()
+    );
+}
+
+#[test]
+fn test_is_system_name() {
+    assert!(is_system_name("darkstar.example.net", "darkstar.example.net"));
+    assert!(is_system_name("DarkStar.Example.Net", "darkstar.example.net"));
+    assert!(is_system_name("darkstar", "darkstar.example.net"));
+    assert!(is_system_name("DARKSTAR", "darkstar.example.net"));
+    assert!(is_system_name("darkstar", "darkstar"));
+    assert!(!is_system_name("darkstar.example", "darkstar.example.net"));
+    assert!(!is_system_name("example.net", "darkstar.example.net"));
+    assert!(!is_system_name("", "darkstar.example.net"));
+
+    // Hosts matching the system name are local and skip the DNS lookup.
+    let name = Some("host.local");
+    assert!(is_local_host("host.local", name));
+    assert!(is_local_host("Host", name));
+    assert!(!is_local_host("hostess", name));
+}
+
+#[test]
+fn test_system_name_override() {
+    // The overridden name is used for local displays and is recognised as
+    // local host.
+    let name = Some("container.example");
+    assert_eq!("container.example:0.0", canonical_display_with(":0", name));
+    assert_eq!(
+        "container.example:1.0",
+        canonical_display_with("container:1", name)
+    );
+    assert_eq!("host.local:0.0", canonical_display_with("host:0", name));
+
+    if std::env::var_os("LOGNAME").is_some() {
+        let want = server_path(":0").unwrap();
+        let got = server_path_with(":0", name).unwrap();
+        assert_ne!(want, got);
+        assert!(got.ends_with("container.example:0.0"), "{}", got.display());
+    }
+}
+
+#[test]
+fn test_system_name_from_env() {
+    assert_eq!(None, system_name_from_env(None));
+    assert_eq!(None, system_name_from_env(Some(String::new())));
+    assert_eq!(
+        Some("container.example"),
+        system_name_from_env(Some("Container.Example".into())).as_deref()
+    );
+}
+
+#[test]
+fn test_set_system_name() {
+    let cell = std::sync::OnceLock::new();
+    // Empty name is rejected and doesn’t set the name.
+    assert!(!set_system_name_in(&cell, ""));
+    assert!(cell.get().is_none());
+    assert!(set_system_name_in(&cell, "Darkstar.Example"));
+    assert_eq!(Some("darkstar.example"), cell.get().unwrap().as_deref());
+    // Once set, the name can’t be changed.
+    assert!(!set_system_name_in(&cell, "other.example"));
+    assert_eq!(Some("darkstar.example"), cell.get().unwrap().as_deref());
+
+    // The global name is determined on first use and fixed afterwards.
+    let name = system_name();
+    assert!(!set_system_name("other.example"));
+    assert!(!set_system_name(""));
+    assert_eq!(name, system_name());
+}
+
+#[test]
+fn test_socket_file_name() {
+    let name = crate::socket_file_name(Some("localhost:1")).unwrap();
+    assert_eq!(name, "host.local:1.0");
+    // The name matches the last component of the socket path.  Determining
+    // the path requires LOGNAME to be set.
+    if let Ok(path) = server_path_with("localhost:1", Some("host.local")) {
+        assert_eq!(Some(name.as_os_str()), path.file_name());
     }
+    assert!(matches!(
+        crate::socket_file_name(Some("")),
+        Err(ConnError::NoDisplay)
+    ));
+}
+
+#[test]
+fn test_canonical_display_localhost() {
+    assert_eq!(canonical_display(":0"), canonical_display("localhost:0"));
 }
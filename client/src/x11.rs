@@ -8,11 +8,37 @@ use crate::{ConnError, EvalError, EvalResponse};
 
 const PROTOCOL_X11_VERSION: u32 = 1;
 
+/// Name of the root window property holding Sawfish’s request window.
+pub const REQUEST_WIN_ATOM: &str = "_SAWFISH_REQUEST_WIN";
+
+/// Name of the property used to pass forms and responses.
+pub const REQUEST_ATOM: &str = "_SAWFISH_REQUEST";
+
 pub struct Client {
     conn: xcb::Connection,
+    /// Canonical name of the display the client is connected to.
+    display: String,
     req_win: x::Window,
     portal: x::Window,
     property: x::Atom,
+    /// The `UTF8_STRING` atom or `ATOM_NONE` if the server doesn’t know it.
+    utf8_string: x::Atom,
+    /// Maximum length of response data.  Reading longer responses fails with
+    /// [`EvalError::ResponseTooLarge`].
+    max_response: usize,
+    /// Maximum length of a form.  Sending longer forms fails with
+    /// [`EvalError::RequestTooLarge`].
+    max_request: usize,
+    /// Maximum time to wait for a response.
+    read_timeout: Option<std::time::Duration>,
+    /// Time the client was created.
+    created_at: std::time::Instant,
+    /// Time the last request was sent.
+    last_used: std::time::Instant,
+    /// Whether the last response read was an error.
+    last_was_error: Option<bool>,
+    /// Number of bytes sent and received.
+    io_stats: crate::IoStats,
 }
 
 impl Client {
@@ -22,59 +48,186 @@ impl Client {
     /// the crate is built without XCB support, a fallback implementation of
     /// this function returns the error.  This eliminates conditional
     /// compilation from the caller.
-    pub fn fallback(display: &str, _err: ConnError) -> Result<Self, ConnError> {
-        Self::open(display)
+    ///
+    /// `err` is the error from connecting through the Unix socket.  If opening
+    /// X11 connection fails, the errors are combined as described in
+    /// [`combine_errors`].  `timeout` bounds the handshake; see
+    /// [`Self::open`].
+    pub fn fallback(
+        display: &str,
+        err: ConnError,
+        timeout: Option<std::time::Duration>,
+    ) -> Result<Self, ConnError> {
+        Self::open(display, timeout)
+            .map_err(|x11_err| combine_errors(err, x11_err))
+    }
+
+    /// Checks whether Sawfish is running on the display; see
+    /// [`is_sawfish_running`].
+    ///
+    /// Like [`Self::fallback`], this is used after connecting through the Unix
+    /// socket failed with `err`.  If checking fails, the errors are combined.
+    pub fn probe(display: &str, err: ConnError) -> Result<bool, ConnError> {
+        is_sawfish_running(display)
+            .map_err(|x11_err| combine_errors(err, x11_err))
     }
 
     /// Opens connection to Sawfish through X11 property protocol.
-    pub fn open(display: &str) -> Result<Self, ConnError> {
-        let (conn, screen) = xcb::Connection::connect(Some(display))?;
-        let setup = conn.get_setup();
+    ///
+    /// If `timeout` is given, the handshake with the server (interning atoms,
+    /// reading the request window property and creating the portal window)
+    /// fails with an I/O error whose kind is `TimedOut` if it doesn’t complete
+    /// in time.  Note that establishing the X11 connection itself is done by
+    /// libxcb and isn’t bounded by the timeout.
+    pub fn open(
+        display: &str,
+        timeout: Option<std::time::Duration>,
+    ) -> Result<Self, ConnError> {
+        let deadline =
+            timeout.map(|timeout| std::time::Instant::now() + timeout);
+        let conn = xcb::Connection::connect(Some(display))?;
+        Self::with_display_connection(
+            display,
+            conn,
+            REQUEST_WIN_ATOM,
+            REQUEST_ATOM,
+            deadline,
+        )
+    }
+
+    /// Opens connection to Sawfish through X11 property protocol using
+    /// custom atom names.
+    ///
+    /// `req_win_atom` is the name of the root window property holding the
+    /// server’s request window.  `request_atom` is the name of the property
+    /// used to pass forms and responses.  Sawfish uses [`REQUEST_WIN_ATOM`] and
+    /// [`REQUEST_ATOM`] respectively.  `timeout` bounds the handshake; see
+    /// [`Self::open`].
+    pub fn open_with_atoms(
+        display: &str,
+        req_win_atom: &str,
+        request_atom: &str,
+        timeout: Option<std::time::Duration>,
+    ) -> Result<Self, ConnError> {
+        let deadline =
+            timeout.map(|timeout| std::time::Instant::now() + timeout);
+        let conn = xcb::Connection::connect(Some(display))?;
+        Self::with_display_connection(
+            display,
+            conn,
+            req_win_atom,
+            request_atom,
+            deadline,
+        )
+    }
+
+    /// Opens connection to Sawfish through X11 property protocol using
+    /// explicit authorisation information.
+    ///
+    /// This is useful with forwarded or remote displays whose authorisation
+    /// cookie isn’t the one X libraries would pick by default.  `timeout`
+    /// bounds the handshake; see [`Self::open`].
+    pub fn open_with_auth(
+        display: &str,
+        auth: xcb::AuthInfo<'_>,
+        timeout: Option<std::time::Duration>,
+    ) -> Result<Self, ConnError> {
+        let deadline =
+            timeout.map(|timeout| std::time::Instant::now() + timeout);
+        let conn = xcb::Connection::connect_to_display_with_auth_info(
+            Some(display),
+            auth,
+        )?;
+        Self::with_display_connection(
+            display,
+            conn,
+            REQUEST_WIN_ATOM,
+            REQUEST_ATOM,
+            deadline,
+        )
+    }
+
+    /// Sets up communication with Sawfish over a connection to `display`
+    /// which has just been established.
+    ///
+    /// If the handshake times out, the returned [`ConnError::Io`] error holds
+    /// synthetic `x11:<display>` path.
+    fn with_display_connection(
+        display: &str,
+        (conn, screen): (xcb::Connection, i32),
+        req_win_atom: &str,
+        request_atom: &str,
+        deadline: Option<std::time::Instant>,
+    ) -> Result<Self, ConnError> {
         let screen = usize::try_from(screen)
-            .ok()
-            .and_then(|idx| setup.roots().nth(idx))
-            .ok_or(ConnError::BadScreen(screen))?;
-        let root = screen.root();
+            .map_err(|_| ConnError::BadScreen(screen))?;
+        let mut client = Self::with_connection(
+            conn,
+            screen,
+            req_win_atom,
+            request_atom,
+            deadline,
+        )
+        .map_err(|err| match err {
+            ConnError::Io(_, err) => {
+                ConnError::Io(format!("x11:{display}").into(), err)
+            }
+            err => err,
+        })?;
+        client.display = crate::unix::canonical_display(display);
+        Ok(client)
+    }
+
+    /// Sets up communication with Sawfish over an existing X11 connection.
+    ///
+    /// Uses the default atom names; see [`Self::with_connection`].
+    pub fn from_connection(
+        conn: xcb::Connection,
+        screen: usize,
+    ) -> Result<Self, ConnError> {
+        Self::with_connection(
+            conn,
+            screen,
+            REQUEST_WIN_ATOM,
+            REQUEST_ATOM,
+            None,
+        )
+    }
+
+    /// Sets up communication with Sawfish over an existing X11 connection
+    /// using custom atom names.
+    ///
+    /// Interns the atoms and creates the portal window on screen with index
+    /// `screen`.  Display name of the resulting client is empty.
+    ///
+    /// If `deadline` is given and the server doesn’t respond in time, fails
+    /// with [`ConnError::Io`] holding a `TimedOut` error and an empty path.
+    fn with_connection(
+        conn: xcb::Connection,
+        screen: usize,
+        req_win_atom: &str,
+        request_atom: &str,
+        deadline: Option<std::time::Instant>,
+    ) -> Result<Self, ConnError> {
+        let root = root_window(&conn, screen)?;
+        let req_win = find_request_window(&conn, root, req_win_atom, deadline)?;
 
         // Intern needed atoms.
         let cookie = conn.send_request(&x::InternAtom {
-            only_if_exists: true,
-            name: "_SAWFISH_REQUEST_WIN".as_bytes(),
+            only_if_exists: false,
+            name: request_atom.as_bytes(),
         });
-        let req_win_atom = conn.wait_for_reply(cookie)?.atom();
-        if req_win_atom.is_none() {
-            return Err(ConnError::ServerNotFound);
-        }
+        let property = wait_for_reply(&conn, cookie, deadline)?.atom();
 
         let cookie = conn.send_request(&x::InternAtom {
-            only_if_exists: false,
-            name: "_SAWFISH_REQUEST".as_bytes(),
+            only_if_exists: true,
+            name: b"UTF8_STRING",
         });
-        let property = conn.wait_for_reply(cookie)?.atom();
-
-        // Get the server's request window ID from the root window property
-        let reply =
-            conn.wait_for_reply(conn.send_request(&x::GetProperty {
-                delete: false,
-                window: root,
-                property: req_win_atom,
-                r#type: x::ATOM_CARDINAL,
-                long_offset: 0,
-                long_length: 1,
-            }))?;
-
-        // Validate property type and format
-        if reply.r#type() != x::ATOM_CARDINAL ||
-            reply.format() != x::Window::FORMAT ||
-            reply.length() != 1
-        {
-            return Err(ConnError::ServerNotFound);
-        }
-        let req_win = reply.value::<x::Window>()[0];
+        let utf8_string = wait_for_reply(&conn, cookie, deadline)?.atom();
 
         // Create the portal window (private communication window)
         let portal = conn.generate_id();
-        conn.send_and_check_request(&x::CreateWindow {
+        let cookie = conn.send_request_checked(&x::CreateWindow {
             depth: x::COPY_FROM_PARENT as u8,
             wid: portal,
             parent: root,
@@ -86,9 +239,28 @@ impl Client {
             class: x::WindowClass::InputOutput,
             visual: x::COPY_FROM_PARENT,
             value_list: &[x::Cw::EventMask(x::EventMask::PROPERTY_CHANGE)],
-        })?;
+        });
+        // Round trip so that checking the request below doesn’t block.
+        let sync = conn.send_request(&x::GetInputFocus {});
+        wait_for_reply(&conn, sync, deadline)?;
+        conn.check_request(cookie)?;
 
-        Ok(Self { conn, req_win, portal, property })
+        let now = std::time::Instant::now();
+        Ok(Self {
+            conn,
+            display: String::new(),
+            req_win,
+            portal,
+            property,
+            utf8_string,
+            max_response: crate::DEFAULT_MAX_RESPONSE,
+            max_request: crate::DEFAULT_MAX_REQUEST,
+            read_timeout: None,
+            created_at: now,
+            last_used: now,
+            last_was_error: None,
+            io_stats: Default::default(),
+        })
     }
 
     /// Sends form to the server for evaluation and waits for response if
@@ -98,30 +270,196 @@ impl Client {
         form: &[u8],
         is_async: bool,
     ) -> Result<EvalResponse, EvalError> {
-        self.send_request(form, is_async).map_err(std::io::Error::other)?;
-        if is_async {
-            self.conn.flush().map_err(std::io::Error::other)?;
-            Ok(Ok(Vec::new()))
-        } else {
-            self.wait_for_property_notify().map_err(std::io::Error::other)?;
-            self.read_response()
-        }
+        self.send_request(form, is_async)?;
+        if is_async { Ok(Ok(Vec::new())) } else { self.read_response() }
     }
 
     /// Sends request to the server.
-    fn send_request(
+    ///
+    /// If `is_async` is `false`, the caller must call [`Self::read_response`]
+    /// before sending another request.  X11 protocol uses a single property
+    /// to pass forms and responses so requests cannot be pipelined.
+    pub fn send_request(
+        &mut self,
+        form: &[u8],
+        is_async: bool,
+    ) -> Result<(), EvalError> {
+        crate::protocol::check_request_len(form.len(), self.max_request)?;
+        self.last_used = std::time::Instant::now();
+        self.write_request(form, is_async)?;
+        flush(&self.conn)?;
+        self.io_stats.bytes_sent += u64::try_from(form.len()).unwrap();
+        Ok(())
+    }
+
+    /// Sends async requests for all forms flushing the connection once; see
+    /// [`crate::Client::batch_send`].
+    pub fn batch_send(
+        &mut self,
+        forms: &[impl AsRef<[u8]>],
+    ) -> Result<(), EvalError> {
+        for form in forms {
+            let len = form.as_ref().len();
+            crate::protocol::check_request_len(len, self.max_request)?;
+        }
+        if forms.is_empty() {
+            return Ok(());
+        }
+        self.last_used = std::time::Instant::now();
+        for form in forms {
+            let form = form.as_ref();
+            self.write_request(form, true)?;
+            self.io_stats.bytes_sent += u64::try_from(form.len()).unwrap();
+        }
+        flush(&self.conn)
+    }
+
+    /// Waits for and reads response from the server.
+    pub fn read_response(&mut self) -> Result<EvalResponse, EvalError> {
+        self.wait_for_property_notify()?;
+        let res = self.read_property()?;
+        self.last_was_error = Some(res.is_err());
+        Ok(res)
+    }
+
+    /// Returns canonical name of the display the client is connected to.
+    pub fn display(&self) -> &str { &self.display }
+
+    /// Returns resource id of Sawfish’s request window.
+    pub fn request_window(&self) -> u32 { self.req_win.resource_id() }
+
+    /// Returns time the client was created.
+    pub fn created_at(&self) -> std::time::Instant { self.created_at }
+
+    /// Returns time the last request was sent.
+    pub fn last_used(&self) -> std::time::Instant { self.last_used }
+
+    /// Returns whether the last response read was an error.
+    pub fn last_was_error(&self) -> Option<bool> { self.last_was_error }
+
+    /// Returns number of bytes sent and received.
+    pub fn io_stats(&self) -> crate::IoStats { self.io_stats }
+
+    /// Sets maximum length of response data.
+    pub fn set_max_response(&mut self, max_response: usize) {
+        self.max_response = max_response;
+    }
+
+    /// Sets maximum length of a form.
+    pub fn set_max_request(&mut self, max_request: usize) {
+        self.max_request = max_request;
+    }
+
+    /// Sends form to the server for evaluation and reads `count` responses.
+    ///
+    /// X11 protocol supports at most one response per request so `count`
+    /// greater than one results in an `Unsupported` error.
+    pub fn eval_multi(
+        &mut self,
+        form: &[u8],
+        count: usize,
+    ) -> Result<Vec<EvalResponse>, EvalError> {
+        match count {
+            0 => self.eval(form, true).map(|_| Vec::new()),
+            1 => self.eval(form, false).map(|res| vec![res]),
+            _ => Err(std::io::ErrorKind::Unsupported.into()),
+        }
+    }
+
+    /// Sends form to the server for evaluation and reads response calling
+    /// `progress` once the whole response is read.
+    ///
+    /// The response is read with a single request so there’s no intermediate
+    /// progress to report.
+    pub fn eval_with_progress(
+        &mut self,
+        form: &[u8],
+        mut progress: impl FnMut(usize, usize),
+    ) -> Result<EvalResponse, EvalError> {
+        let res = self.eval(form, false)?;
+        let len = res.as_ref().map_or_else(Vec::len, Vec::len);
+        progress(len, len);
+        Ok(res)
+    }
+
+    /// Returns an `Unsupported` error since X11 protocol has no response
+    /// frames.
+    pub fn eval_frame(&mut self, _form: &[u8]) -> Result<Vec<u8>, EvalError> {
+        Err(std::io::ErrorKind::Unsupported.into())
+    }
+
+    /// Reads a stray response; not supported with X11 protocol.
+    pub fn drain_pending(&mut self) -> Result<Option<EvalResponse>, EvalError> {
+        Err(std::io::ErrorKind::Unsupported.into())
+    }
+
+    /// Sends form to the server for evaluation and writes response data into
+    /// `out`.
+    ///
+    /// Returns whether evaluation succeeded.
+    pub fn eval_to_writer(
+        &mut self,
+        form: &[u8],
+        out: &mut impl std::io::Write,
+    ) -> Result<bool, EvalError> {
+        let (success, data) = match self.eval(form, false)? {
+            Ok(data) => (true, data),
+            Err(data) => (false, data),
+        };
+        out.write_all(&data)?;
+        Ok(success)
+    }
+
+    /// Returns maximum time to wait for a response.
+    pub fn read_timeout(&self) -> Option<std::time::Duration> {
+        self.read_timeout
+    }
+
+    /// Sets maximum time to wait for a response.
+    ///
+    /// Like [`std::os::unix::net::UnixStream::set_read_timeout`], fails with
+    /// `InvalidInput` error if the timeout is zero.
+    pub fn set_read_timeout(
+        &mut self,
+        timeout: Option<std::time::Duration>,
+    ) -> std::io::Result<()> {
+        if timeout.is_some_and(|timeout| timeout.is_zero()) {
+            return Err(std::io::ErrorKind::InvalidInput.into());
+        }
+        self.read_timeout = timeout;
+        Ok(())
+    }
+
+    /// Returns an `Unsupported` error since X11 connection does not support
+    /// write timeouts.
+    pub fn set_write_timeout(
+        &mut self,
+        _timeout: Option<std::time::Duration>,
+    ) -> std::io::Result<()> {
+        Err(std::io::ErrorKind::Unsupported.into())
+    }
+
+    /// Returns file descriptor of the X server connection.
+    pub fn as_fd(&self) -> std::os::fd::BorrowedFd<'_> {
+        connection_fd(&self.conn)
+    }
+
+    /// Writes request to the portal window and notifies the server.
+    fn write_request(
         &mut self,
         form: &[u8],
         is_async: bool,
-    ) -> Result<(), xcb::Error> {
+    ) -> Result<(), EvalError> {
         // Set the property on the portal window to the form.
-        self.conn.send_and_check_request(&x::ChangeProperty {
-            mode: x::PropMode::Replace,
-            window: self.portal,
-            property: self.property,
-            r#type: x::ATOM_STRING,
-            data: form,
-        })?;
+        self.conn
+            .send_and_check_request(&x::ChangeProperty {
+                mode: x::PropMode::Replace,
+                window: self.portal,
+                property: self.property,
+                r#type: x::ATOM_STRING,
+                data: form,
+            })
+            .map_err(std::io::Error::other)?;
         // Swallow the PropertyNotify event resulting from us changing the
         // property..
         self.wait_for_property_notify()?;
@@ -138,24 +476,30 @@ impl Client {
                 0,
             ]),
         );
-        self.conn.send_and_check_request(&x::SendEvent {
-            propagate: false,
-            destination: x::SendEventDest::Window(self.req_win),
-            event_mask: x::EventMask::NO_EVENT,
-            event: &event,
-        })?;
+        self.conn
+            .send_and_check_request(&x::SendEvent {
+                propagate: false,
+                destination: x::SendEventDest::Window(self.req_win),
+                event_mask: x::EventMask::NO_EVENT,
+                event: &event,
+            })
+            .map_err(std::io::Error::other)?;
         Ok(())
     }
 
-    /// Reads response from the server.
-    fn read_response(&mut self) -> Result<EvalResponse, EvalError> {
+    /// Reads response from the portal window property.
+    ///
+    /// The property may be of `STRING` or `UTF8_STRING` type.  In either case
+    /// the bytes are passed through as is and it’s up to the caller to decode
+    /// them (as Latin-1 or UTF-8 respectively).
+    fn read_property(&mut self) -> Result<EvalResponse, EvalError> {
         let mut long_length = 16u32;
-        let (success, data) = loop {
+        loop {
             let cookie = self.conn.send_request(&x::GetProperty {
                 delete: false,
                 window: self.portal,
                 property: self.property,
-                r#type: x::ATOM_STRING,
+                r#type: x::ATOM_ANY,
                 long_offset: 0,
                 long_length,
             });
@@ -163,7 +507,11 @@ impl Client {
                 .conn
                 .wait_for_reply(cookie)
                 .map_err(std::io::Error::other)?;
-            if reply.r#type() != x::ATOM_STRING || reply.format() != 8 {
+            if !is_text_property(
+                reply.r#type(),
+                reply.format(),
+                self.utf8_string,
+            ) {
                 return Err(EvalError::BadResponse {
                     window: self.portal,
                     atom: self.property,
@@ -171,35 +519,654 @@ impl Client {
                     format: reply.format(),
                 });
             }
+            let value = reply.value::<u8>();
             let bytes_after = reply.bytes_after();
             if bytes_after == 0 {
-                break reply
-                    .value::<u8>()
-                    .split_first()
-                    .map(|(status, data)| (*status == 1, data.to_vec()))
-                    .ok_or(EvalError::NoResponse)?;
+                self.io_stats.bytes_received +=
+                    u64::try_from(value.len()).unwrap();
+                return crate::protocol::split_response(value);
             }
-            long_length += (bytes_after / 4) + 1;
-        };
-        Ok(if success { Ok(data) } else { Err(data) })
+            long_length =
+                next_long_length(value.len(), bytes_after, self.max_response)?;
+        }
     }
 
     /// Loops waiting for a `PropertyNotify` event on the portal window.
-    fn wait_for_property_notify(&mut self) -> Result<(), xcb::Error> {
+    ///
+    /// If read timeout is set and the event doesn’t arrive in time, fails with
+    /// an I/O error whose kind is `TimedOut`.
+    fn wait_for_property_notify(&mut self) -> Result<(), EvalError> {
+        let Some(timeout) = self.read_timeout else {
+            loop {
+                let event = self
+                    .conn
+                    .wait_for_event()
+                    .map_err(std::io::Error::other)?;
+                if self.is_response_notify(&event) {
+                    return Ok(());
+                }
+            }
+        };
+
+        let deadline = std::time::Instant::now() + timeout;
+        flush(&self.conn)?;
         loop {
-            let event = self.conn.wait_for_event()?;
-            if let xcb::Event::X(x::Event::PropertyNotify(ev)) = event &&
-                ev.window() == self.portal &&
-                ev.atom() == self.property
+            // Process queued events first.  They may have been read from the
+            // socket already in which case the socket won’t be readable.
+            while let Some(event) =
+                self.conn.poll_for_event().map_err(std::io::Error::other)?
             {
-                return Ok(());
+                if self.is_response_notify(&event) {
+                    return Ok(());
+                }
+            }
+            wait_readable(self.as_fd(), deadline)?;
+        }
+    }
+
+    /// Returns whether the event notifies of response being written to the
+    /// portal window.
+    fn is_response_notify(&self, event: &xcb::Event) -> bool {
+        matches!(
+            event,
+            xcb::Event::X(x::Event::PropertyNotify(ev))
+                if ev.window() == self.portal && ev.atom() == self.property
+        )
+    }
+}
+
+/// Checks whether Sawfish is running on the display.
+///
+/// Connects to the X server and checks that the root window property naming
+/// Sawfish’s request window is set and that the window exists.  Unlike
+/// [`Client::open`], doesn’t create the portal window.
+pub fn is_sawfish_running(display: &str) -> Result<bool, ConnError> {
+    let (conn, screen) = xcb::Connection::connect(Some(display))?;
+    let screen =
+        usize::try_from(screen).map_err(|_| ConnError::BadScreen(screen))?;
+    is_sawfish_running_on(&conn, screen)
+}
+
+/// Checks whether Sawfish is running on screen with given index; see
+/// [`is_sawfish_running`].
+fn is_sawfish_running_on(
+    conn: &xcb::Connection,
+    screen: usize,
+) -> Result<bool, ConnError> {
+    let root = root_window(conn, screen)?;
+    let req_win = match find_request_window(conn, root, REQUEST_WIN_ATOM, None)
+    {
+        Ok(req_win) => req_win,
+        Err(
+            ConnError::ServerNotFound | ConnError::BadServerProperty { .. },
+        ) => {
+            return Ok(false);
+        }
+        Err(err) => return Err(err),
+    };
+    // The property may be left over after Sawfish crashed.
+    let cookie = conn.send_request(&x::GetWindowAttributes { window: req_win });
+    match conn.wait_for_reply(cookie) {
+        Ok(_) => Ok(true),
+        Err(xcb::Error::Protocol(xcb::ProtocolError::X(
+            x::Error::Window(_),
+            _,
+        ))) => Ok(false),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Returns root window of screen with given index.
+fn root_window(
+    conn: &xcb::Connection,
+    screen: usize,
+) -> Result<x::Window, ConnError> {
+    let screen_err =
+        || ConnError::BadScreen(i32::try_from(screen).unwrap_or(i32::MAX));
+    let screen = conn.get_setup().roots().nth(screen).ok_or_else(screen_err)?;
+    Ok(screen.root())
+}
+
+/// Reads Sawfish’s request window from the root window property.
+///
+/// `req_win_atom` is the name of the property.  Fails with
+/// [`ConnError::ServerNotFound`] if the atom doesn’t exist and with
+/// [`ConnError::BadServerProperty`] if the property is missing or malformed.
+fn find_request_window(
+    conn: &xcb::Connection,
+    root: x::Window,
+    req_win_atom: &str,
+    deadline: Option<std::time::Instant>,
+) -> Result<x::Window, ConnError> {
+    let cookie = conn.send_request(&x::InternAtom {
+        only_if_exists: true,
+        name: req_win_atom.as_bytes(),
+    });
+    let req_win_atom = wait_for_reply(conn, cookie, deadline)?.atom();
+    if req_win_atom.is_none() {
+        return Err(ConnError::ServerNotFound);
+    }
+
+    let cookie = conn.send_request(&x::GetProperty {
+        delete: false,
+        window: root,
+        property: req_win_atom,
+        r#type: x::ATOM_CARDINAL,
+        long_offset: 0,
+        long_length: 1,
+    });
+    let reply = wait_for_reply(conn, cookie, deadline)?;
+    check_server_property(reply.r#type(), reply.format(), reply.length())?;
+    Ok(reply.value::<x::Window>()[0])
+}
+
+/// Returns file descriptor of the X server connection.
+fn connection_fd(conn: &xcb::Connection) -> std::os::fd::BorrowedFd<'_> {
+    use std::os::fd::AsRawFd;
+    // SAFETY: The descriptor is owned by the connection and remains open for
+    // as long as the connection is borrowed.
+    unsafe { std::os::fd::BorrowedFd::borrow_raw(conn.as_raw_fd()) }
+}
+
+/// Waits for reply to a request giving up once `deadline` passes.
+///
+/// Without a deadline, blocks until the reply arrives.  Otherwise, fails with
+/// [`ConnError::Io`] holding a `TimedOut` error and an empty path if the
+/// reply doesn’t arrive in time.
+fn wait_for_reply<C: xcb::CookieWithReplyChecked>(
+    conn: &xcb::Connection,
+    cookie: C,
+    deadline: Option<std::time::Instant>,
+) -> Result<C::Reply, ConnError> {
+    let Some(deadline) = deadline else {
+        return Ok(conn.wait_for_reply(cookie)?);
+    };
+    conn.flush()?;
+    loop {
+        if let Some(reply) = conn.poll_for_reply(&cookie) {
+            return Ok(reply?);
+        }
+        wait_readable(connection_fd(conn), deadline)
+            .map_err(|err| ConnError::Io(Default::default(), err))?;
+    }
+}
+
+/// Flushes requests queued on the connection.
+///
+/// Failures are reported as [`EvalError::X11`] so that the underlying XCB
+/// error is preserved.
+fn flush(conn: &xcb::Connection) -> Result<(), EvalError> {
+    conn.flush().map_err(EvalError::from)
+}
+
+/// Waits until the file descriptor is readable or the deadline passes.
+///
+/// Fails with `TimedOut` error if the deadline passes.
+fn wait_readable(
+    fd: std::os::fd::BorrowedFd<'_>,
+    deadline: std::time::Instant,
+) -> std::io::Result<()> {
+    use std::os::fd::AsRawFd;
+
+    loop {
+        let remaining =
+            deadline.saturating_duration_since(std::time::Instant::now());
+        if remaining.is_zero() {
+            return Err(std::io::ErrorKind::TimedOut.into());
+        }
+        // Round up so that sub-millisecond remainder doesn’t cause a busy
+        // loop.
+        let timeout = remaining.as_micros().div_ceil(1000);
+        let timeout =
+            libc::c_int::try_from(timeout).unwrap_or(libc::c_int::MAX);
+        let mut pollfd = libc::pollfd {
+            fd: fd.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        // SAFETY: pollfd is a valid pointer to a single pollfd structure.
+        match unsafe { libc::poll(&mut pollfd, 1, timeout) } {
+            -1 => {
+                let err = std::io::Error::last_os_error();
+                if err.kind() != std::io::ErrorKind::Interrupted {
+                    return Err(err);
+                }
             }
+            0 => (),
+            _ => return Ok(()),
         }
     }
 }
 
+/// Returns whether property of given type and format holds text.
+///
+/// Accepts `STRING` and `UTF8_STRING` properties of 8-bit format.
+/// `utf8_string` is the interned `UTF8_STRING` atom.
+fn is_text_property(typ: x::Atom, format: u8, utf8_string: x::Atom) -> bool {
+    format == 8 &&
+        typ != x::ATOM_NONE &&
+        (typ == x::ATOM_STRING || typ == utf8_string)
+}
+
+/// Combines errors from connecting through Unix socket and X11.
+///
+/// If the Unix socket does not exist (or its path couldn’t be determined), the
+/// environment is likely X11-only and the X11 error is returned.  Otherwise,
+/// both errors are returned in [`ConnError::Both`].
+fn combine_errors(unix_err: ConnError, x11_err: ConnError) -> ConnError {
+    let no_socket = match &unix_err {
+        ConnError::NoLogname => true,
+        ConnError::Io(_, err) => err.kind() == std::io::ErrorKind::NotFound,
+        _ => false,
+    };
+    if no_socket {
+        x11_err
+    } else {
+        ConnError::Both(Box::new(unix_err), Box::new(x11_err))
+    }
+}
+
+/// Checks type, format and length of the root window property holding
+/// Sawfish’s request window.
+fn check_server_property(
+    typ: x::Atom,
+    format: u8,
+    length: u32,
+) -> Result<(), ConnError> {
+    if typ == x::ATOM_CARDINAL && format == x::Window::FORMAT && length == 1 {
+        Ok(())
+    } else {
+        Err(ConnError::BadServerProperty { typ, format, length })
+    }
+}
+
+/// Returns `long_length` needed to read the entire response property.
+///
+/// `len` is the number of bytes read so far (from the start of the property)
+/// and `bytes_after` is the number of bytes remaining.  Fails with
+/// [`EvalError::ResponseTooLarge`] if the response data (i.e. property without
+/// the status byte) would be longer than `max_response` bytes.
+fn next_long_length(
+    len: usize,
+    bytes_after: u32,
+    max_response: usize,
+) -> Result<u32, EvalError> {
+    let total = u64::try_from(len).unwrap() + u64::from(bytes_after);
+    let data_len = total.saturating_sub(1);
+    if !matches!(
+        crate::protocol::check_response_len(data_len, max_response),
+        Ok(Some(_))
+    ) {
+        return Err(EvalError::ResponseTooLarge(data_len, None));
+    }
+    Ok(u32::try_from(total.div_ceil(4)).unwrap_or(u32::MAX))
+}
+
 impl Drop for Client {
     fn drop(&mut self) {
         self.conn.send_request(&x::DestroyWindow { window: self.portal });
     }
 }
+
+
+#[test]
+fn test_next_long_length() {
+    assert_eq!(4, next_long_length(12, 4, 100).unwrap());
+    assert_eq!(5, next_long_length(16, 1, 100).unwrap());
+    assert_eq!(26, next_long_length(64, 37, 100).unwrap());
+    match next_long_length(64, 38, 100) {
+        Err(EvalError::ResponseTooLarge(101, None)) => (),
+        res => panic!("Unexpected result: {res:?}"),
+    }
+}
+
+#[test]
+fn test_is_text_property() {
+    use xcb::XidNew;
+
+    let utf8_string = x::Atom::new(300);
+    assert!(is_text_property(x::ATOM_STRING, 8, utf8_string));
+    assert!(is_text_property(utf8_string, 8, utf8_string));
+    assert!(!is_text_property(x::ATOM_STRING, 16, utf8_string));
+    assert!(!is_text_property(utf8_string, 32, utf8_string));
+    assert!(!is_text_property(x::ATOM_CARDINAL, 8, utf8_string));
+    assert!(!is_text_property(x::Atom::new(301), 8, utf8_string));
+
+    // If UTF8_STRING isn’t interned, only STRING is accepted.
+    assert!(is_text_property(x::ATOM_STRING, 8, x::ATOM_NONE));
+    assert!(!is_text_property(x::ATOM_NONE, 8, x::ATOM_NONE));
+}
+
+#[test]
+fn test_open_with_atoms_no_server() {
+    match Client::open_with_atoms(
+        ":32767",
+        "_TEST_REQUEST_WIN",
+        "_TEST_REQUEST",
+        None,
+    ) {
+        Err(ConnError::X11(_)) => (),
+        Err(err) => panic!("Unexpected error: {err}"),
+        Ok(_) => panic!("Unexpectedly connected"),
+    }
+}
+
+#[test]
+fn test_from_connection() {
+    // The test requires an X server.  Without one there’s nothing to test.
+    let Ok((conn, screen)) = xcb::Connection::connect(None) else { return };
+    let screen = usize::try_from(screen).unwrap();
+    let count = conn.get_setup().roots().count();
+
+    let (bad_conn, _) = xcb::Connection::connect(None).unwrap();
+    match Client::from_connection(bad_conn, count) {
+        Err(ConnError::BadScreen(idx)) => {
+            assert_eq!(count, usize::try_from(idx).unwrap())
+        }
+        res => panic!("Unexpected result: {:?}", res.err()),
+    }
+
+    // Whether the connection succeeds depends on Sawfish running.
+    match Client::from_connection(conn, screen) {
+        Ok(client) => {
+            assert_eq!("", client.display());
+            assert_ne!(0, client.request_window());
+        }
+        Err(
+            ConnError::ServerNotFound | ConnError::BadServerProperty { .. },
+        ) => (),
+        Err(err) => panic!("Unexpected error: {err}"),
+    }
+}
+
+#[test]
+fn test_batch_send() {
+    // The test requires Sawfish running.  Without it there’s nothing to test.
+    let Ok(display) = std::env::var("DISPLAY") else { return };
+    let Ok(mut client) = Client::open(&display, None) else { return };
+
+    let res = client.batch_send(&["t", ""]);
+    assert!(matches!(res, Err(EvalError::EmptyForm)), "{res:?}");
+    assert_eq!(0, client.io_stats.bytes_sent);
+
+    // Use side-effect-free forms since this runs against user’s session.
+    client.batch_send(&["(+ 1 2)", "t"]).unwrap();
+    assert_eq!(8, client.io_stats.bytes_sent);
+    // The connection is still usable.
+    let res = client.eval(b"(+ 2 3)", false).unwrap();
+    assert_eq!(Ok(b"5".to_vec()), res);
+}
+
+#[test]
+fn test_check_server_property() {
+    let check = |typ, format, length| match check_server_property(
+        typ, format, length,
+    ) {
+        Ok(()) => None,
+        Err(ConnError::BadServerProperty { typ, format, length }) => {
+            Some((typ, format, length))
+        }
+        Err(err) => panic!("Unexpected error: {err}"),
+    };
+
+    assert_eq!(None, check(x::ATOM_CARDINAL, 32, 1));
+    // Missing property.
+    assert_eq!(Some((x::ATOM_NONE, 0, 0)), check(x::ATOM_NONE, 0, 0));
+    // Malformed property.
+    assert_eq!(Some((x::ATOM_STRING, 8, 4)), check(x::ATOM_STRING, 8, 4));
+    assert_eq!(Some((x::ATOM_CARDINAL, 16, 1)), check(x::ATOM_CARDINAL, 16, 1));
+    assert_eq!(Some((x::ATOM_CARDINAL, 32, 2)), check(x::ATOM_CARDINAL, 32, 2));
+}
+
+#[test]
+fn test_server_not_found() {
+    // The test requires an X server.  Without one there’s nothing to test.
+    let Ok(display) = std::env::var("DISPLAY") else { return };
+    let Ok((conn, _)) = xcb::Connection::connect(Some(&display)) else {
+        return;
+    };
+
+    // Atom which doesn’t exist.
+    let name = format!("_SAWFISH_CLIENT_TEST_MISSING_{}", std::process::id());
+    match Client::open_with_atoms(&display, &name, REQUEST_ATOM, None) {
+        Err(ConnError::ServerNotFound) => (),
+        res => panic!("Unexpected result: {:?}", res.err()),
+    }
+
+    // Atom which exists but the root window has no such property.
+    let name = format!("_SAWFISH_CLIENT_TEST_UNSET_{}", std::process::id());
+    let cookie = conn.send_request(&x::InternAtom {
+        only_if_exists: false,
+        name: name.as_bytes(),
+    });
+    conn.wait_for_reply(cookie).unwrap();
+    match Client::open_with_atoms(&display, &name, REQUEST_ATOM, None) {
+        Err(ConnError::BadServerProperty { typ, format: 0, length: 0 }) => {
+            assert_eq!(x::ATOM_NONE, typ)
+        }
+        res => panic!("Unexpected result: {:?}", res.err()),
+    }
+}
+
+#[test]
+fn test_open_with_auth() {
+    // There’s no X server on the display so connecting must fail cleanly
+    // rather than, say, panic.
+    let auth = xcb::AuthInfo { name: "MIT-MAGIC-COOKIE-1", data: "cookie" };
+    assert!(Client::open_with_auth(":4242", auth, None).is_err());
+
+    // With an X server, whether connecting succeeds depends on the server
+    // accepting the cookie and on Sawfish running.  Either way, it shouldn’t
+    // panic.
+    if let Ok(display) = std::env::var("DISPLAY") {
+        let _ = Client::open_with_auth(&display, auth, None);
+    }
+}
+
+#[test]
+fn test_flush_closed_connection() {
+    use std::os::fd::AsRawFd;
+
+    // The test requires an X server.  Without one there’s nothing to test.
+    let Ok((conn, _)) = xcb::Connection::connect(None) else { return };
+    // SAFETY: The descriptor is owned by the connection and stays open.
+    assert_eq!(0, unsafe { libc::shutdown(conn.as_raw_fd(), libc::SHUT_RDWR) });
+    conn.send_request(&x::NoOperation {});
+    match flush(&conn) {
+        Err(EvalError::X11(xcb::Error::Connection(_))) => (),
+        res => panic!("Unexpected result: {res:?}"),
+    }
+}
+
+/// Starts a fake X server which completes connection setup and responds to
+/// requests with whatever `handler` returns.
+///
+/// `handler` is called with request’s major opcode and the entire request
+/// and returns a reply or error to send (see [`fake_reply`]) or `None` if the
+/// server should not respond.  Sequence number of the response is filled in
+/// by the server.  Returns the client connection and the server’s thread
+/// which exits once the client disconnects.
+#[cfg(test)]
+fn start_fake_server(
+    handler: impl Fn(u8, &[u8]) -> Option<Vec<u8>> + Send + 'static,
+) -> (xcb::Connection, std::thread::JoinHandle<()>) {
+    use std::io::{Read, Write};
+    let (client, mut server) = std::os::unix::net::UnixStream::pair().unwrap();
+    let thread = std::thread::spawn(move || {
+        // Connection setup request without authorisation data.
+        let mut request = [0; 12];
+        server.read_exact(&mut request).unwrap();
+        let mut reply = Vec::with_capacity(80);
+        // Success, protocol version 11.0 and 72 bytes of additional data.
+        reply.extend_from_slice(&[1, 0]);
+        reply.extend_from_slice(&11u16.to_ne_bytes());
+        reply.extend_from_slice(&0u16.to_ne_bytes());
+        reply.extend_from_slice(&18u16.to_ne_bytes());
+        // Release, resource id base and mask and motion buffer size.
+        reply.extend_from_slice(&0u32.to_ne_bytes());
+        reply.extend_from_slice(&0x0020_0000u32.to_ne_bytes());
+        reply.extend_from_slice(&0x001f_ffffu32.to_ne_bytes());
+        reply.extend_from_slice(&0u32.to_ne_bytes());
+        // No vendor, maximum request length, one screen and no formats.
+        reply.extend_from_slice(&0u16.to_ne_bytes());
+        reply.extend_from_slice(&0xffffu16.to_ne_bytes());
+        reply.extend_from_slice(&[1, 0, 0, 0, 32, 32, 8, 255, 0, 0, 0, 0]);
+        // The screen: root window 1 and no allowed depths.
+        reply.extend_from_slice(&1u32.to_ne_bytes());
+        reply.extend_from_slice(&[0; 36]);
+        server.write_all(&reply).unwrap();
+        // Handle requests until the client disconnects.
+        let mut seq = 0u16;
+        let mut request = [0; 4];
+        while server.read_exact(&mut request[..4]).is_ok() {
+            let len = usize::from(u16::from_ne_bytes([request[2], request[3]]));
+            let mut request = request[..4].to_vec();
+            request.resize(len * 4, 0);
+            server.read_exact(&mut request[4..]).unwrap();
+            seq = seq.wrapping_add(1);
+            if let Some(mut reply) = handler(request[0], &request) {
+                reply[2..4].copy_from_slice(&seq.to_ne_bytes());
+                server.write_all(&reply).unwrap();
+            }
+        }
+    });
+    let conn = xcb::Connection::connect_with_fd(client.into(), None);
+    (conn.unwrap(), thread)
+}
+
+/// Builds a reply to send from [`start_fake_server`]’s handler.
+///
+/// `data` is the byte following the reply type and `body` what follows the
+/// length field.  Padding and the length are filled in.
+#[cfg(test)]
+fn fake_reply(data: u8, body: &[u8]) -> Vec<u8> {
+    let extra = body.len().saturating_sub(24).div_ceil(4);
+    let mut reply = vec![1, data, 0, 0];
+    reply.extend_from_slice(&u32::try_from(extra).unwrap().to_ne_bytes());
+    reply.extend_from_slice(body);
+    reply.resize(32 + extra * 4, 0);
+    reply
+}
+
+#[test]
+fn test_is_sawfish_running() {
+    const INTERN_ATOM: u8 = 16;
+    const GET_PROPERTY: u8 = 20;
+    const GET_WINDOW_ATTRIBUTES: u8 = 3;
+
+    /// Runs the check against a fake server with `atom` as interned
+    /// `_SAWFISH_REQUEST_WIN` atom and whether request window exists.
+    fn check(atom: u32, window_exists: bool) -> bool {
+        let (conn, thread) = start_fake_server(move |opcode, _| {
+            Some(match opcode {
+                INTERN_ATOM => fake_reply(0, &atom.to_ne_bytes()),
+                GET_PROPERTY => {
+                    let body = [
+                        &x::ATOM_CARDINAL.resource_id().to_ne_bytes()[..],
+                        &0u32.to_ne_bytes(),
+                        &1u32.to_ne_bytes(),
+                        &[0; 12],
+                        &0x123u32.to_ne_bytes(),
+                    ];
+                    fake_reply(32, &body.concat())
+                }
+                GET_WINDOW_ATTRIBUTES if window_exists => {
+                    fake_reply(0, &[0; 36])
+                }
+                GET_WINDOW_ATTRIBUTES => {
+                    // BadWindow error.
+                    let mut err = vec![0, 3, 0, 0];
+                    err.extend_from_slice(&0x123u32.to_ne_bytes());
+                    err.extend_from_slice(&[0, 0, GET_WINDOW_ATTRIBUTES]);
+                    err.resize(32, 0);
+                    err
+                }
+                _ => return None,
+            })
+        });
+        let res = is_sawfish_running_on(&conn, 0).unwrap();
+        core::mem::drop(conn);
+        thread.join().unwrap();
+        res
+    }
+
+    assert!(check(300, true));
+    // The atom doesn’t exist.
+    assert!(!check(0, true));
+    // The property is left over after Sawfish exited.
+    assert!(!check(300, false));
+}
+
+#[test]
+fn test_open_timeout() {
+    let (conn, thread) = start_fake_server(|_, _| None);
+    let timeout = std::time::Duration::from_millis(50);
+    let start = std::time::Instant::now();
+    let res = Client::with_display_connection(
+        "stalled:0",
+        (conn, 0),
+        REQUEST_WIN_ATOM,
+        REQUEST_ATOM,
+        Some(start + timeout),
+    );
+    match res {
+        Err(ConnError::Io(path, err)) => {
+            assert_eq!(std::io::ErrorKind::TimedOut, err.kind());
+            assert_eq!(std::path::Path::new("x11:stalled:0"), path);
+        }
+        Err(err) => panic!("Unexpected error: {err}"),
+        Ok(_) => panic!("Unexpectedly connected"),
+    }
+    assert!(start.elapsed() >= timeout);
+    thread.join().unwrap();
+}
+
+#[test]
+fn test_wait_readable() {
+    use std::io::Write;
+    use std::os::fd::AsFd;
+
+    let (mut tx, rx) = std::os::unix::net::UnixStream::pair().unwrap();
+    let timeout = std::time::Duration::from_millis(50);
+
+    // Nothing is ever written; times out.
+    let start = std::time::Instant::now();
+    let err = wait_readable(rx.as_fd(), start + timeout).unwrap_err();
+    assert_eq!(std::io::ErrorKind::TimedOut, err.kind());
+    assert!(start.elapsed() >= timeout);
+
+    tx.write_all(b"x").unwrap();
+    let deadline = std::time::Instant::now() + timeout;
+    wait_readable(rx.as_fd(), deadline).unwrap();
+}
+
+#[test]
+fn test_combine_errors() {
+    use std::io::ErrorKind;
+
+    let path = std::path::PathBuf::from("/tmp/.sawfish-user/host:0.0");
+
+    let err = combine_errors(ConnError::NoLogname, ConnError::ServerNotFound);
+    assert!(matches!(err, ConnError::ServerNotFound), "{err:?}");
+
+    let unix_err = ConnError::Io(path.clone(), ErrorKind::NotFound.into());
+    let err = combine_errors(unix_err, ConnError::ServerNotFound);
+    assert!(matches!(err, ConnError::ServerNotFound), "{err:?}");
+
+    let unix_err = ConnError::Io(path, ErrorKind::PermissionDenied.into());
+    let err = combine_errors(unix_err, ConnError::ServerNotFound);
+    assert!(
+        matches!(
+            &err,
+            ConnError::Both(unix, x11)
+                if matches!(**unix, ConnError::Io(..)) &&
+                    matches!(**x11, ConnError::ServerNotFound)
+        ),
+        "{err:?}"
+    );
+    assert_eq!(
+        "Unix socket: /tmp/.sawfish-user/host:0.0: permission denied; X11: No \
+         Sawfish server found on X11 screen",
+        err.to_string()
+    );
+}
@@ -0,0 +1,139 @@
+// sawfish-client -- client library to communicate with Sawfish window manager
+// © 2025 by Michał Nazarewicz <mina86@mina86.com>
+
+//! Tests verifying that evaluating forms doesn’t make needless allocations.
+//!
+//! The tests install a counting global allocator and modify environment of the
+//! process so they live in their own test binary.  To avoid races on the
+//! environment, there is only a single test in it.
+
+use std::io::{Read, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+
+/// Allocator counting allocations made by each thread.
+struct CountingAlloc;
+
+std::thread_local! {
+    static ALLOCATIONS: core::cell::Cell<usize> =
+        const { core::cell::Cell::new(0) };
+}
+
+// SAFETY: Delegates to the system allocator.
+unsafe impl std::alloc::GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: std::alloc::Layout) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+        // SAFETY: Caller upholds the contract.
+        unsafe { std::alloc::System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: std::alloc::Layout) {
+        // SAFETY: Caller upholds the contract.
+        unsafe { std::alloc::System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAlloc = CountingAlloc;
+
+/// Returns number of allocations the current thread made while running
+/// `func`.
+fn count_allocations<R>(func: impl FnOnce() -> R) -> (usize, R) {
+    let before = ALLOCATIONS.with(core::cell::Cell::get);
+    let res = func();
+    (ALLOCATIONS.with(core::cell::Cell::get) - before, res)
+}
+
+/// Returns a multi-megabyte response sent by the server for `big` form.
+fn big_response() -> Vec<u8> {
+    (0..4 << 20).map(|idx| b'a' + (idx % 26) as u8).collect()
+}
+
+/// Serves requests on `server` until the client disconnects.
+///
+/// `big` and `t` forms get dedicated responses while all other synchronous
+/// requests are responded to with `response`.
+fn server_thread(mut server: UnixStream) {
+    const HEADER_LEN: usize = sawfish_client::protocol::REQUEST_HEADER_LEN;
+    loop {
+        let mut header = [0; HEADER_LEN];
+        match server.read_exact(&mut header) {
+            Ok(()) => (),
+            Err(err) if err.kind() == std::io::ErrorKind::UnexpectedEof => {
+                break;
+            }
+            Err(err) => panic!("{err}"),
+        }
+        let len = u64::from_ne_bytes(header[1..].try_into().unwrap());
+        let mut form = vec![0; usize::try_from(len).unwrap()];
+        server.read_exact(&mut form).unwrap();
+        if header[0] == 1 {
+            continue;
+        }
+        let data = match form.as_slice() {
+            b"big" => big_response(),
+            b"t" => b"t".to_vec(),
+            _ => b"response".to_vec(),
+        };
+        let len = u64::try_from(data.len() + 1).unwrap();
+        server.write_all(&len.to_ne_bytes()).unwrap();
+        server.write_all(&[1]).unwrap();
+        server.write_all(&data).unwrap();
+    }
+}
+
+#[test]
+fn test_allocations() {
+    // Use a made up user name so that the socket isn’t created in the
+    // directory of a real Sawfish server.
+    let logname = format!("sawfish-client-test-{}", std::process::id());
+    // SAFETY: This is the only test in the binary so no other threads access
+    // the environment.
+    unsafe {
+        std::env::set_var("LOGNAME", &logname);
+        std::env::set_var("SAWFISH_HOSTNAME", "host.test");
+        std::env::remove_var("SAWFISH_TIMEOUT_MS");
+    }
+
+    let display = Some(":4245");
+    let dir = std::path::PathBuf::from(format!("/tmp/.sawfish-{logname}"));
+    let path = dir.join(sawfish_client::socket_file_name(display).unwrap());
+    std::fs::create_dir_all(&dir).unwrap();
+    let _ = std::fs::remove_file(&path);
+    let listener = UnixListener::bind(&path).unwrap();
+
+    let mut client = sawfish_client::Client::open(display).unwrap();
+    let (server, _) = listener.accept().unwrap();
+    let server = std::thread::spawn(move || server_thread(server));
+
+    // Sending a form doesn’t copy it.
+    let form = format!("({})", "x".repeat(1 << 20));
+    let (count, res) = count_allocations(|| client.send(&form));
+    res.unwrap();
+    assert_eq!(0, count);
+
+    // The only allocation is the response.
+    let (count, res) = count_allocations(|| client.eval(&form));
+    assert_eq!(Ok(b"response".to_vec()), res.unwrap());
+    assert_eq!(1, count);
+
+    // Form built from an owned vector is sent without copying.
+    let (count, res) = count_allocations(|| {
+        client.send(sawfish_client::Form::from(form.into_bytes()))
+    });
+    res.unwrap();
+    assert_eq!(0, count);
+
+    // The multi-megabyte response is discarded without being buffered.
+    let (count, res) = count_allocations(|| client.eval_status("big"));
+    assert!(res.unwrap());
+    assert_eq!(0, count);
+
+    // The connection stays in sync.
+    assert_eq!(Ok(b"t".to_vec()), client.eval("t").unwrap());
+
+    core::mem::drop(client);
+    server.join().unwrap();
+    core::mem::drop(listener);
+    std::fs::remove_file(&path).unwrap();
+    std::fs::remove_dir(&dir).unwrap();
+}
@@ -0,0 +1,82 @@
+// sawfish-client -- client library to communicate with Sawfish window manager
+// © 2025 by Michał Nazarewicz <mina86@mina86.com>
+
+//! Tests of the `SAWFISH_TIMEOUT_MS` environment variable.
+//!
+//! The test modifies environment of the process so it lives in its own test
+//! binary and is the only test in it.
+
+use std::os::fd::AsFd;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::time::Duration;
+
+/// Returns read and write timeout of the client’s socket.
+fn timeouts(
+    client: &sawfish_client::Client,
+) -> (Option<Duration>, Option<Duration>) {
+    let sock = UnixStream::from(client.as_fd().try_clone_to_owned().unwrap());
+    (sock.read_timeout().unwrap(), sock.write_timeout().unwrap())
+}
+
+#[test]
+fn test_default_timeout() {
+    // Use a made up user name so that the socket isn’t created in the
+    // directory of a real Sawfish server.
+    let logname = format!("sawfish-client-test-{}", std::process::id());
+    // SAFETY: This is the only test in the binary so no other threads access
+    // the environment.
+    unsafe {
+        std::env::set_var("LOGNAME", &logname);
+        std::env::set_var("SAWFISH_HOSTNAME", "host.test");
+        std::env::remove_var("SAWFISH_TIMEOUT_MS");
+    }
+
+    let display = Some(":4244");
+    let dir = std::path::PathBuf::from(format!("/tmp/.sawfish-{logname}"));
+    let path = dir.join(sawfish_client::socket_file_name(display).unwrap());
+    std::fs::create_dir_all(&dir).unwrap();
+    let _ = std::fs::remove_file(&path);
+    let listener = UnixListener::bind(&path).unwrap();
+
+    // Without the variable, timeouts aren’t set.
+    let client = sawfish_client::Client::open(display).unwrap();
+    assert_eq!((None, None), timeouts(&client));
+
+    // SAFETY: See above.
+    unsafe { std::env::set_var("SAWFISH_TIMEOUT_MS", "2000") };
+    let want = Some(Duration::from_secs(2));
+    let client = sawfish_client::Client::open(display).unwrap();
+    assert_eq!((want, want), timeouts(&client));
+    let (client, _) = sawfish_client::Client::open_verbose(display).unwrap();
+    assert_eq!((want, want), timeouts(&client));
+    let client = sawfish_client::Client::open_retry(
+        display,
+        1,
+        Duration::from_millis(10),
+    )
+    .unwrap();
+    assert_eq!((want, want), timeouts(&client));
+    let client = sawfish_client::Client::open_with_timeout(
+        display,
+        Duration::from_secs(5),
+    )
+    .unwrap();
+    assert_eq!((want, want), timeouts(&client));
+
+    // Explicit calls take precedence.
+    let mut client = sawfish_client::Client::open(display).unwrap();
+    let explicit = Some(Duration::from_secs(3));
+    client.set_read_timeout(explicit).unwrap();
+    client.set_write_timeout(None).unwrap();
+    assert_eq!((explicit, None), timeouts(&client));
+
+    // Invalid values are ignored.
+    // SAFETY: See above.
+    unsafe { std::env::set_var("SAWFISH_TIMEOUT_MS", "soon") };
+    let client = sawfish_client::Client::open(display).unwrap();
+    assert_eq!((None, None), timeouts(&client));
+
+    core::mem::drop(listener);
+    std::fs::remove_file(&path).unwrap();
+    std::fs::remove_dir(&dir).unwrap();
+}
@@ -0,0 +1,40 @@
+// sawfish-client -- client library to communicate with Sawfish window manager
+// © 2025 by Michał Nazarewicz <mina86@mina86.com>
+
+//! Tests of [`sawfish_client::Client::open_verbose`].
+//!
+//! The test modifies environment of the process so it lives in its own test
+//! binary and is the only test in it.
+
+#[test]
+fn test_open_verbose() {
+    let display = Some(":4242.7");
+
+    // SAFETY: This is the only test in the binary so no other threads access
+    // the environment.
+    unsafe {
+        std::env::remove_var("LOGNAME");
+        std::env::set_var("SAWFISH_HOSTNAME", "host.test");
+    }
+    // Without LOGNAME the socket path can’t be determined.
+    assert!(sawfish_client::Client::open_verbose(display).is_err());
+
+    // Use a made up user name so that the socket isn’t created in the
+    // directory of a real Sawfish server.
+    let logname = format!("sawfish-client-test-{}", std::process::id());
+    // SAFETY: See above.
+    unsafe { std::env::set_var("LOGNAME", &logname) };
+    let dir = std::path::PathBuf::from(format!("/tmp/.sawfish-{logname}"));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("host.test:4242.7");
+    let _ = std::fs::remove_file(&path);
+    let listener = std::os::unix::net::UnixListener::bind(&path).unwrap();
+    let res = sawfish_client::Client::open_verbose(display);
+    core::mem::drop(listener);
+    std::fs::remove_file(&path).unwrap();
+    std::fs::remove_dir(&dir).unwrap();
+
+    let (client, got) = res.unwrap();
+    assert_eq!(path, got);
+    assert_eq!(sawfish_client::Transport::Unix, client.transport());
+}
@@ -1,10 +1,12 @@
 // Example usage of the sawfish-client library.
 // © 2025 by Michał Nazarewicz <mina86@mina86.com>
 
-use std::ffi::OsStr;
+use std::ffi::{OsStr, OsString};
 use std::io::Read;
 use std::path::{Path, PathBuf};
 
+use sawfish_client::sexp;
+
 /// Example program using the sawfish-client library.
 ///
 /// ```shell
@@ -13,12 +15,38 @@ use std::path::{Path, PathBuf};
 /// < "darkstar.example.net"
 /// ```
 fn main() -> std::process::ExitCode {
-    let mut args = std::env::args_os();
+    let mut args = std::env::args_os().peekable();
     let argv0 = PathBuf::from(args.next().unwrap());
     let argv0 = argv0.display();
 
-    // Establish connection.  open will read $DISPLAY to get the display name.
-    let mut conn = match sawfish_client::open(None) {
+    // --display must come first since the connection is established before
+    // processing remaining arguments.
+    let display = if args.next_if(|arg| arg == "--display").is_some() {
+        match args.next().map(OsString::into_string) {
+            Some(Ok(display)) => Some(display),
+            Some(Err(_)) | None => {
+                eprintln!("{argv0}: --display requires a valid display name");
+                return std::process::ExitCode::FAILURE;
+            }
+        }
+    } else {
+        None
+    };
+
+    // Establish connection.  If --display isn’t given, $DISPLAY or the
+    // configured default display is used.
+    let config = config_display();
+    let display = match sawfish_client::get_display(
+        display.as_deref(),
+        config.as_deref(),
+    ) {
+        Ok(display) => display,
+        Err(err) => {
+            eprintln!("{argv0}: {err}");
+            return std::process::ExitCode::FAILURE;
+        }
+    };
+    let mut conn = match sawfish_client::open(Some(&display)) {
         Ok(conn) => conn,
         Err(err) => {
             eprintln!("{argv0}: {err}");
@@ -28,32 +56,26 @@ fn main() -> std::process::ExitCode {
 
     // Sends a single form for evaluation.  If `is_async` is true, does not read
     // the response.
-    let mut eval = |form: &[u8], is_async: bool| {
-        println!("> {}", String::from_utf8_lossy(form));
-        let res = if is_async {
-            conn.send(form)
-        } else {
-            conn.eval(form).map(|res| {
-                let (ch, data) = match res {
-                    Ok(data) => ('<', data),
-                    Err(data) => ('!', data),
-                };
-                println!("{ch} {}", String::from_utf8_lossy(&data));
-            })
-        };
-        if let Err(err) = res {
-            eprintln!("{argv0}: {err}");
+    let mut eval = |form: &[u8], is_async: bool, out: &Output| {
+        out.form(form);
+        match conn.eval_opt(form, !is_async) {
+            Ok(Some(res)) => out.response(res),
+            Ok(None) => (),
+            Err(err) => eprintln!("{argv0}: {err}"),
         }
     };
 
     // Process arguments.
     let mut found = false;
     let mut quiet = false;
+    let mut lines = false;
+    let mut quote = true;
+    let mut out = Output::default();
     let mut dash_dash = false;
     while let Some(arg) = args.next() {
         if dash_dash || !arg.as_encoded_bytes().starts_with(b"-") {
             found = true;
-            eval(arg.as_encoded_bytes(), quiet);
+            eval(arg.as_encoded_bytes(), quiet, &out);
         } else if arg == "-h" || arg == "--help" {
             found = false;
             break;
@@ -61,18 +83,67 @@ fn main() -> std::process::ExitCode {
             quiet = true;
         } else if arg == "-Q" || arg == "--no-quiet" {
             quiet = false;
+        } else if arg == "--no-echo" {
+            out.echo = false;
+        } else if arg == "--null" {
+            out.end = '\0';
+        } else if arg == "--prefix" {
+            let Some(prefix) = args.next().as_deref().and_then(parse_prefix)
+            else {
+                eprintln!(
+                    "{argv0}: --prefix requires a three-character argument"
+                );
+                return std::process::ExitCode::FAILURE;
+            };
+            out.prefix = prefix;
+        } else if arg == "--no-quote" {
+            quote = false;
+        } else if arg == "--lines" {
+            lines = true;
+        } else if arg == "-F" || arg == "--file" {
+            found = true;
+            let Some(path) = args.next() else {
+                eprintln!("{argv0}: -F requires an argument");
+                return std::process::ExitCode::FAILURE;
+            };
+            let data = match std::fs::read(&path) {
+                Ok(data) => data,
+                Err(err) => {
+                    eprintln!("{argv0}: {}: {err}", Path::new(&path).display());
+                    return std::process::ExitCode::FAILURE;
+                }
+            };
+            for form in split_forms(&data, lines) {
+                eval(form, quiet, &out);
+            }
+        } else if arg == "--watch" {
+            found = true;
+            let Some(path) = args.next() else {
+                eprintln!("{argv0}: --watch requires an argument");
+                return std::process::ExitCode::FAILURE;
+            };
+            let path = Path::new(&path);
+            let res = watch(
+                path,
+                |form| eval(form, quiet, &out),
+                |err| eprintln!("{argv0}: {}: {err}", path.display()),
+            );
+            if let Err(err) = res {
+                eprintln!("{argv0}: {}: {err}", path.display());
+                return std::process::ExitCode::FAILURE;
+            }
         } else if arg == "-" || arg == "--stdin" {
             found = true;
             let mut form = Vec::new();
             match std::io::stdin().read_to_end(&mut form) {
                 Ok(0) => continue,
-                Ok(_) => eval(form.as_slice(), quiet),
+                Ok(_) => eval(form.as_slice(), quiet, &out),
                 Err(err) => eprintln!("{argv0}: {err}"),
             }
         } else if let Some(func) = is_func_arg(&arg) {
             found = true;
-            if let Some(form) = build_form(func, args) {
-                eval(&form, quiet);
+            if let Some(form) = build_form(func, args, quote) {
+                eval(&form, quiet, &out);
                 break;
             } else {
                 eprintln!("{argv0}: -f requires an argument");
@@ -92,11 +163,28 @@ fn main() -> std::process::ExitCode {
     // If no forms were given as arguments, print help screen.
     if !found {
         println!(
-            "usage: {argv0} (-q | -Q | <form> | -)… [-f <func> <arg>…]
+            "usage: {argv0} [--display <display>]
+       [--no-echo] [--null] [--prefix <chars>]
+       (-q | -Q | --lines | <form> | - | -F <file> | --watch <fifo>)…
+       [--no-quote] [-f <func> <arg>…]
 Options:
+     --display    Connect to Sawfish running on <display>.  Must be the first
+                  argument.  Defaults to $DISPLAY or, if that’s not set, to
+                  contents of $XDG_CONFIG_HOME/sawfish-client/display.
+     --no-echo    Don’t print forms being sent.
+     --null       Terminate each output entry with NUL rather than newline.
+     --prefix     Use given three <chars> to prefix sent forms, successful
+                  responses and error responses.  Default is `><!`.
   -q --quiet      Don’t wait for server response after sending a form.
   -Q --no-quiet   Wait for a response after sending a form.
+     --no-quote   Don’t quote <arg>s of -f which aren’t symbols or numbers.
+     --lines      Treat each line of subsequent <file>s as a separate form.
   -  --stdin      Read form from standard input until EOF.
+  -F --file       Read form from <file>.
+     --watch      Read forms from named pipe <fifo> and evaluate each as soon
+                  as it’s complete.  Forms may span multiple lines.  When all
+                  writers close the pipe, it’s reopened and waits for the
+                  next writer.  Runs until reading the pipe fails.
   -f --func       Send `(<func> <arg>…)` form for evaluation.
   <form>          Send `<form>` for evaluation."
         )
@@ -105,6 +193,125 @@ Options:
 }
 
 
+/// Output formatting options.
+struct Output {
+    /// Whether to print forms being sent.
+    echo: bool,
+    /// Characters preceding sent form, successful response and error response.
+    prefix: [char; 3],
+    /// Character terminating each output entry.
+    end: char,
+}
+
+impl Default for Output {
+    fn default() -> Self {
+        Self { echo: true, prefix: ['>', '<', '!'], end: '\n' }
+    }
+}
+
+impl Output {
+    /// Prints a form if echo is enabled.
+    fn form(&self, form: &[u8]) {
+        if self.echo {
+            self.print(self.prefix[0], form);
+        }
+    }
+
+    /// Prints response to a form.
+    fn response(&self, res: sawfish_client::EvalResponse) {
+        let (ch, data) = match res {
+            Ok(data) => (self.prefix[1], data),
+            Err(data) => (self.prefix[2], data),
+        };
+        self.print(ch, &data);
+    }
+
+    fn print(&self, ch: char, data: &[u8]) {
+        print!("{ch} {}{}", String::from_utf8_lossy(data), self.end);
+    }
+}
+
+/// Parses `--prefix` argument which must consist of three characters.
+fn parse_prefix(arg: &OsStr) -> Option<[char; 3]> {
+    let mut chars = arg.to_str()?.chars();
+    let prefix = [chars.next()?, chars.next()?, chars.next()?];
+    chars.next().is_none().then_some(prefix)
+}
+
+/// Reads the default display from the configuration file.
+///
+/// The file is `sawfish-client/display` in `$XDG_CONFIG_HOME` directory (or
+/// `~/.config` if the variable isn’t set) and contains the display name.
+/// Returns `None` if the file doesn’t exist or can’t be read.
+fn config_display() -> Option<String> {
+    let dir = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| {
+            std::env::var_os("HOME")
+                .map(|home| Path::new(&home).join(".config"))
+        })?;
+    let path = dir.join("sawfish-client").join("display");
+    let display = std::fs::read_to_string(path).ok()?;
+    Some(display.trim().to_owned())
+}
+
+/// Splits contents of a file given with `-F`/`--file` into forms.
+///
+/// If `lines` is `true`, each non-blank line is a separate form.  Otherwise,
+/// the entire non-empty contents is a single form.
+fn split_forms(data: &[u8], lines: bool) -> Vec<&[u8]> {
+    if lines {
+        data.split(|&byte| byte == b'\n')
+            .filter(|line| !line.trim_ascii().is_empty())
+            .collect()
+    } else if data.is_empty() {
+        Vec::new()
+    } else {
+        vec![data]
+    }
+}
+
+/// Reads forms from a named pipe and calls `eval` for each as it arrives.
+///
+/// Forms are read with [`sexp::read_form`] so a form may span multiple lines
+/// and is evaluated once it’s complete.  Malformed input (e.g. an incomplete
+/// form when writer closes the pipe) is passed to `report`.  When all writers
+/// close the pipe, it’s reopened which blocks until another writer opens it.
+/// The connection to Sawfish is kept open throughout.  Returns only if
+/// opening or reading the pipe fails.  Fails with `InvalidInput` error if
+/// `path` isn’t a named pipe since a regular file would be re-read and its
+/// forms re-evaluated forever.
+fn watch(
+    path: &Path,
+    mut eval: impl FnMut(&[u8]),
+    mut report: impl FnMut(sexp::SexpError),
+) -> std::io::Result<()> {
+    use std::os::unix::fs::FileTypeExt;
+
+    if !std::fs::metadata(path)?.file_type().is_fifo() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "not a named pipe",
+        ));
+    }
+    loop {
+        let mut fifo = std::io::BufReader::new(std::fs::File::open(path)?);
+        loop {
+            match sexp::read_form(&mut fifo) {
+                Ok(Some(form)) => eval(&form),
+                Ok(None) => break,
+                Err(sexp::SexpError::Io(err)) => return Err(err),
+                Err(err @ sexp::SexpError::UnexpectedEof) => {
+                    report(err);
+                    break;
+                }
+                Err(err) => report(err),
+            }
+        }
+    }
+}
+
 /// Checks whether argument is `-f`/`--func` and if so, whether `<func>` is
 /// attached to it, as in `-fsystem-name` or `--func=system-name`.
 fn is_func_arg(arg: &OsStr) -> Option<Option<&OsStr>> {
@@ -126,8 +333,13 @@ fn is_func_arg(arg: &OsStr) -> Option<Option<&OsStr>> {
 ///
 /// `func` is the inner-value returned by `is_func_arg`.  Returns `None` if
 /// resulting form is empty, i.e. there are no arguments following `-f`/`--func`
-/// switch.
-fn build_form(func: Option<&OsStr>, args: std::env::ArgsOs) -> Option<Vec<u8>> {
+/// switch.  If `quote` is `true`, arguments (other than the function name) are
+/// quoted as strings as described in `quote_arg`.
+fn build_form(
+    func: Option<&OsStr>,
+    args: impl Iterator<Item = OsString>,
+    quote: bool,
+) -> Option<Vec<u8>> {
     let mut form = Vec::new();
     if let Some(func) = func {
         form.push(b'(');
@@ -135,9 +347,82 @@ fn build_form(func: Option<&OsStr>, args: std::env::ArgsOs) -> Option<Vec<u8>> {
     }
     for arg in args {
         form.push(b' ');
-        form.extend_from_slice(arg.as_encoded_bytes());
+        // If func is None, the first argument is the function name.
+        let quoted =
+            (quote && form.len() > 1).then(|| quote_arg(&arg)).flatten();
+        match quoted {
+            Some(arg) => form.extend_from_slice(arg.as_bytes()),
+            None => form.extend_from_slice(arg.as_encoded_bytes()),
+        }
     }
     form.push(b')');
     form[0] = b'(';
     (form.len() > 2).then_some(form)
 }
+
+/// Returns argument quoted as a Lisp string if it needs quoting.
+///
+/// Arguments which are empty or contain whitespace or special characters are
+/// quoted unless they look like a parenthesised sub-form, a string literal or
+/// a quoted, backquoted or `#`-prefixed form (e.g. `'sym` or `'(a b)`).
+/// Symbols and numbers are left untouched.  Returns `None` if the argument
+/// should be used as is.
+fn quote_arg(arg: &OsStr) -> Option<String> {
+    let arg = arg.to_str()?;
+    let special = |ch: char| ch.is_whitespace() || "()[]\"';`,#".contains(ch);
+    let quote = arg.is_empty() ||
+        (!arg.starts_with(['(', '"', '\'', '`', '#']) &&
+            arg.contains(special));
+    quote.then(|| sexp::quote_string(arg))
+}
+
+
+#[test]
+fn test_build_form() {
+    #[track_caller]
+    fn check(want: &str, func: Option<&str>, args: &[&str], quote: bool) {
+        let args = args.iter().map(OsString::from);
+        let got = build_form(func.map(OsStr::new), args, quote).unwrap();
+        assert_eq!(want, String::from_utf8(got).unwrap());
+    }
+
+    let args = ["display-message", "hello world"];
+    check(r#"(display-message "hello world")"#, None, &args, true);
+    check("(display-message hello world)", None, &args, false);
+    check(
+        r#"(display-message "hello world")"#,
+        Some("display-message"),
+        &args[1..],
+        true,
+    );
+
+    check("(+ 1 -2.5)", Some("+"), &["1", "-2.5"], true);
+    check("(foo t nil sym)", Some("foo"), &["t", "nil", "sym"], true);
+    check(r#"(foo "" "a\"b")"#, Some("foo"), &["", r#"a"b"#], true);
+    check(
+        r#"(foo (bar 1) "already quoted")"#,
+        Some("foo"),
+        &["(bar 1)", r#""already quoted""#],
+        true,
+    );
+    check(
+        "(foo 'sym '(a b) `x #t)",
+        Some("foo"),
+        &["'sym", "'(a b)", "`x", "#t"],
+        true,
+    );
+}
+
+#[test]
+fn test_watch_rejects_regular_file() {
+    let path = std::env::temp_dir()
+        .join(format!("sawfish-client-watch-{}", std::process::id()));
+    std::fs::write(&path, "(system-name)").unwrap();
+    let res = watch(
+        &path,
+        |form| panic!("Unexpected form: {form:?}"),
+        |err| panic!("Unexpected error: {err}"),
+    );
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(std::io::ErrorKind::InvalidInput, res.unwrap_err().kind());
+}
@@ -0,0 +1,60 @@
+// Health check probe using the sawfish-client library.
+// © 2025 by Michał Nazarewicz <mina86@mina86.com>
+
+use std::path::PathBuf;
+
+/// Checks whether the Sawfish server is reachable.
+///
+/// Connects to the server, pings it and prints the transport used and path of
+/// the socket.  Exits with status zero if the server is reachable and non-zero
+/// otherwise which makes it suitable as a monitoring probe.
+///
+/// ```shell
+/// $ cargo run -p healthcheck
+/// transport: unix
+/// path: /tmp/.sawfish-user/darkstar.example.net:0.0
+/// status: ok
+/// ```
+fn main() -> std::process::ExitCode {
+    let mut args = std::env::args_os();
+    let argv0 = PathBuf::from(args.next().unwrap());
+    let argv0 = argv0.display();
+
+    let display = match args.next().map(|arg| arg.into_string()) {
+        None => None,
+        Some(Ok(display)) if args.next().is_none() => Some(display),
+        _ => {
+            eprintln!("usage: {argv0} [<display>]");
+            return std::process::ExitCode::from(2);
+        }
+    };
+
+    let (mut client, path) =
+        match sawfish_client::Client::open_verbose(display.as_deref()) {
+            Ok(res) => res,
+            Err(err) => {
+                eprintln!("{argv0}: {err}");
+                println!("status: unreachable");
+                return std::process::ExitCode::FAILURE;
+            }
+        };
+
+    let transport = match client.transport() {
+        sawfish_client::Transport::Unix => "unix",
+        sawfish_client::Transport::X11 => "x11",
+    };
+    println!("transport: {transport}");
+    println!("path: {}", path.display());
+
+    match client.ping() {
+        Ok(()) => {
+            println!("status: ok");
+            std::process::ExitCode::SUCCESS
+        }
+        Err(err) => {
+            eprintln!("{argv0}: {err}");
+            println!("status: not responding");
+            std::process::ExitCode::FAILURE
+        }
+    }
+}
@@ -1,7 +1,7 @@
 // Example usage of the sawfish-client library using Tokio async executor.
 // © 2025 by Michał Nazarewicz <mina86@mina86.com>
 
-use std::ffi::OsStr;
+use std::ffi::{OsStr, OsString};
 use std::io::Read;
 use std::path::{Path, PathBuf};
 
@@ -30,11 +30,15 @@ async fn main() -> std::process::ExitCode {
     // Process arguments.
     let mut found = false;
     let mut quiet = false;
+    let mut lines = false;
+    let mut quote = true;
+    let mut out = Output::default();
     let mut dash_dash = false;
     while let Some(arg) = args.next() {
         if dash_dash || !arg.as_encoded_bytes().starts_with(b"-") {
             found = true;
-            eval(&argv0, &mut client, arg.as_encoded_bytes(), quiet).await;
+            eval(&argv0, &mut client, arg.as_encoded_bytes(), quiet, &out)
+                .await;
         } else if arg == "-h" || arg == "--help" {
             found = false;
             break;
@@ -42,18 +46,54 @@ async fn main() -> std::process::ExitCode {
             quiet = true;
         } else if arg == "-Q" || arg == "--no-quiet" {
             quiet = false;
+        } else if arg == "--no-echo" {
+            out.echo = false;
+        } else if arg == "--null" {
+            out.end = '\0';
+        } else if arg == "--prefix" {
+            let Some(prefix) = args.next().as_deref().and_then(parse_prefix)
+            else {
+                eprintln!(
+                    "{argv0}: --prefix requires a three-character argument"
+                );
+                return std::process::ExitCode::FAILURE;
+            };
+            out.prefix = prefix;
+        } else if arg == "--no-quote" {
+            quote = false;
+        } else if arg == "--lines" {
+            lines = true;
+        } else if arg == "-F" || arg == "--file" {
+            found = true;
+            let Some(path) = args.next() else {
+                eprintln!("{argv0}: -F requires an argument");
+                return std::process::ExitCode::FAILURE;
+            };
+            let data = match std::fs::read(&path) {
+                Ok(data) => data,
+                Err(err) => {
+                    eprintln!("{argv0}: {}: {err}", Path::new(&path).display());
+                    return std::process::ExitCode::FAILURE;
+                }
+            };
+            for form in split_forms(&data, lines) {
+                eval(&argv0, &mut client, form, quiet, &out).await;
+            }
         } else if arg == "-" || arg == "--stdin" {
             found = true;
             let mut form = Vec::new();
             match std::io::stdin().read_to_end(&mut form) {
                 Err(err) => eprintln!("{argv0}: {err}"),
                 Ok(0) => continue,
-                _ => eval(&argv0, &mut client, form.as_slice(), quiet).await,
+                _ => {
+                    eval(&argv0, &mut client, form.as_slice(), quiet, &out)
+                        .await
+                }
             }
         } else if let Some(func) = is_func_arg(&arg) {
             found = true;
-            if let Some(form) = build_form(func, args) {
-                eval(&argv0, &mut client, &form, quiet).await;
+            if let Some(form) = build_form(func, args, quote) {
+                eval(&argv0, &mut client, &form, quiet, &out).await;
                 break;
             } else {
                 eprintln!("{argv0}: -f requires an argument");
@@ -73,11 +113,20 @@ async fn main() -> std::process::ExitCode {
     // If no forms were given as arguments, print help screen.
     if !found {
         println!(
-            "usage: {argv0} (-q | -Q | <form> | -)… [-f <func> <arg>…]
+            "usage: {argv0} [--no-echo] [--null] [--prefix <chars>]
+       (-q | -Q | --lines | <form> | - | -F <file>)…
+       [--no-quote] [-f <func> <arg>…]
 Options:
+     --no-echo    Don’t print forms being sent.
+     --null       Terminate each output entry with NUL rather than newline.
+     --prefix     Use given three <chars> to prefix sent forms, successful
+                  responses and error responses.  Default is `><!`.
   -q --quiet      Don’t wait for server response after sending a form.
   -Q --no-quiet   Wait for a response after sending a form.
+     --no-quote   Don’t quote <arg>s of -f which aren’t symbols or numbers.
+     --lines      Treat each line of subsequent <file>s as a separate form.
   -  --stdin      Read form from standard input until EOF.
+  -F --file       Read form from <file>.
   -f --func       Send `(<func> <arg>…)` form for evaluation.
   <form>          Send `<form>` for evaluation."
         )
@@ -91,18 +140,13 @@ async fn eval(
     client: &mut sawfish_client::TokioClient,
     form: &[u8],
     is_async: bool,
+    out: &Output,
 ) {
-    println!("> {}", String::from_utf8_lossy(form));
+    out.form(form);
     let res = if is_async {
         client.send(form).await
     } else {
-        client.eval(form).await.map(|res| {
-            let (ch, data) = match res {
-                Ok(data) => ('<', data),
-                Err(data) => ('!', data),
-            };
-            println!("{ch} {}", String::from_utf8_lossy(&data));
-        })
+        client.eval(form).await.map(|res| out.response(res))
     };
     if let Err(err) = res {
         eprintln!("{argv0}: {err}");
@@ -110,6 +154,67 @@ async fn eval(
 }
 
 
+/// Output formatting options.
+struct Output {
+    /// Whether to print forms being sent.
+    echo: bool,
+    /// Characters preceding sent form, successful response and error response.
+    prefix: [char; 3],
+    /// Character terminating each output entry.
+    end: char,
+}
+
+impl Default for Output {
+    fn default() -> Self {
+        Self { echo: true, prefix: ['>', '<', '!'], end: '\n' }
+    }
+}
+
+impl Output {
+    /// Prints a form if echo is enabled.
+    fn form(&self, form: &[u8]) {
+        if self.echo {
+            self.print(self.prefix[0], form);
+        }
+    }
+
+    /// Prints response to a form.
+    fn response(&self, res: sawfish_client::EvalResponse) {
+        let (ch, data) = match res {
+            Ok(data) => (self.prefix[1], data),
+            Err(data) => (self.prefix[2], data),
+        };
+        self.print(ch, &data);
+    }
+
+    fn print(&self, ch: char, data: &[u8]) {
+        print!("{ch} {}{}", String::from_utf8_lossy(data), self.end);
+    }
+}
+
+/// Parses `--prefix` argument which must consist of three characters.
+fn parse_prefix(arg: &OsStr) -> Option<[char; 3]> {
+    let mut chars = arg.to_str()?.chars();
+    let prefix = [chars.next()?, chars.next()?, chars.next()?];
+    chars.next().is_none().then_some(prefix)
+}
+
+/// Splits contents of a file given with `-F`/`--file` into forms.
+///
+/// If `lines` is `true`, each non-blank line is a separate form.  Otherwise,
+/// the entire non-empty contents is a single form.
+fn split_forms(data: &[u8], lines: bool) -> Vec<&[u8]> {
+    if lines {
+        data.split(|&byte| byte == b'\n')
+            .filter(|line| !line.trim_ascii().is_empty())
+            .collect()
+    } else if data.is_empty() {
+        Vec::new()
+    } else {
+        vec![data]
+    }
+}
+
 /// Checks whether argument is `-f`/`--func` and if so, whether `<func>` is
 /// attached to it, as in `-fsystem-name` or `--func=system-name`.
 fn is_func_arg(arg: &OsStr) -> Option<Option<&OsStr>> {
@@ -131,8 +236,13 @@ fn is_func_arg(arg: &OsStr) -> Option<Option<&OsStr>> {
 ///
 /// `func` is the inner-value returned by `is_func_arg`.  Returns `None` if
 /// resulting form is empty, i.e. there are no arguments following `-f`/`--func`
-/// switch.
-fn build_form(func: Option<&OsStr>, args: std::env::ArgsOs) -> Option<Vec<u8>> {
+/// switch.  If `quote` is `true`, arguments (other than the function name) are
+/// quoted as strings as described in `quote_arg`.
+fn build_form(
+    func: Option<&OsStr>,
+    args: impl Iterator<Item = OsString>,
+    quote: bool,
+) -> Option<Vec<u8>> {
     let mut form = Vec::new();
     if let Some(func) = func {
         form.push(b'(');
@@ -140,9 +250,68 @@ fn build_form(func: Option<&OsStr>, args: std::env::ArgsOs) -> Option<Vec<u8>> {
     }
     for arg in args {
         form.push(b' ');
-        form.extend_from_slice(arg.as_encoded_bytes());
+        // If func is None, the first argument is the function name.
+        let quoted =
+            (quote && form.len() > 1).then(|| quote_arg(&arg)).flatten();
+        match quoted {
+            Some(arg) => form.extend_from_slice(arg.as_bytes()),
+            None => form.extend_from_slice(arg.as_encoded_bytes()),
+        }
     }
     form.push(b')');
     form[0] = b'(';
     (form.len() > 2).then_some(form)
 }
+
+/// Returns argument quoted as a Lisp string if it needs quoting.
+///
+/// Arguments which are empty or contain whitespace or special characters are
+/// quoted unless they look like a parenthesised sub-form, a string literal or
+/// a quoted, backquoted or `#`-prefixed form (e.g. `'sym` or `'(a b)`).
+/// Symbols and numbers are left untouched.  Returns `None` if the argument
+/// should be used as is.
+fn quote_arg(arg: &OsStr) -> Option<String> {
+    let arg = arg.to_str()?;
+    let special = |ch: char| ch.is_whitespace() || "()[]\"';`,#".contains(ch);
+    let quote = arg.is_empty() ||
+        (!arg.starts_with(['(', '"', '\'', '`', '#']) &&
+            arg.contains(special));
+    quote.then(|| sawfish_client::sexp::quote_string(arg))
+}
+
+
+#[test]
+fn test_build_form() {
+    #[track_caller]
+    fn check(want: &str, func: Option<&str>, args: &[&str], quote: bool) {
+        let args = args.iter().map(OsString::from);
+        let got = build_form(func.map(OsStr::new), args, quote).unwrap();
+        assert_eq!(want, String::from_utf8(got).unwrap());
+    }
+
+    let args = ["display-message", "hello world"];
+    check(r#"(display-message "hello world")"#, None, &args, true);
+    check("(display-message hello world)", None, &args, false);
+    check(
+        r#"(display-message "hello world")"#,
+        Some("display-message"),
+        &args[1..],
+        true,
+    );
+
+    check("(+ 1 -2.5)", Some("+"), &["1", "-2.5"], true);
+    check("(foo t nil sym)", Some("foo"), &["t", "nil", "sym"], true);
+    check(r#"(foo "" "a\"b")"#, Some("foo"), &["", r#"a"b"#], true);
+    check(
+        r#"(foo (bar 1) "already quoted")"#,
+        Some("foo"),
+        &["(bar 1)", r#""already quoted""#],
+        true,
+    );
+    check(
+        "(foo 'sym '(a b) `x #t)",
+        Some("foo"),
+        &["'sym", "'(a b)", "`x", "#t"],
+        true,
+    );
+}